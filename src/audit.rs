@@ -0,0 +1,190 @@
+use anyhow::{ensure, Context, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::config::{get_config_path, get_current_exec_path, Config};
+
+/// the prev hash of the first record
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// the audit entry, the hash of the entry json chains the records
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// rfc3339 time
+    pub time: String,
+    /// the auth user or the os user
+    pub who: String,
+    /// push, create, remove, rename, copy
+    pub action: String,
+    pub path: String,
+    /// the rename or copy target
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub overwrite: bool,
+    /// the previous record hash
+    pub prev: String,
+}
+
+impl AuditEntry {
+    /// blake3 of the entry json
+    #[inline]
+    fn hash(&self) -> Result<String> {
+        Ok(blake3::hash(&serde_json::to_vec(self)?)
+            .to_hex()
+            .to_string())
+    }
+}
+
+/// the audit log line
+#[derive(Serialize, Deserialize)]
+pub struct AuditRecord {
+    #[serde(flatten)]
+    pub entry: AuditEntry,
+    pub hash: String,
+}
+
+/// append only hash chained audit log, json line per record
+pub struct AuditLog {
+    path: PathBuf,
+    who: String,
+    /// the last record hash
+    last: Mutex<String>,
+}
+
+/// the audit log file, default audit.log next to the config file
+#[inline]
+pub fn audit_path(config: &Config) -> Result<PathBuf> {
+    if let Some(ref path) = config.audit.path {
+        return Ok(path.clone());
+    }
+    let dir = match get_config_path() {
+        Ok(config) => config.parent().map(Path::to_path_buf).unwrap_or_default(),
+        Err(_) => get_current_exec_path()?,
+    };
+    Ok(dir.join("audit.log"))
+}
+
+/// the last record hash of the audit log, genesis if empty
+#[inline]
+fn last_hash(path: &Path) -> Result<String> {
+    if !path.exists() {
+        return Ok(GENESIS.to_string());
+    }
+    let mut last = None;
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            last = Some(line);
+        }
+    }
+    match last {
+        Some(line) => {
+            let record: AuditRecord = serde_json::from_str(&line)
+                .with_context(|| format!("audit log:{} last record error", path.display()))?;
+            Ok(record.hash)
+        }
+        None => Ok(GENESIS.to_string()),
+    }
+}
+
+impl AuditLog {
+    /// open the audit log, none if not enabled
+    #[inline]
+    pub fn open(config: &Config) -> Result<Option<Self>> {
+        if !config.audit.enabled {
+            return Ok(None);
+        }
+        let path = audit_path(config)?;
+        let last = last_hash(&path)?;
+        let who = config
+            .auth
+            .as_ref()
+            .and_then(|auth| auth.user.clone())
+            .or_else(|| std::env::var("USER").ok())
+            .or_else(|| std::env::var("USERNAME").ok())
+            .unwrap_or_else(|| "unknown".to_string());
+        log::debug!("audit log:{} who:{who}", path.display());
+        Ok(Some(Self {
+            path,
+            who,
+            last: Mutex::new(last),
+        }))
+    }
+
+    /// append the record
+    #[inline]
+    pub fn append(
+        &self,
+        action: &str,
+        path: &str,
+        to: Option<&str>,
+        overwrite: bool,
+    ) -> Result<()> {
+        let mut last = self.last.lock().unwrap();
+        let entry = AuditEntry {
+            time: Local::now().to_rfc3339(),
+            who: self.who.clone(),
+            action: action.to_string(),
+            path: path.to_string(),
+            to: to.map(ToString::to_string),
+            overwrite,
+            prev: last.clone(),
+        };
+        let hash = entry.hash()?;
+        let mut line = serde_json::to_string(&AuditRecord {
+            entry,
+            hash: hash.clone(),
+        })?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("open audit log:{} error", self.path.display()))?
+            .write_all(line.as_bytes())?;
+        *last = hash;
+        Ok(())
+    }
+
+    /// append the record, the audit error not fail the operation
+    #[inline]
+    pub fn record(&self, action: &str, path: &str, to: Option<&str>, overwrite: bool) {
+        if let Err(err) = self.append(action, path, to, overwrite) {
+            log::warn!("write audit log error:{err:#}");
+        }
+    }
+}
+
+/// verify the hash chain of the audit log, return the record count
+#[inline]
+pub fn verify(path: &Path) -> Result<usize> {
+    let file =
+        File::open(path).with_context(|| format!("open audit log:{} error", path.display()))?;
+    let mut prev = GENESIS.to_string();
+    let mut count = 0;
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let number = index + 1;
+        let record: AuditRecord = serde_json::from_str(&line)
+            .with_context(|| format!("audit log line:{number} parse error"))?;
+        ensure!(
+            record.entry.prev == prev,
+            "audit log line:{number} chain broken, prev hash mismatch"
+        );
+        ensure!(
+            record.entry.hash()? == record.hash,
+            "audit log line:{number} record modified, hash mismatch"
+        );
+        prev = record.hash;
+        count += 1;
+    }
+    Ok(count)
+}
@@ -0,0 +1,180 @@
+use anyhow::{bail, Context, Result};
+use netxclient::client::NetxClientArcDef;
+use netxclient::prelude::*;
+
+use crate::config::{AuthConfig, Config};
+use crate::interface_server::*;
+use crate::redact::add_secret;
+use crate::tls;
+
+/// os keyring service name
+const KEYRING_SERVICE: &str = "fsc";
+
+/// keyring entry name of file encrypt key
+pub const ENCRYPT_KEY_ENTRY: &str = "encrypt_key";
+
+/// keyring entry name of server token
+#[inline]
+fn token_entry(addr: &str) -> String {
+    format!("token@{addr}")
+}
+
+/// keyring entry name of user password
+#[inline]
+fn password_entry(user: &str, addr: &str) -> String {
+    format!("{user}@{addr}")
+}
+
+/// keyring entry name of private key passphrase
+#[inline]
+pub fn passphrase_entry(key: &std::path::Path) -> String {
+    format!("passphrase@{}", key.display())
+}
+
+/// get secret from os keyring, return none if not found
+#[inline]
+pub fn keyring_get(name: &str) -> Option<String> {
+    match keyring::Entry::new(KEYRING_SERVICE, name).and_then(|entry| entry.get_password()) {
+        Ok(secret) => Some(secret),
+        Err(keyring::Error::NoEntry) => None,
+        Err(err) => {
+            log::debug!("keyring get {name} error:{err}");
+            None
+        }
+    }
+}
+
+/// save secret to os keyring
+#[inline]
+fn keyring_set(name: &str, secret: &str) -> Result<()> {
+    keyring::Entry::new(KEYRING_SERVICE, name)?
+        .set_password(secret)
+        .with_context(|| format!("keyring save {name} error"))
+}
+
+/// delete secret from os keyring, not found is ok
+#[inline]
+fn keyring_delete(name: &str) -> Result<bool> {
+    match keyring::Entry::new(KEYRING_SERVICE, name)?.delete_password() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(err) => Err(err).with_context(|| format!("keyring delete {name} error")),
+    }
+}
+
+/// save token or password to os keyring
+/// if the config file set token save it, if set user prompt password, otherwise prompt token,
+/// the --token flag is rejected by the caller, not keep the token in the shell history
+#[inline]
+pub fn login(config: &Config, key_passphrase: bool, encrypt_key: bool) -> Result<()> {
+    let addr = &config.server.addr;
+    if encrypt_key {
+        let key = rpassword::prompt_password("encrypt key(64 hex chars): ")?;
+        crate::crypto::Key::from_hex(&key)?;
+        keyring_set(ENCRYPT_KEY_ENTRY, key.trim())?;
+        println!("save encrypt key");
+        return Ok(());
+    }
+    if key_passphrase {
+        let key = config
+            .tls
+            .as_ref()
+            .and_then(|tls| tls.key.as_ref())
+            .context("tls key not set")?;
+        let key = tls::resolve_path(key)?;
+        let passphrase = rpassword::prompt_password("private key passphrase: ")?;
+        keyring_set(&passphrase_entry(&key), &passphrase)?;
+        println!("save {} passphrase", key.display());
+        return Ok(());
+    }
+
+    let auth = config.auth.clone().unwrap_or_default();
+    if let Some(token) = auth.token {
+        keyring_set(&token_entry(addr), &token)?;
+        println!("save {addr} token");
+    } else if let Some(user) = auth.user {
+        let password = rpassword::prompt_password(format!("{user} password: "))?;
+        keyring_set(&password_entry(&user, addr), &password)?;
+        println!("save {user}@{addr} password");
+    } else {
+        let token = rpassword::prompt_password(format!("{addr} token: "))?;
+        keyring_set(&token_entry(addr), &token)?;
+        println!("save {addr} token");
+    }
+    Ok(())
+}
+
+/// delete token and password from os keyring
+#[inline]
+pub fn logout(config: &Config) -> Result<()> {
+    let addr = &config.server.addr;
+    if keyring_delete(&token_entry(addr))? {
+        println!("delete {addr} token");
+    }
+    if let Some(user) = config.auth.as_ref().and_then(|auth| auth.user.as_ref()) {
+        if keyring_delete(&password_entry(user, addr))? {
+            println!("delete {user}@{addr} password");
+        }
+    }
+    Ok(())
+}
+
+/// read password from env FS_PASSWORD, then os keyring, if not found prompt input
+#[inline]
+pub fn read_password(user: &str, addr: &str) -> Result<String> {
+    if let Ok(password) = std::env::var("FS_PASSWORD") {
+        return Ok(password);
+    }
+    if let Some(password) = keyring_get(&password_entry(user, addr)) {
+        return Ok(password);
+    }
+    Ok(rpassword::prompt_password(format!("{user} password: "))?)
+}
+
+/// read custom credential from env FS_CREDENTIAL
+#[inline]
+fn read_credential(kind: &str) -> Result<String> {
+    match std::env::var("FS_CREDENTIAL") {
+        Ok(credential) => Ok(credential),
+        Err(_) => {
+            bail!("auth kind:{kind} credential not set, set auth.credential or env FS_CREDENTIAL")
+        }
+    }
+}
+
+/// authenticate after connect, before any file rpc
+/// custom credential first, then token, then user and password
+/// addr is the config server addr, used to find the keyring secret
+#[inline]
+pub async fn authenticate(client: &NetxClientArcDef, auth: AuthConfig, addr: &str) -> Result<()> {
+    let server = impl_struct!(client=>IFileStoreService);
+    if let Some(kind) = auth.kind {
+        let credential = match auth.credential {
+            Some(credential) => credential,
+            None => read_credential(&kind)?,
+        };
+        add_secret(&credential);
+        server
+            .on_connect(&kind, &credential)
+            .await
+            .with_context(|| format!("server {addr} on connect auth kind:{kind} error"))?;
+        return Ok(());
+    }
+
+    let token = auth.token.or_else(|| keyring_get(&token_entry(addr)));
+    if let Some(token) = token {
+        add_secret(&token);
+        server
+            .authenticate(&token)
+            .await
+            .context("authenticate error")?;
+    } else if let Some(user) = auth.user {
+        let password = read_password(&user, addr)?;
+        add_secret(&password);
+        server
+            .login(&user, &password)
+            .await
+            .with_context(|| format!("user:{user} login error"))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,246 @@
+use anyhow::{bail, ensure, Context, Result};
+use clap::Parser;
+use std::io::Read;
+use std::path::Path;
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use fsc::checksum::PullVerify;
+use fsc::clap_struct::{BatchLine, ImageArgs, ImageCommands, Opt};
+use fsc::client::TransferOptions;
+use fsc::config::DefaultsConfig;
+use fsc::crypto::{AgeIdentities, AgeRecipients, Key};
+use fsc::output::{print_json, TransferSummary};
+use fsc::FileStoreClient;
+
+/// batch script runner, run the script commands over one connection
+pub struct Batch<'a> {
+    pub client: &'a FileStoreClient,
+    pub defaults: &'a DefaultsConfig,
+    pub cancel: CancellationToken,
+    /// push --encrypt and pull decrypt key
+    pub encrypt_key: Option<Key>,
+    /// pull age decrypt identities
+    pub age_identities: AgeIdentities,
+    pub json: bool,
+    pub quiet: bool,
+    pub yes: bool,
+}
+
+impl Batch<'_> {
+    /// run the script, `-` read from stdin,
+    /// skip the empty and `#` comment line, abort at the first error unless keep going
+    #[inline]
+    pub async fn run(&self, script: &Path, keep_going: bool) -> Result<()> {
+        let script = if script == Path::new("-") {
+            let mut script = String::new();
+            std::io::stdin().read_to_string(&mut script)?;
+            script
+        } else {
+            std::fs::read_to_string(script)
+                .with_context(|| format!("read batch script:{} error", script.display()))?
+        };
+
+        let mut failed = 0;
+        for (index, line) in script.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            ensure!(!self.cancel.is_cancelled(), "batch cancelled");
+            log::info!("batch line {}: {line}", index + 1);
+            if let Err(err) = self.execute(line).await {
+                let err = err.context(format!("batch line {}: {line}", index + 1));
+                if !keep_going {
+                    return Err(err);
+                }
+                log::error!("{err:#}");
+                failed += 1;
+            }
+        }
+        ensure!(failed == 0, "batch {failed} commands failed");
+        Ok(())
+    }
+
+    /// parse and execute the line command
+    #[inline]
+    async fn execute(&self, line: &str) -> Result<()> {
+        let args = shlex::split(line).context("invalid quoting")?;
+        let BatchLine { command } = BatchLine::try_parse_from(args)?;
+        let start = Instant::now();
+        let (action, result) = match command {
+            Opt::Push {
+                dir,
+                file,
+                name,
+                files_from,
+                r#async,
+                no_async,
+                block,
+                overwrite,
+                no_overwrite,
+                on_conflict,
+                jobs,
+                encrypt,
+                encrypt_to,
+                parallel,
+                remove_source,
+                auto_rename,
+                sparse,
+                rename_prefix,
+                // the batch is connected
+                queue_offline: _,
+            } => {
+                let key = crate::push_encrypt_key(self.encrypt_key.as_ref(), encrypt)?;
+                let options = self
+                    .options(r#async, block, overwrite)
+                    .with_no_async(no_async)
+                    .with_no_overwrite(no_overwrite)
+                    .with_on_conflict(on_conflict)
+                    .with_jobs(jobs)
+                    .with_encrypt_key(key)
+                    .with_encrypt_to(AgeRecipients::parse(&encrypt_to)?)
+                    .with_parallel(parallel)
+                    .with_remove_source(remove_source)
+                    .with_auto_rename(auto_rename)
+                    .with_sparse(sparse)
+                    .with_rename_prefix(rename_prefix);
+                let result = match files_from {
+                    Some(list) => crate::push_files_from(self.client, dir, &list, options).await,
+                    None => crate::push_args(self.client, dir, file, name, options).await,
+                };
+                ("push", result)
+            }
+            Opt::Pull {
+                file,
+                save,
+                r#async,
+                no_async,
+                block,
+                overwrite,
+                no_overwrite,
+                on_conflict,
+                jobs,
+                checksum,
+                verify,
+                no_verify,
+                check,
+                sparse,
+                rename_prefix,
+            } => {
+                let verify = if no_verify { PullVerify::None } else { verify };
+                let options = self
+                    .options(r#async, block, overwrite)
+                    .with_no_async(no_async)
+                    .with_no_overwrite(no_overwrite)
+                    .with_on_conflict(on_conflict)
+                    .with_jobs(jobs)
+                    .with_encrypt_key(self.encrypt_key.clone())
+                    .with_age_identities(self.age_identities.clone())
+                    .with_checksum(checksum)
+                    .with_verify(verify)
+                    .with_sparse(sparse)
+                    .with_rename_prefix(rename_prefix);
+                (
+                    "pull",
+                    crate::pull_pattern(self.client, file, save, check, options).await,
+                )
+            }
+            Opt::Image(ImageArgs {
+                command:
+                    ImageCommands::Push {
+                        dir,
+                        path,
+                        r#async,
+                        no_async,
+                        block,
+                        overwrite,
+                        no_overwrite,
+                        on_conflict,
+                        jobs,
+                        encrypt,
+                        encrypt_to,
+                        manifest,
+                        sign_key,
+                        resume,
+                        dedupe,
+                        flatten,
+                        remove_source,
+                        rename_prefix,
+                    },
+            }) => {
+                let key = crate::push_encrypt_key(self.encrypt_key.as_ref(), encrypt)?;
+                let options = self
+                    .options(r#async, block, overwrite)
+                    .with_no_async(no_async)
+                    .with_no_overwrite(no_overwrite)
+                    .with_on_conflict(on_conflict)
+                    .with_jobs(jobs)
+                    .with_encrypt_key(key)
+                    .with_encrypt_to(AgeRecipients::parse(&encrypt_to)?)
+                    .with_remove_source(remove_source)
+                    .with_rename_prefix(rename_prefix);
+                let result = crate::push_image(
+                    self.client,
+                    dir,
+                    path,
+                    options,
+                    manifest,
+                    sign_key,
+                    resume,
+                    dedupe,
+                    flatten,
+                )
+                .await;
+                ("image push", result)
+            }
+            Opt::ShowDir {
+                dir,
+                format,
+                display,
+            } => return crate::show_dir(self.client.netx(), dir, format, display, self.json).await,
+            Opt::Info {
+                file,
+                hashes,
+                sha512,
+                display,
+            } => {
+                let client = self.client.netx();
+                return crate::show_file_info(client, file, hashes, sha512, display, self.json)
+                    .await;
+            }
+            Opt::Rm { path } => return crate::remove(self.client, path, self.yes).await,
+            _ => bail!("command not supported in batch"),
+        };
+        let summary = match result {
+            Ok(summary) => summary,
+            Err(err) => {
+                let summary = TransferSummary::new(action, String::new(), String::new())
+                    .with_start(start)
+                    .fail(1, format!("{err:#}"));
+                crate::history::record(&summary);
+                return Err(err);
+            }
+        };
+        crate::history::record(&summary);
+
+        if self.json {
+            print_json(&summary)?;
+        } else if !self.quiet {
+            summary.print();
+        }
+        ensure!(
+            summary.success,
+            "{}",
+            summary.error.as_deref().unwrap_or("transfer failed")
+        );
+        Ok(())
+    }
+
+    /// the line flags, if not set use the config defaults
+    #[inline]
+    fn options(&self, r#async: bool, block: Option<usize>, overwrite: bool) -> TransferOptions {
+        TransferOptions::new(self.defaults, r#async, block, overwrite)
+            .with_cancel(self.cancel.clone())
+    }
+}
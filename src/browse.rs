@@ -0,0 +1,413 @@
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use netxclient::prelude::*;
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph};
+use std::io::Stdout;
+use std::path::{Path, PathBuf};
+
+use fsc::clap_struct::DisplayArgs;
+use fsc::client::TransferOptions;
+use fsc::config::DefaultsConfig;
+use fsc::interface_server::*;
+use fsc::FileStoreClient;
+
+type Tui = Terminal<CrosstermBackend<Stdout>>;
+
+/// browser input mode
+enum Mode {
+    Normal,
+    /// show the selected file info
+    Info(Vec<String>),
+    /// input the new name of the selected entry
+    Rename(String),
+    /// confirm delete the selected entry, with the count and size preview
+    Delete(String),
+}
+
+/// interactive remote directory browser
+pub struct Browser<'a> {
+    client: &'a FileStoreClient,
+    defaults: DefaultsConfig,
+    display: DisplayArgs,
+    dir: PathBuf,
+    entries: Vec<Entry>,
+    state: ListState,
+    mode: Mode,
+    status: String,
+}
+
+impl<'a> Browser<'a> {
+    #[inline]
+    pub fn new(client: &'a FileStoreClient, defaults: DefaultsConfig, dir: PathBuf) -> Self {
+        Self {
+            client,
+            defaults,
+            display: DisplayArgs::default(),
+            dir,
+            entries: vec![],
+            state: ListState::default(),
+            mode: Mode::Normal,
+            status: String::new(),
+        }
+    }
+
+    /// run the browser until quit
+    #[inline]
+    pub async fn run(mut self) -> Result<()> {
+        self.load().await?;
+        let mut terminal = enter()?;
+        let result = self.event_loop(&mut terminal).await;
+        leave(&mut terminal)?;
+        result
+    }
+
+    #[inline]
+    async fn event_loop(&mut self, terminal: &mut Tui) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = tokio::task::block_in_place(event::read)? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match std::mem::replace(&mut self.mode, Mode::Normal) {
+                Mode::Normal => match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Up | KeyCode::Char('k') => self.select(-1),
+                    KeyCode::Down | KeyCode::Char('j') => self.select(1),
+                    KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => self.open().await,
+                    KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h') => self.parent().await,
+                    KeyCode::Char('i') => self.info().await,
+                    KeyCode::Char('p') => {
+                        leave(terminal)?;
+                        self.pull().await;
+                        *terminal = enter()?;
+                    }
+                    KeyCode::Char('d') => self.confirm_delete().await,
+                    KeyCode::Char('r') => {
+                        if let Some(entry) = self.selected() {
+                            self.mode = Mode::Rename(entry.name.clone());
+                        }
+                    }
+                    _ => {}
+                },
+                Mode::Info(_) => {}
+                Mode::Delete(_) => {
+                    if let KeyCode::Char('y') | KeyCode::Char('Y') = key.code {
+                        self.delete().await;
+                    } else {
+                        self.status = "delete cancelled".to_string();
+                    }
+                }
+                Mode::Rename(mut name) => match key.code {
+                    KeyCode::Enter => self.rename(&name).await,
+                    KeyCode::Esc => self.status = "rename cancelled".to_string(),
+                    KeyCode::Backspace => {
+                        name.pop();
+                        self.mode = Mode::Rename(name);
+                    }
+                    KeyCode::Char(c) => {
+                        name.push(c);
+                        self.mode = Mode::Rename(name);
+                    }
+                    _ => self.mode = Mode::Rename(name),
+                },
+            }
+        }
+    }
+
+    /// remote path of the entry name in current dir
+    #[inline]
+    fn remote_path(&self, name: &str) -> PathBuf {
+        PathBuf::from(self.dir.join(name).to_string_lossy().replace('\\', "/"))
+    }
+
+    #[inline]
+    fn selected(&self) -> Option<&Entry> {
+        self.state
+            .selected()
+            .and_then(|index| self.entries.get(index))
+    }
+
+    #[inline]
+    fn select(&mut self, step: isize) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let index = self.state.selected().unwrap_or(0) as isize + step;
+        let index = index.clamp(0, self.entries.len() as isize - 1) as usize;
+        self.state.select(Some(index));
+    }
+
+    /// load current dir entries
+    #[inline]
+    async fn load(&mut self) -> Result<()> {
+        let mut entries = self.client.list(&self.dir).await?;
+        entries.sort_by(|a, b| b.file_type.cmp(&a.file_type).then(a.name.cmp(&b.name)));
+        self.entries = entries;
+        self.state.select(if self.entries.is_empty() {
+            None
+        } else {
+            Some(0)
+        });
+        Ok(())
+    }
+
+    /// reload current dir, show the error in status
+    #[inline]
+    async fn reload(&mut self) {
+        if let Err(err) = self.load().await {
+            self.status = format!("load {} error:{err}", self.dir.display());
+        }
+    }
+
+    /// change to dir, back to the old dir if load error
+    #[inline]
+    async fn change_dir(&mut self, dir: PathBuf) {
+        let old = std::mem::replace(&mut self.dir, dir);
+        if let Err(err) = self.load().await {
+            self.status = format!("open {} error:{err}", self.dir.display());
+            self.dir = old;
+        } else {
+            self.status.clear();
+        }
+    }
+
+    #[inline]
+    async fn open(&mut self) {
+        match self.selected() {
+            Some(entry) if entry.file_type == 1 => {
+                let dir = self.remote_path(&entry.name);
+                self.change_dir(dir).await;
+            }
+            Some(_) => self.info().await,
+            None => {}
+        }
+    }
+
+    #[inline]
+    async fn parent(&mut self) {
+        let parent = match self.dir.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => return,
+        };
+        self.change_dir(parent).await;
+    }
+
+    #[inline]
+    async fn info(&mut self) {
+        let Some(entry) = self.selected() else {
+            return;
+        };
+        if entry.file_type == 1 {
+            return;
+        }
+        let path = self.remote_path(&entry.name);
+        match self.client.info(&path).await {
+            Ok(info) => {
+                self.mode = Mode::Info(vec![
+                    format!("file name: {}", info.name),
+                    format!(
+                        "size: {} Byte ({})",
+                        info.size,
+                        self.display.size(info.size)
+                    ),
+                    format!("blake3: {}", info.b3.as_deref().unwrap_or("none")),
+                    format!("sha256: {}", info.sha256.as_deref().unwrap_or("none")),
+                    format!("create time: {}", self.display.time(info.create_time)),
+                    format!("can modify: {}", info.can_modify),
+                    format!("content type: {}", info.content_type()),
+                ])
+            }
+            Err(err) => self.status = format!("get {} info error:{err}", path.display()),
+        }
+    }
+
+    /// pull the selected file to current local dir, run outside the tui to show the progress
+    #[inline]
+    async fn pull(&mut self) {
+        let Some(entry) = self.selected() else {
+            return;
+        };
+        if entry.file_type == 1 {
+            self.status = "only file can be pulled".to_string();
+            return;
+        }
+        let path = self.remote_path(&entry.name);
+        let result = crate::pull_file(
+            self.client,
+            path.clone(),
+            None,
+            false,
+            TransferOptions::from(&self.defaults),
+        )
+        .await;
+        self.status = match result {
+            Ok(summary) if summary.success => format!("pull {} success", summary.local),
+            Ok(summary) => format!(
+                "pull {} error:{}",
+                path.display(),
+                summary.error.unwrap_or_default()
+            ),
+            Err(err) => format!("pull {} error:{err}", path.display()),
+        };
+    }
+
+    /// count the files and size of the selected entry, then confirm delete
+    #[inline]
+    async fn confirm_delete(&mut self) {
+        use humansize::{format_size, WINDOWS};
+        let Some(entry) = self.selected() else {
+            return;
+        };
+        let path = self.remote_path(&entry.name);
+        let client = self.client.netx();
+        let server = impl_struct!(client=>IFileStoreService);
+        match crate::remote_usage(&server, path.clone()).await {
+            Ok((files, size)) => {
+                self.mode = Mode::Delete(format!(
+                    "{} ({} files, {})",
+                    path.display(),
+                    files,
+                    format_size(size, WINDOWS)
+                ))
+            }
+            Err(err) => self.status = format!("count {} error:{err}", path.display()),
+        }
+    }
+
+    #[inline]
+    async fn delete(&mut self) {
+        let Some(entry) = self.selected() else {
+            return;
+        };
+        let path = self.remote_path(&entry.name);
+        self.status = match self.client.remove(&path).await {
+            Ok(()) => format!("delete {} success", path.display()),
+            Err(err) => format!("delete {} error:{err}", path.display()),
+        };
+        self.reload().await;
+    }
+
+    #[inline]
+    async fn rename(&mut self, name: &str) {
+        let Some(entry) = self.selected() else {
+            return;
+        };
+        if name.is_empty() || name == entry.name {
+            return;
+        }
+        let from = self.remote_path(&entry.name);
+        let to = self.remote_path(name);
+        self.status = match self.client.rename(&from, &to).await {
+            Ok(()) => format!("rename {} -> {} success", from.display(), to.display()),
+            Err(err) => format!("rename {} error:{err}", from.display()),
+        };
+        self.reload().await;
+    }
+
+    #[inline]
+    fn draw(&mut self, frame: &mut Frame) {
+        let [list_area, status_area] = {
+            let areas =
+                Layout::vertical([Constraint::Min(1), Constraint::Length(3)]).split(frame.size());
+            [areas[0], areas[1]]
+        };
+
+        let items = self
+            .entries
+            .iter()
+            .map(|entry| {
+                if entry.file_type == 1 {
+                    ListItem::new(format!("{:>10}  {}/", "", entry.name)).style(
+                        Style::default()
+                            .fg(Color::Blue)
+                            .add_modifier(Modifier::BOLD),
+                    )
+                } else {
+                    ListItem::new(format!(
+                        "{:>10}  {}",
+                        self.display.size(entry.size),
+                        entry.name
+                    ))
+                    .style(Style::default().fg(Color::Cyan))
+                }
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(self.dir.display().to_string()),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut self.state);
+
+        let status = match self.mode {
+            Mode::Rename(ref name) => format!("rename to: {name}"),
+            Mode::Delete(ref preview) => format!("delete {preview}? (y/n)"),
+            _ if !self.status.is_empty() => self.status.clone(),
+            _ => {
+                "enter:open  backspace:back  i:info  p:pull  r:rename  d:delete  q:quit".to_string()
+            }
+        };
+        frame.render_widget(
+            Paragraph::new(status).block(Block::default().borders(Borders::ALL)),
+            status_area,
+        );
+
+        if let Mode::Info(ref lines) = self.mode {
+            let area = popup_area(frame.size(), lines.len() as u16 + 2);
+            frame.render_widget(Clear, area);
+            frame.render_widget(
+                Paragraph::new(lines.join("\n"))
+                    .block(Block::default().borders(Borders::ALL).title("info")),
+                area,
+            );
+        }
+    }
+}
+
+/// centered popup area
+#[inline]
+fn popup_area(area: Rect, height: u16) -> Rect {
+    let width = area.width.saturating_sub(4);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// enter the tui
+#[inline]
+fn enter() -> Result<Tui> {
+    enable_raw_mode()?;
+    execute!(std::io::stdout(), EnterAlternateScreen)?;
+    Ok(Terminal::new(CrosstermBackend::new(std::io::stdout()))?)
+}
+
+/// leave the tui, restore the terminal
+#[inline]
+fn leave(terminal: &mut Tui) -> Result<()> {
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    Ok(())
+}
+
+/// browse remote directory
+#[inline]
+pub async fn browse(client: &FileStoreClient, defaults: DefaultsConfig, dir: &Path) -> Result<()> {
+    Browser::new(client, defaults, dir.to_path_buf())
+        .run()
+        .await
+}
@@ -0,0 +1,205 @@
+use anyhow::{Context, Result};
+use chrono::Local;
+use console::style;
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use fsc::client::{ResumePoint, TransferEvent};
+use fsc::config::{get_config_path, get_current_exec_path};
+
+/// the checkpoint of the interrupted sync push or pull
+#[derive(Serialize)]
+pub struct Checkpoint {
+    pub time: String,
+    /// push or pull
+    pub action: String,
+    pub local: String,
+    pub remote: String,
+    pub size: u64,
+    /// the local file modified secs of the push, the changed file not resumed
+    pub modified: u64,
+    /// push: the local blake3, pull: the remote blake3
+    pub hash: String,
+    /// the confirmed offset
+    pub offset: u64,
+    pub overwrite: bool,
+}
+
+impl Checkpoint {
+    #[inline]
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            time: row.get("time")?,
+            action: row.get("action")?,
+            local: row.get("local")?,
+            remote: row.get("remote")?,
+            size: row.get::<_, i64>("size")? as u64,
+            modified: row.get::<_, i64>("modified")? as u64,
+            hash: row.get("hash")?,
+            offset: row.get::<_, i64>("offset")? as u64,
+            overwrite: row.get("overwrite")?,
+        })
+    }
+
+    /// the resume point, none if the local file of the push changed
+    #[inline]
+    pub fn resume_point(&self) -> Option<ResumePoint> {
+        if self.action == "push" {
+            let (size, modified) = local_stat(Path::new(&self.local)).ok()?;
+            if size != self.size || modified != self.modified {
+                return None;
+            }
+        }
+        Some(ResumePoint {
+            offset: self.offset,
+            hash: self.hash.clone(),
+        })
+    }
+
+    /// print the checkpoint line
+    #[inline]
+    pub fn print_line(&self) {
+        use humansize::{format_size, WINDOWS};
+        let (from, to) = if self.action == "pull" {
+            (&self.remote, &self.local)
+        } else {
+            (&self.local, &self.remote)
+        };
+        println!(
+            "{} {:<4} {}/{} {from} -> {to}",
+            self.time,
+            style(&self.action).cyan(),
+            style(format_size(self.offset, WINDOWS)).green(),
+            format_size(self.size, WINDOWS)
+        );
+    }
+}
+
+/// the local file size and modified secs
+#[inline]
+fn local_stat(file: &Path) -> Result<(u64, u64)> {
+    let metadata = std::fs::metadata(file)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    Ok((metadata.len(), modified))
+}
+
+/// the state db file, next to the config file
+#[inline]
+fn db_path() -> Result<PathBuf> {
+    let dir = match get_config_path() {
+        Ok(config) => config.parent().map(Path::to_path_buf).unwrap_or_default(),
+        Err(_) => get_current_exec_path()?,
+    };
+    Ok(dir.join("state.db"))
+}
+
+/// open the state db, create the table if not exists
+#[inline]
+fn open() -> Result<Connection> {
+    let path = db_path()?;
+    let conn = Connection::open(&path)
+        .with_context(|| format!("open state db:{} error", path.display()))?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS checkpoints (
+            action TEXT NOT NULL,
+            local TEXT NOT NULL,
+            remote TEXT NOT NULL,
+            time TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            modified INTEGER NOT NULL,
+            hash TEXT NOT NULL,
+            offset INTEGER NOT NULL,
+            overwrite INTEGER NOT NULL,
+            PRIMARY KEY (action, local, remote)
+        );",
+    )?;
+    Ok(conn)
+}
+
+/// save the checkpoint, remove it if the transfer finished
+#[inline]
+fn save(event: &TransferEvent) -> Result<()> {
+    let TransferEvent::Checkpoint {
+        file,
+        local,
+        push,
+        hash,
+        offset,
+        size,
+        overwrite,
+    } = *event
+    else {
+        return Ok(());
+    };
+    let action = if push { "push" } else { "pull" };
+    // the resume run in the other dir
+    let local = if local.is_absolute() {
+        local.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(local)
+    };
+    let local = local.to_string_lossy();
+    let conn = open()?;
+    if offset >= size {
+        conn.execute(
+            "DELETE FROM checkpoints WHERE action = ?1 AND local = ?2 AND remote = ?3",
+            params![action, local, file],
+        )?;
+        return Ok(());
+    }
+    let modified = if push {
+        local_stat(Path::new(local.as_ref()))?.1
+    } else {
+        0
+    };
+    conn.execute(
+        "INSERT OR REPLACE INTO checkpoints (action, local, remote, time, size, modified, hash, offset, overwrite)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            action,
+            local,
+            file,
+            Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            size as i64,
+            modified as i64,
+            hash,
+            offset as i64,
+            overwrite
+        ],
+    )?;
+    Ok(())
+}
+
+/// record the checkpoint event, the state error not fail the transfer
+#[inline]
+pub fn record(event: &TransferEvent) {
+    if let Err(err) = save(event) {
+        log::warn!("save transfer checkpoint error:{err:#}");
+    }
+}
+
+/// the checkpoints of the interrupted transfers by the time
+#[inline]
+pub fn list() -> Result<Vec<Checkpoint>> {
+    let conn = open()?;
+    let mut statement = conn.prepare("SELECT * FROM checkpoints ORDER BY time")?;
+    let checkpoints = statement
+        .query_map([], Checkpoint::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(checkpoints)
+}
+
+/// remove the checkpoint not able to resume
+#[inline]
+pub fn remove(checkpoint: &Checkpoint) -> Result<()> {
+    open()?.execute(
+        "DELETE FROM checkpoints WHERE action = ?1 AND local = ?2 AND remote = ?3",
+        params![checkpoint.action, checkpoint.local, checkpoint.remote],
+    )?;
+    Ok(())
+}
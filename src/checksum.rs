@@ -0,0 +1,219 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// file checksum algorithm, compatible with the external systems not speak blake3
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Blake3,
+    Sha256,
+    Sha512,
+    /// xxh3 64 bits, not cryptographic
+    Xxh3,
+    /// md5, not cryptographic
+    Md5,
+}
+
+impl HashAlgorithm {
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Blake3 => "blake3",
+            Self::Sha256 => "sha256",
+            Self::Sha512 => "sha512",
+            Self::Xxh3 => "xxh3",
+            Self::Md5 => "md5",
+        }
+    }
+}
+
+impl std::fmt::Display for HashAlgorithm {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = anyhow::Error;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "blake3" | "b3" => Ok(Self::Blake3),
+            "sha256" => Ok(Self::Sha256),
+            "sha512" => Ok(Self::Sha512),
+            "xxh3" => Ok(Self::Xxh3),
+            "md5" => Ok(Self::Md5),
+            _ => Err(anyhow!(
+                "unknown hash algorithm:{s}, support blake3, sha256, sha512, xxh3, md5"
+            )),
+        }
+    }
+}
+
+/// the verification of the pulled file against the remote hash
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum PullVerify {
+    #[default]
+    Blake3,
+    /// for the fips constrained environment
+    Sha256,
+    /// skip the verification
+    None,
+}
+
+/// incremental hasher of the algorithm
+pub enum Hasher {
+    Blake3(Box<blake3::Hasher>),
+    Sha256(sha2::Sha256),
+    Sha512(sha2::Sha512),
+    Xxh3(Box<xxhash_rust::xxh3::Xxh3>),
+    Md5(md5::Md5),
+}
+
+impl Hasher {
+    #[inline]
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Blake3 => Self::Blake3(Box::default()),
+            HashAlgorithm::Sha256 => Self::Sha256(sha2::Sha256::new()),
+            HashAlgorithm::Sha512 => Self::Sha512(sha2::Sha512::new()),
+            HashAlgorithm::Xxh3 => Self::Xxh3(Box::default()),
+            HashAlgorithm::Md5 => Self::Md5(md5::Md5::new()),
+        }
+    }
+
+    #[inline]
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Blake3(hasher) => {
+                hasher.update(data);
+            }
+            Self::Sha256(hasher) => hasher.update(data),
+            Self::Sha512(hasher) => hasher.update(data),
+            Self::Xxh3(hasher) => hasher.update(data),
+            Self::Md5(hasher) => hasher.update(data),
+        }
+    }
+
+    /// the lowercase hex checksum
+    #[inline]
+    pub fn finalize(self) -> String {
+        match self {
+            Self::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+            Self::Sha256(hasher) => hex::encode(hasher.finalize()),
+            Self::Sha512(hasher) => hex::encode(hasher.finalize()),
+            Self::Xxh3(hasher) => format!("{:016x}", hasher.digest()),
+            Self::Md5(hasher) => hex::encode(hasher.finalize()),
+        }
+    }
+}
+
+/// the expected checksum, `sha256:<hex>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checksum {
+    pub algorithm: HashAlgorithm,
+    pub hex: String,
+}
+
+impl FromStr for Checksum {
+    type Err = anyhow::Error;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self> {
+        let (algorithm, hex) = s
+            .split_once(':')
+            .with_context(|| format!("checksum:{s} format error, example sha256:<hex>"))?;
+        Ok(Self {
+            algorithm: algorithm.parse()?,
+            hex: hex.trim().to_ascii_lowercase(),
+        })
+    }
+}
+
+impl std::fmt::Display for Checksum {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.hex)
+    }
+}
+
+/// parse the checksum file of the `sha256sum` or `b3sum` format,
+/// the `hex  path` or the binary mode `hex *path` lines, skip the empty and the comment lines
+#[inline]
+pub fn parse_checksum_file(text: &str) -> Result<Vec<(String, PathBuf)>> {
+    let mut checksums = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (hex, path) = line.split_once(' ').with_context(|| {
+            format!(
+                "checksum line:{} format error, example <hex>  <path>",
+                index + 1
+            )
+        })?;
+        let path = path.strip_prefix([' ', '*']).unwrap_or(path);
+        anyhow::ensure!(
+            !hex.is_empty() && hex.bytes().all(|c| c.is_ascii_hexdigit()) && !path.is_empty(),
+            "checksum line:{} format error, example <hex>  <path>",
+            index + 1
+        );
+        checksums.push((hex.to_ascii_lowercase(), PathBuf::from(path)));
+    }
+    Ok(checksums)
+}
+
+/// guess the algorithm of the checksum file by the hex length,
+/// the 64 hex is blake3 if the file name has b3 or blake3, otherwise sha256
+#[inline]
+pub fn guess_algorithm(file: &Path, hex: &str) -> Result<HashAlgorithm> {
+    match hex.len() {
+        16 => Ok(HashAlgorithm::Xxh3),
+        32 => Ok(HashAlgorithm::Md5),
+        128 => Ok(HashAlgorithm::Sha512),
+        64 => {
+            let name = file
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_ascii_lowercase();
+            if name.contains("b3") || name.contains("blake3") {
+                Ok(HashAlgorithm::Blake3)
+            } else {
+                Ok(HashAlgorithm::Sha256)
+            }
+        }
+        len => Err(anyhow!(
+            "unknown hash algorithm of the {len} hex, set --hash"
+        )),
+    }
+}
+
+/// compute the local file checksum in the blocking thread
+#[inline]
+pub async fn file_checksum(path: &Path, algorithm: HashAlgorithm) -> Result<String> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut file = std::fs::File::open(&path)
+            .with_context(|| format!("open file:{} error", path.display()))?;
+        let mut hasher = Hasher::new(algorithm);
+        let mut data = vec![0; 512 * 1024];
+        loop {
+            match file.read(&mut data)? {
+                0 => break,
+                len => hasher.update(&data[..len]),
+            }
+        }
+        Ok(hasher.finalize())
+    })
+    .await?
+}
@@ -1,7 +1,179 @@
-use clap::{Args, Parser, Subcommand};
+use clap::{ArgAction, ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use log::LevelFilter;
+use std::ffi::OsString;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 
+use crate::checksum::{Checksum, HashAlgorithm, PullVerify};
+use crate::conflict::ConflictPolicy;
+use crate::rewrite::RenamePrefix;
+use crate::tune::Jobs;
+
 #[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(flatten)]
+    pub server: ServerArgs,
+    #[command(flatten)]
+    pub log: LogArgs,
+    /// print json output, for scripts and other programs
+    #[arg(long, global = true, value_parser, default_value = "false")]
+    pub json: bool,
+    /// quiet mode, not show progress bar and only log error,
+    /// progress bar auto hide if stderr is not a terminal
+    #[arg(long, short, global = true, value_parser, default_value = "false")]
+    pub quiet: bool,
+    /// progress output, bar or json: newline-delimited progress events
+    #[arg(long, global = true, value_enum, default_value = "bar")]
+    pub progress: ProgressMode,
+    /// write json progress events to the file or FIFO instead of stderr
+    #[arg(long, global = true, value_parser)]
+    pub progress_file: Option<PathBuf>,
+    /// not prompt before remove remote data, for automation
+    #[arg(long, short, global = true, value_parser, default_value = "false")]
+    pub yes: bool,
+    /// write the transfer summary report json to file
+    #[arg(long, global = true, value_parser)]
+    pub summary_file: Option<PathBuf>,
+    /// color output: auto, always or never, auto disable if env NO_COLOR set or output is piped
+    #[arg(long, global = true, value_enum, default_value = "auto")]
+    pub color: ColorMode,
+    /// match the paths case insensitive: the glob, the sync compare and the conflict detection,
+    /// for the windows origin trees and the case sensitive remote store
+    #[arg(long, global = true, value_parser, default_value = "false")]
+    pub case_insensitive: bool,
+    #[command(subcommand)]
+    pub command: Opt,
+}
+
+/// progress output mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProgressMode {
+    /// progress bar
+    Bar,
+    /// newline-delimited json progress events
+    Json,
+}
+
+/// color output mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+/// directory listing output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ListFormat {
+    Text,
+    /// comma separated, with header name,type,size,created
+    Csv,
+    /// tab separated, with header name,type,size,created
+    Tsv,
+}
+
+/// the collision policy of the same file names in the flattened image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FlattenCollision {
+    /// fail the image push
+    Fail,
+    /// rename the later files to `file (1).bin`
+    Rename,
+    /// keep the first file, skip the others
+    Skip,
+}
+
+/// size and time display options
+#[derive(Debug, Clone, Copy, Default, Args)]
+pub struct DisplayArgs {
+    /// show size in bytes
+    #[arg(long, value_parser, default_value = "false")]
+    pub bytes: bool,
+    /// show time as ISO 8601 (RFC 3339)
+    #[arg(long, value_parser, default_value = "false")]
+    pub iso_time: bool,
+    /// show time in UTC
+    #[arg(long, value_parser, default_value = "false")]
+    pub utc: bool,
+}
+
+/// log options
+#[derive(Debug, Args)]
+pub struct LogArgs {
+    /// log more, -v info, -vv debug, -vvv trace, default warn
+    #[arg(long, short, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+    /// log level: off, error, warn, info, debug, trace
+    #[arg(long, global = true, value_parser)]
+    pub log_level: Option<LevelFilter>,
+    /// write log to file, rotate when larger than 10MB
+    #[arg(long, global = true, value_parser)]
+    pub log_file: Option<PathBuf>,
+}
+
+/// connect options, used instead of the config file
+#[derive(Debug, Args)]
+pub struct ServerArgs {
+    /// server addr host:port, if set the config file is not used
+    #[arg(long, global = true, value_parser)]
+    pub server: Option<String>,
+    /// service name
+    #[arg(
+        long,
+        global = true,
+        value_parser,
+        default_value = "file-store-service"
+    )]
+    pub service_name: String,
+    /// verify key
+    #[arg(long, global = true, value_parser, default_value = "")]
+    pub verify_key: String,
+    /// request timeout ms
+    #[arg(long, global = true, value_parser, default_value = "15000")]
+    pub request_out_time_ms: u32,
+    /// auth token
+    #[arg(long, global = true, value_parser)]
+    pub token: Option<String>,
+    /// login user, the password read from env FS_PASSWORD or prompt input
+    #[arg(long, global = true, value_parser)]
+    pub user: Option<String>,
+    /// proxy url, socks5://host:port or http://host:port
+    #[arg(long, global = true, value_parser)]
+    pub proxy: Option<String>,
+    /// webhook url, post the transfer summary json when the transfer finished
+    #[arg(long, global = true, value_parser)]
+    pub notify_url: Option<String>,
+    /// desktop notification when the long transfer finished or failed
+    #[arg(long, global = true, value_parser, default_value = "false")]
+    pub notify: bool,
+    /// retry the failed rpc and reconnect the broken transfer, default the config failover_retries
+    #[arg(long, global = true, value_parser)]
+    pub retries: Option<u32>,
+    /// the delay ms before the first retry, default 1000
+    #[arg(long, global = true, value_parser)]
+    pub retry_delay: Option<u64>,
+    /// the delay multiplier of the next retry, default 2, the delay has +-25% jitter
+    #[arg(long, global = true, value_parser)]
+    pub retry_backoff: Option<f64>,
+    /// tls ca file path
+    #[arg(long, global = true, value_parser)]
+    pub ca: Option<PathBuf>,
+    /// tls server certificate sha256 fingerprint
+    #[arg(long, global = true, value_parser)]
+    pub pinned_sha256: Option<String>,
+    /// tls server name, default is the server addr host
+    #[arg(long, global = true, value_parser)]
+    pub tls_server_name: Option<String>,
+    /// tls cert file path
+    #[arg(long, global = true, value_parser, requires = "key")]
+    pub cert: Option<PathBuf>,
+    /// tls key file path
+    #[arg(long, global = true, value_parser, requires = "cert")]
+    pub key: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
 pub enum Opt {
     /// create config
     Create,
@@ -10,36 +182,116 @@ pub enum Opt {
         /// save dir
         #[arg(long, short, value_parser)]
         dir: Option<PathBuf>,
-        /// local file
-        #[arg(value_parser)]
-        file: PathBuf,
+        /// local files, `-` stream from stdin, the size is unknown until the end,
+        /// the glob pattern push all the matched files, example `target/release/*.so`
+        #[arg(value_parser, required_unless_present = "files_from")]
+        file: Vec<PathBuf>,
+        /// remote file name, required if push stdin, default the local file name
+        #[arg(long, value_parser)]
+        name: Option<String>,
+        /// push the files of the list, one path per line, `-` read the list from stdin
+        #[arg(long, value_parser, conflicts_with_all = ["file", "name"])]
+        files_from: Option<PathBuf>,
         /// async write
         #[arg(long, short, value_parser, default_value = "false")]
         r#async: bool,
+        /// not async write, override the config defaults async
+        #[arg(long, value_parser, default_value = "false", conflicts_with = "async")]
+        no_async: bool,
         /// transfer block size default 65536
-        #[arg(long, short, value_parser, default_value = "65536")]
-        block: usize,
-        /// if service exists file, over write file
+        #[arg(long, short, value_parser)]
+        block: Option<usize>,
+        /// if service exists file, over write file, same as `--on-conflict overwrite`
         #[arg(long, short, value_parser, default_value = "false")]
         overwrite: bool,
+        /// not over write file, override the config defaults overwrite
+        #[arg(long, value_parser, default_value = "false", conflicts_with_all = ["overwrite", "on_conflict"])]
+        no_overwrite: bool,
+        /// if exists file, skip, overwrite, rename to `file (1).bin` or ask on the terminal
+        #[arg(long, value_enum, conflicts_with = "overwrite")]
+        on_conflict: Option<ConflictPolicy>,
+        /// the concurrent file transfers of the multiple files, a number or auto tune by the throughput
+        #[arg(long, short, value_parser)]
+        jobs: Option<Jobs>,
+        /// encrypt the file before upload, the key read from env FS_ENCRYPT_KEY, config encrypt.key or os keyring
+        #[arg(long, value_parser, default_value = "false")]
+        encrypt: bool,
+        /// age encrypt the file to the recipient before upload, can be repeated
+        #[arg(long, value_parser, conflicts_with = "encrypt")]
+        encrypt_to: Vec<String>,
+        /// upload the 4MB parts of the large file in parallel by the multipart push
+        #[arg(long, value_parser)]
+        parallel: Option<usize>,
+        /// remove the local files after the server blake3 verified
+        #[arg(long, value_parser, default_value = "false")]
+        remove_source: bool,
+        /// if the remote file exists, push to the unique name suffixed the short blake3,
+        /// and the timestamp if also exists, never overwrite
+        #[arg(long, value_parser, default_value = "false", conflicts_with_all = ["overwrite", "on_conflict"])]
+        auto_rename: bool,
+        /// skip the holes of the sparse file, example the vm disk image, only the data written by the offset
+        #[arg(long, value_parser, default_value = "false")]
+        sparse: bool,
+        /// rewrite the path prefix, local_prefix=remote_prefix, the pull map the reverse, can be repeated
+        #[arg(long, value_parser)]
+        rename_prefix: Vec<RenamePrefix>,
+        /// if the server is unreachable, queue the push to the `fsc jobs` queue and exit successfully,
+        /// the watch or schedule daemon or `resume` upload it when the connection returns,
+        /// the queued job only keep the overwrite, not the other transfer options
+        #[arg(long, value_parser, default_value = "false", conflicts_with_all = [
+            "files_from", "encrypt", "encrypt_to", "remove_source", "auto_rename",
+            "on_conflict", "sparse", "parallel", "block", "async",
+        ])]
+        queue_offline: bool,
     },
     /// pull file
     Pull {
         /// remote file path
         #[arg(value_parser)]
         file: PathBuf,
-        /// save file path
+        /// save file path, - write to the stdout
         #[arg(long, short, value_parser)]
         save: Option<PathBuf>,
         /// async write
         #[arg(long, short, value_parser, default_value = "false")]
         r#async: bool,
+        /// not async write, override the config defaults async
+        #[arg(long, value_parser, default_value = "false", conflicts_with = "async")]
+        no_async: bool,
         /// transfer block size default 65536
-        #[arg(long, short, value_parser, default_value = "65536")]
-        block: usize,
-        /// if exists file, over write file
+        #[arg(long, short, value_parser)]
+        block: Option<usize>,
+        /// if exists file, over write file, same as `--on-conflict overwrite`
         #[arg(long, short, value_parser, default_value = "false")]
         overwrite: bool,
+        /// not over write file, override the config defaults overwrite
+        #[arg(long, value_parser, default_value = "false", conflicts_with_all = ["overwrite", "on_conflict"])]
+        no_overwrite: bool,
+        /// if exists file, skip, overwrite, rename to `file (1).bin` or ask on the terminal
+        #[arg(long, value_enum, conflicts_with = "overwrite")]
+        on_conflict: Option<ConflictPolicy>,
+        /// the concurrent file transfers of the multiple files, a number or auto tune by the throughput
+        #[arg(long, short, value_parser)]
+        jobs: Option<Jobs>,
+        /// verify the pulled file by the expected checksum, example sha256:<hex>
+        #[arg(long, value_parser)]
+        checksum: Option<Checksum>,
+        /// verify the pulled file by the remote hash: blake3, sha256 or none
+        #[arg(long, value_enum, default_value = "blake3")]
+        verify: PullVerify,
+        /// trust the server hash, skip the local re-hash of the pulled file, same as `--verify none`,
+        /// example the tls link
+        #[arg(long, value_parser, default_value = "false", conflicts_with_all = ["verify", "checksum"])]
+        no_verify: bool,
+        /// check the existing local file by the remote hash, pull only if differs or missing
+        #[arg(long, value_parser, default_value = "false")]
+        check: bool,
+        /// keep the zero blocks as the holes of the local sparse file, not the async pull
+        #[arg(long, value_parser, default_value = "false", conflicts_with = "async")]
+        sparse: bool,
+        /// rewrite the path prefix, local_prefix=remote_prefix, the pull map the reverse, can be repeated
+        #[arg(long, value_parser)]
+        rename_prefix: Vec<RenamePrefix>,
     },
     /// image path
     Image(ImageArgs),
@@ -49,13 +301,240 @@ pub enum Opt {
         /// remote directory path
         #[arg(value_parser)]
         dir: PathBuf,
+        /// output format: text, csv or tsv
+        #[arg(long, short, value_enum, default_value = "text")]
+        format: ListFormat,
+        #[command(flatten)]
+        display: DisplayArgs,
     },
     /// show remote file info
     Info {
         /// remote file path
         #[arg(value_parser)]
         file: PathBuf,
+        /// compute the checksums on the server: blake3, sha256, sha512, xxh3, md5
+        #[arg(long = "hash", value_enum, value_delimiter = ',')]
+        hashes: Vec<HashAlgorithm>,
+        /// show the sha512
+        #[arg(long, value_parser, default_value = "false")]
+        sha512: bool,
+        #[command(flatten)]
+        display: DisplayArgs,
+    },
+    /// print the first block of the remote file, the text or the hex dump of the binary
+    Preview {
+        /// remote file path
+        #[arg(value_parser)]
+        file: PathBuf,
+        /// the preview bytes
+        #[arg(long, value_parser, default_value = "4096")]
+        bytes: usize,
+    },
+    /// search the lines of the remote text files by the regex, stream the file blocks
+    Grep {
+        /// the regex pattern
+        #[arg(value_parser)]
+        pattern: String,
+        /// remote file path, or directory path if recursive
+        #[arg(value_parser)]
+        path: PathBuf,
+        /// search the files of the directory tree
+        #[arg(long, short, value_parser, default_value = "false")]
+        recursive: bool,
+        /// case insensitive
+        #[arg(long, short, value_parser, default_value = "false")]
+        ignore_case: bool,
+        /// skip the larger files of the recursive search, default 16MB
+        #[arg(long, value_parser, default_value = "16777216")]
+        max_size: u64,
+    },
+    /// compare two remote files by the blake3, not download the files
+    Cmp {
+        /// remote file path
+        #[arg(value_parser)]
+        a: PathBuf,
+        /// remote file path
+        #[arg(value_parser)]
+        b: PathBuf,
+        /// read the files by range, locate the first differing offset
+        #[arg(long, value_parser, default_value = "false")]
+        range: bool,
+    },
+    /// compute the local file checksums, compare with the remote `info --hash`,
+    /// or export the checksums of the remote directory tree
+    Checksum {
+        /// local files
+        #[arg(value_parser, required_unless_present = "dir")]
+        files: Vec<PathBuf>,
+        /// remote directory, the checksums of every file in the tree, the path relative to the dir
+        #[arg(long, value_parser, conflicts_with = "files")]
+        dir: Option<PathBuf>,
+        /// write the checksum file, example `b3sums.txt`, verify by `b3sum -c` or `sha256sum -c`
+        #[arg(short, long, value_parser)]
+        output: Option<PathBuf>,
+        /// blake3, sha256, sha512, xxh3, md5
+        #[arg(
+            long = "hash",
+            value_enum,
+            value_delimiter = ',',
+            default_value = "sha256"
+        )]
+        hashes: Vec<HashAlgorithm>,
+    },
+    /// verify the local tree against the checksum file, example the `checksum --dir` export,
+    /// report the mismatched and the missing files
+    Verify {
+        /// the checksum file, example `b3sums.txt`
+        #[arg(long, value_parser)]
+        manifest: PathBuf,
+        /// the local root of the paths in the checksum file
+        #[arg(long, value_parser, default_value = ".")]
+        root: PathBuf,
+        /// the algorithm of the checksum file, guess by the hex length and the file name if not set
+        #[arg(long = "hash", value_enum)]
+        hash: Option<HashAlgorithm>,
+        /// the files hashed concurrently, default the cpu count
+        #[arg(short, long, value_parser)]
+        jobs: Option<usize>,
+    },
+    /// continue the interrupted sync push and pull by the saved checkpoints, example after the reboot
+    Resume {
+        /// list the checkpoints, not resume
+        #[arg(long, short, value_parser, default_value = "false")]
+        list: bool,
+    },
+    /// remove remote file or directory
+    Rm {
+        /// remote file or directory path
+        #[arg(value_parser)]
+        path: PathBuf,
+    },
+    /// interactive remote directory browser
+    Browse {
+        /// remote directory path
+        #[arg(value_parser, default_value = "./")]
+        dir: PathBuf,
+    },
+    /// rehash the remote files of the directory on the server,
+    /// compare with the stored hash and the image manifest, report the corrupted files
+    Scrub {
+        /// remote directory path
+        #[arg(value_parser, default_value = "./")]
+        dir: PathBuf,
+    },
+    /// print the remote file changes of the directory
+    Events {
+        /// remote directory path
+        #[arg(value_parser, default_value = "./")]
+        dir: PathBuf,
+    },
+    /// watch the local directory, push the new and changed files
+    Watch {
+        /// local directory path
+        #[arg(value_parser)]
+        local: PathBuf,
+        /// remote save dir
+        #[arg(value_parser)]
+        remote_dir: Option<PathBuf>,
+        /// debounce the file change events, seconds
+        #[arg(long, value_parser, default_value = "2")]
+        debounce: u64,
+        /// prometheus metrics listen address, serve GET /metrics
+        #[arg(long, value_parser)]
+        metrics: Option<SocketAddr>,
+        /// async write
+        #[arg(long, short, value_parser, default_value = "false")]
+        r#async: bool,
+        /// not async write, override the config defaults async
+        #[arg(long, value_parser, default_value = "false", conflicts_with = "async")]
+        no_async: bool,
+        /// transfer block size default 65536
+        #[arg(long, short, value_parser)]
+        block: Option<usize>,
+        /// compare the xxh3 instead of the blake3, fast but not cryptographic
+        #[arg(long, value_parser, default_value = "false")]
+        fast_verify: bool,
+        /// the max concurrent jobs of the `fsc jobs` queue
+        #[arg(long, value_parser, default_value = "2")]
+        queue_concurrency: usize,
+    },
+    /// run the config [jobs] sync by the cron schedule
+    Schedule {
+        /// prometheus metrics listen address, serve GET /metrics
+        #[arg(long, value_parser)]
+        metrics: Option<SocketAddr>,
+        /// all jobs compare the size and the xxh3 instead of the blake3
+        #[arg(long, value_parser, default_value = "false")]
+        fast_verify: bool,
+        /// the max concurrent jobs of the `fsc jobs` queue
+        #[arg(long, value_parser, default_value = "2")]
+        queue_concurrency: usize,
+    },
+    /// run the commands of the script over one connection, one command per line
+    Batch {
+        /// script file, `-` read from stdin
+        #[arg(value_parser)]
+        script: PathBuf,
+        /// continue the next command if error, default abort
+        #[arg(long, short, value_parser, default_value = "false")]
+        keep_going: bool,
     },
+    /// serve the remote directory over the local gateway
+    #[command(group(ArgGroup::new("gateway").required(true).multiple(true)))]
+    Serve {
+        /// webdav gateway listen address, e.g. 127.0.0.1:8080
+        #[arg(long, value_parser, group = "gateway")]
+        webdav: Option<SocketAddr>,
+        /// http gateway listen address, GET /files/<path> download and GET /ls/<dir> list
+        #[arg(long, value_parser, group = "gateway")]
+        http: Option<SocketAddr>,
+        /// remote root directory
+        #[arg(value_parser, default_value = "./")]
+        dir: PathBuf,
+    },
+    /// read or edit config
+    Config(ConfigArgs),
+    /// list or show the local transfer history
+    History(HistoryArgs),
+    /// queue the transfer jobs of the watch or schedule daemon, list, cancel or pause them
+    Jobs(JobsArgs),
+    /// verify the local audit log hash chain
+    Audit(AuditArgs),
+    /// save token, password or private key passphrase to os keyring
+    Login {
+        /// save tls private key passphrase
+        #[arg(long, value_parser, default_value = "false")]
+        key_passphrase: bool,
+        /// save file encrypt key
+        #[arg(long, value_parser, default_value = "false")]
+        encrypt_key: bool,
+    },
+    /// delete token and password from os keyring
+    Logout,
+    /// run the external command `fsc-<name>` on PATH, example: fsc foo --bar
+    #[command(external_subcommand)]
+    External(Vec<OsString>),
+}
+
+impl Opt {
+    /// the command call the server rpc, the local commands run without the reachable server
+    #[inline]
+    pub fn needs_connection(&self) -> bool {
+        !matches!(
+            self,
+            Opt::Create
+                | Opt::Config(_)
+                | Opt::History(_)
+                | Opt::Jobs(_)
+                | Opt::Audit(_)
+                | Opt::Login { .. }
+                | Opt::Logout
+                | Opt::External(_)
+                | Opt::Verify { .. }
+                | Opt::Checksum { dir: None, .. }
+                | Opt::Resume { list: true }
+        )
+    }
 }
 
 #[derive(Debug, Args)]
@@ -65,6 +544,14 @@ pub struct ImageArgs {
     pub command: ImageCommands,
 }
 
+/// batch script line command
+#[derive(Parser)]
+#[command(no_binary_name = true)]
+pub struct BatchLine {
+    #[command(subcommand)]
+    pub command: Opt,
+}
+
 #[derive(Debug, Subcommand)]
 pub enum ImageCommands {
     /// push image
@@ -78,11 +565,202 @@ pub enum ImageCommands {
         /// async write
         #[arg(long, short, value_parser, default_value = "false")]
         r#async: bool,
+        /// not async write, override the config defaults async
+        #[arg(long, value_parser, default_value = "false", conflicts_with = "async")]
+        no_async: bool,
         /// transfer block size default 65536
-        #[arg(long, short, value_parser, default_value = "65536")]
-        block: usize,
-        /// if service exists file, over write file
+        #[arg(long, short, value_parser)]
+        block: Option<usize>,
+        /// if service exists file, over write file, same as `--on-conflict overwrite`
         #[arg(long, short, value_parser, default_value = "false")]
         overwrite: bool,
+        /// not over write file, override the config defaults overwrite
+        #[arg(long, value_parser, default_value = "false", conflicts_with_all = ["overwrite", "on_conflict"])]
+        no_overwrite: bool,
+        /// if exists file, skip, overwrite, rename to `file (1).bin` or ask on the terminal
+        #[arg(long, value_enum, conflicts_with = "overwrite")]
+        on_conflict: Option<ConflictPolicy>,
+        /// the concurrent file transfers of the multiple files, a number or auto tune by the throughput
+        #[arg(long, short, value_parser)]
+        jobs: Option<Jobs>,
+        /// encrypt the file before upload, the key read from env FS_ENCRYPT_KEY, config encrypt.key or os keyring
+        #[arg(long, value_parser, default_value = "false")]
+        encrypt: bool,
+        /// age encrypt the file to the recipient before upload, can be repeated
+        #[arg(long, value_parser, conflicts_with = "encrypt")]
+        encrypt_to: Vec<String>,
+        /// push the image manifest file with the files size and blake3
+        #[arg(long, value_parser, default_value = "false")]
+        manifest: bool,
+        /// sign the manifest by the ed25519 pkcs8 pem private key, push the manifest signature
+        #[arg(long, value_parser)]
+        sign_key: Option<PathBuf>,
+        /// resume the interrupted push, skip the done files confirmed on the server
+        #[arg(long, value_parser, default_value = "false")]
+        resume: bool,
+        /// upload the identical files once, the others copy on the server
+        #[arg(long, value_parser, default_value = "false")]
+        dedupe: bool,
+        /// upload the files of the nested tree into the image root,
+        /// the same names: fail, rename to `file (1).bin` or skip, default fail
+        #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "fail")]
+        flatten: Option<FlattenCollision>,
+        /// remove the local files after the server blake3 verified
+        #[arg(long, value_parser, default_value = "false", conflicts_with = "resume")]
+        remove_source: bool,
+        /// rewrite the path prefix, local_prefix=remote_prefix, the pull map the reverse, can be repeated
+        #[arg(long, value_parser)]
+        rename_prefix: Vec<RenamePrefix>,
+    },
+    /// verify the remote image files by the manifest and the signature
+    Verify {
+        /// remote image path
+        #[arg(value_parser)]
+        path: PathBuf,
+        /// ed25519 spki pem public key, verify the manifest signature
+        #[arg(long, value_parser)]
+        public_key: Option<PathBuf>,
+        /// fail if the manifest is not signed or the signature is not verified
+        #[arg(long, value_parser, default_value = "false")]
+        require_signature: bool,
+    },
+}
+
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct HistoryArgs {
+    #[command(subcommand)]
+    pub command: HistoryCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HistoryCommands {
+    /// list the last transfers, example: fsc history list app.zip
+    List {
+        /// filter the local or remote path contains it
+        #[arg(value_parser)]
+        path: Option<String>,
+        /// max records
+        #[arg(long, short, value_parser, default_value = "20")]
+        limit: usize,
+    },
+    /// show the transfer detail
+    Show {
+        /// transfer id
+        #[arg(value_parser)]
+        id: i64,
+    },
+}
+
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct JobsArgs {
+    #[command(subcommand)]
+    pub command: JobsCommands,
+}
+
+/// the queued job transfer
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum JobAction {
+    Push,
+    Pull,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum JobsCommands {
+    /// queue the job, example: fsc jobs add push ./app.zip releases/app.zip -p 10
+    Add {
+        /// push or pull
+        #[arg(value_enum)]
+        action: JobAction,
+        /// the local file of push, the remote file of pull
+        #[arg(value_parser)]
+        from: PathBuf,
+        /// the remote file of push, the local file of pull
+        #[arg(value_parser)]
+        to: PathBuf,
+        /// the larger priority run first
+        #[arg(
+            long,
+            short,
+            value_parser,
+            default_value = "0",
+            allow_negative_numbers = true
+        )]
+        priority: i64,
+        /// if exists file, over write file
+        #[arg(long, short, value_parser, default_value = "false")]
+        overwrite: bool,
+    },
+    /// list the queued and running jobs
+    List {
+        /// list the finished jobs too
+        #[arg(long, short, value_parser, default_value = "false")]
+        all: bool,
+    },
+    /// cancel the queued or running job
+    Cancel {
+        /// job id
+        #[arg(value_parser)]
+        id: i64,
+    },
+    /// pause the queued or running job, the running job restart when resumed
+    Pause {
+        /// job id
+        #[arg(value_parser)]
+        id: i64,
+    },
+    /// resume the paused job
+    Resume {
+        /// job id
+        #[arg(value_parser)]
+        id: i64,
+    },
+}
+
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct AuditArgs {
+    #[command(subcommand)]
+    pub command: AuditCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AuditCommands {
+    /// verify the audit log not modified, example: fsc audit verify
+    Verify {
+        /// audit log file, default the config audit.path
+        #[arg(value_parser)]
+        path: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommands {
+    /// get config value, example: fsc config get server.addr
+    Get {
+        /// config key, the id not clash the global --key
+        #[arg(id = "config_key", value_name = "KEY", value_parser)]
+        key: String,
+    },
+    /// set config value, example: fsc config set server.addr 127.0.0.1:7556
+    Set {
+        /// config key, the id not clash the global --key
+        #[arg(id = "config_key", value_name = "KEY", value_parser)]
+        key: String,
+        /// config value
+        #[arg(value_parser)]
+        value: String,
     },
+    /// show config
+    Show,
+    /// check config, cert/key files and try connect server
+    Check,
 }
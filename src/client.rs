@@ -0,0 +1,1823 @@
+use anyhow::{bail, ensure, Context, Result};
+use futures::stream::{self, Stream, StreamExt};
+use netxclient::client::NetxClientArcDef;
+use netxclient::prelude::*;
+use std::collections::{HashSet, VecDeque};
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+use crate::audit::AuditLog;
+use crate::checksum::{self, Checksum, HashAlgorithm, PullVerify};
+use crate::config::{Config, DefaultsConfig};
+use crate::conflict::{self, ConflictPolicy, Resolution};
+use crate::connect::Reconnect;
+use crate::controller::{FileChanged, FileWriteService, IFileWS, WriteHandle};
+use crate::crypto::{self, AgeIdentities, AgeRecipients, Key, TempFile};
+use crate::interface_server::*;
+use crate::io::{RemoteReader, RemoteWriter, READ_BLOCK};
+use crate::retry::RetryPolicy;
+use crate::rewrite::RenamePrefix;
+use crate::sparse;
+use crate::tune::Jobs;
+
+/// the multipart push part size
+pub const PART_SIZE: u64 = 4 * 1024 * 1024;
+/// the multipart push part retries
+const PART_RETRIES: u32 = 3;
+
+/// the max paths of one get_files_info call
+const FILES_INFO_BATCH: usize = 256;
+/// the sync push and pull save the checkpoint every the bytes
+const CHECKPOINT_BYTES: u64 = 64 * 1024 * 1024;
+
+/// the checkpoint of the interrupted transfer, continue after the restart
+#[derive(Debug, Clone)]
+pub struct ResumePoint {
+    /// the confirmed offset, the push continue from the server offset
+    pub offset: u64,
+    /// push: the local blake3 computed at the start, pull: the remote blake3 at the start
+    pub hash: String,
+}
+
+/// push and pull options
+#[derive(Debug, Clone)]
+pub struct TransferOptions {
+    /// async write or read
+    pub r#async: bool,
+    /// transfer block size, none the server preferred or the default block
+    pub block: Option<usize>,
+    /// if exists file, over write file
+    pub overwrite: bool,
+    /// if exists file, resolve by the policy, none fail if not overwrite
+    pub on_conflict: Option<ConflictPolicy>,
+    /// cancel the transfer, the key is released on the server
+    pub cancel: CancellationToken,
+    /// push: encrypt the file before upload, pull: decrypt the encrypted file
+    pub encrypt_key: Option<Key>,
+    /// push: age encrypt the file to the recipients before upload
+    pub encrypt_to: AgeRecipients,
+    /// pull: decrypt the age encrypted file
+    pub age_identities: AgeIdentities,
+    /// push: upload the parts in parallel by the multipart push if larger than 1
+    pub parallel: usize,
+    /// pull: verify the pulled file by the expected checksum, example the artifact store sha256
+    pub checksum: Option<Checksum>,
+    /// pull: verify the pulled file by the remote blake3 or sha256, or skip
+    pub verify: PullVerify,
+    /// image push: the lock lease secs, 0 the lock never expire
+    pub lock_ttl_secs: u64,
+    /// push: remove the local file after the remote hash verified
+    pub remove_source: bool,
+    /// push: upload under the unique suffixed name if the remote file exists
+    pub auto_rename: bool,
+    /// multiple files: the concurrent file transfers, fixed or auto tuned
+    pub jobs: Jobs,
+    /// match the remote and local paths case insensitive: the glob, the sync compare and the conflict
+    pub case_insensitive: bool,
+    /// rewrite the path prefix, push map the local prefix to the remote, pull the reverse
+    pub rename_prefix: Vec<RenamePrefix>,
+    /// push: skip the holes of the sparse file by the offset writes,
+    /// pull: seek over the zero blocks to keep the holes, not the async pull
+    pub sparse: bool,
+    /// continue the interrupted sync transfer from the checkpoint
+    pub resume: Option<ResumePoint>,
+}
+
+impl Default for TransferOptions {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            r#async: false,
+            block: None,
+            overwrite: false,
+            on_conflict: None,
+            cancel: CancellationToken::new(),
+            encrypt_key: None,
+            encrypt_to: AgeRecipients::default(),
+            age_identities: AgeIdentities::default(),
+            parallel: 1,
+            checksum: None,
+            verify: PullVerify::default(),
+            lock_ttl_secs: DefaultsConfig::LOCK_TTL_SECS,
+            remove_source: false,
+            auto_rename: false,
+            jobs: Jobs::default(),
+            case_insensitive: false,
+            rename_prefix: vec![],
+            sparse: false,
+            resume: None,
+        }
+    }
+}
+
+impl TransferOptions {
+    /// the command line flags, if not set use the config defaults
+    #[inline]
+    pub fn new(
+        defaults: &DefaultsConfig,
+        r#async: bool,
+        block: Option<usize>,
+        overwrite: bool,
+    ) -> Self {
+        Self {
+            r#async: defaults.r#async(r#async),
+            block: block.or(defaults.block),
+            overwrite: defaults.overwrite(overwrite)
+                || defaults.on_conflict == Some(ConflictPolicy::Overwrite),
+            on_conflict: defaults.on_conflict,
+            cancel: CancellationToken::new(),
+            encrypt_key: None,
+            encrypt_to: AgeRecipients::default(),
+            age_identities: AgeIdentities::default(),
+            parallel: 1,
+            checksum: None,
+            verify: PullVerify::default(),
+            lock_ttl_secs: defaults.lock_ttl_secs(),
+            remove_source: false,
+            auto_rename: false,
+            jobs: defaults.jobs.unwrap_or_default(),
+            case_insensitive: defaults.case_insensitive,
+            rename_prefix: vec![],
+            sparse: false,
+            resume: None,
+        }
+    }
+
+    /// set the cancellation token
+    #[inline]
+    pub fn with_cancel(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// set the file encrypt key
+    #[inline]
+    pub fn with_encrypt_key(mut self, key: Option<Key>) -> Self {
+        self.encrypt_key = key;
+        self
+    }
+
+    /// set the push age recipients
+    #[inline]
+    pub fn with_encrypt_to(mut self, recipients: AgeRecipients) -> Self {
+        self.encrypt_to = recipients;
+        self
+    }
+
+    /// set the pull age identities
+    #[inline]
+    pub fn with_age_identities(mut self, identities: AgeIdentities) -> Self {
+        self.age_identities = identities;
+        self
+    }
+
+    /// set the pull expected checksum
+    #[inline]
+    pub fn with_checksum(mut self, checksum: Option<Checksum>) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    /// set the pull verification
+    #[inline]
+    pub fn with_verify(mut self, verify: PullVerify) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// set the push parallel parts
+    #[inline]
+    pub fn with_parallel(mut self, parallel: Option<usize>) -> Self {
+        self.parallel = parallel.unwrap_or(1).max(1);
+        self
+    }
+
+    /// turn off the config default async write
+    #[inline]
+    pub fn with_no_async(mut self, no_async: bool) -> Self {
+        if no_async {
+            self.r#async = false;
+        }
+        self
+    }
+
+    /// turn off the config default overwrite and the default overwrite conflict policy
+    #[inline]
+    pub fn with_no_overwrite(mut self, no_overwrite: bool) -> Self {
+        if no_overwrite {
+            self.overwrite = false;
+            if self.on_conflict == Some(ConflictPolicy::Overwrite) {
+                self.on_conflict = None;
+            }
+        }
+        self
+    }
+
+    /// set the conflict policy, override the overwrite flag
+    #[inline]
+    pub fn with_on_conflict(mut self, on_conflict: Option<ConflictPolicy>) -> Self {
+        if let Some(policy) = on_conflict {
+            self.overwrite = policy == ConflictPolicy::Overwrite;
+            self.on_conflict = Some(policy);
+        }
+        self
+    }
+
+    /// set upload under the unique name if the remote file exists
+    #[inline]
+    pub fn with_auto_rename(mut self, auto_rename: bool) -> Self {
+        self.auto_rename = auto_rename;
+        self
+    }
+
+    /// set the concurrent file transfers of the multiple files, if not set keep the config defaults
+    #[inline]
+    pub fn with_jobs(mut self, jobs: Option<Jobs>) -> Self {
+        if let Some(jobs) = jobs {
+            self.jobs = jobs;
+        }
+        self
+    }
+
+    /// set remove the pushed local file
+    #[inline]
+    pub fn with_remove_source(mut self, remove_source: bool) -> Self {
+        self.remove_source = remove_source;
+        self
+    }
+
+    /// set the path prefix rewrite rules
+    #[inline]
+    pub fn with_rename_prefix(mut self, rename_prefix: Vec<RenamePrefix>) -> Self {
+        self.rename_prefix = rename_prefix;
+        self
+    }
+
+    /// set the sparse file transfer
+    #[inline]
+    pub fn with_sparse(mut self, sparse: bool) -> Self {
+        self.sparse = sparse;
+        self
+    }
+
+    /// set continue the interrupted transfer from the checkpoint
+    #[inline]
+    pub fn with_resume(mut self, resume: Option<ResumePoint>) -> Self {
+        self.resume = resume;
+        self
+    }
+}
+
+impl From<&DefaultsConfig> for TransferOptions {
+    #[inline]
+    fn from(defaults: &DefaultsConfig) -> Self {
+        Self::new(defaults, false, None, false)
+    }
+}
+
+/// transfer progress event
+#[derive(Debug)]
+pub enum TransferEvent<'a> {
+    /// the local file blake3 is computed
+    Hashed {
+        file: &'a str,
+        hash: &'a str,
+        elapsed: Duration,
+    },
+    /// start transfer the file
+    Start { file: &'a str, size: u64 },
+    /// the transferred bytes of the file
+    Progress {
+        file: &'a str,
+        position: u64,
+        size: u64,
+    },
+    /// transfer error, reconnect and retry
+    Retry {
+        file: &'a str,
+        count: u32,
+        retries: u32,
+        error: &'a anyhow::Error,
+    },
+    /// the block write or read error, retry the block on the same connection
+    BlockRetry {
+        file: &'a str,
+        offset: u64,
+        count: u32,
+        error: &'a anyhow::Error,
+    },
+    /// the confirmed offset of the sync push or pull, save to continue after the restart,
+    /// the offset is the size when the transfer finished
+    Checkpoint {
+        file: &'a str,
+        local: &'a Path,
+        push: bool,
+        hash: &'a str,
+        offset: u64,
+        size: u64,
+        overwrite: bool,
+    },
+    /// the file transfer finish
+    Finish { file: &'a str, size: u64 },
+}
+
+/// transfer progress callback
+pub type ProgressCallback = Arc<dyn Fn(TransferEvent) + Send + Sync>;
+
+/// the lock lease of the pushing files, renewed in the background until drop
+pub struct LockLease {
+    renew: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl Drop for LockLease {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(ref renew) = self.renew {
+            renew.abort();
+        }
+    }
+}
+
+/// file store client, used to embed in other rust services
+///
+/// ```no_run
+/// # async fn example(config: fsc::config::Config) -> anyhow::Result<()> {
+/// let client = fsc::FileStoreClient::connect(config).await?;
+/// for entry in client.list("./").await? {
+///     println!("{}", entry.name);
+/// }
+/// client.push_file("./file", "dir/file", Default::default()).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct FileStoreClient {
+    /// shared with the lock lease renew, replaced when reconnected
+    client: Arc<Mutex<NetxClientArcDef>>,
+    reconnect: Reconnect,
+    wfs: Arc<Actor<FileWriteService>>,
+    progress: Mutex<ProgressCallback>,
+    audit: Option<AuditLog>,
+    retry: RetryPolicy,
+    capabilities: Capabilities,
+}
+
+impl FileStoreClient {
+    /// connect the server, the server addr list is tried in order
+    #[inline]
+    pub async fn connect(config: Config) -> Result<Self> {
+        let audit = AuditLog::open(&config)?;
+        let retry = RetryPolicy::from(&config.connect);
+        let wfs = FileWriteService::new();
+        let reconnect = Reconnect::new(config, wfs.clone());
+        let client = reconnect.connect().await?;
+        let capabilities = {
+            let server = impl_struct!(client=>IFileStoreService);
+            server.get_capabilities().await.unwrap_or_else(|err| {
+                log::debug!("get server capabilities error:{err}, the old server");
+                Capabilities::default()
+            })
+        };
+        log::debug!("server capabilities:{capabilities:?}");
+        Ok(Self {
+            client: Arc::new(Mutex::new(client)),
+            reconnect,
+            wfs,
+            progress: Mutex::new(Arc::new(|_| {})),
+            audit,
+            retry,
+            capabilities,
+        })
+    }
+
+    /// the server capabilities learned when connected, the default of the old server
+    #[inline]
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    /// the transfer block negotiated with the server, the not set block use the server preferred,
+    /// the block larger than the server max is clamped
+    #[inline]
+    fn block(&self, block: Option<usize>) -> usize {
+        let Capabilities {
+            preferred_block,
+            max_block,
+            ..
+        } = self.capabilities;
+        let block = match block {
+            Some(block) => block,
+            None if preferred_block > 0 => preferred_block as usize,
+            None => DefaultsConfig::BLOCK,
+        };
+        if max_block > 0 && block as u64 > max_block {
+            log::warn!(
+                "block:{block} larger than the server max block:{max_block}, use {max_block}"
+            );
+            return max_block as usize;
+        }
+        block
+    }
+
+    /// register the transfer progress callback, replace the previous one
+    ///
+    /// ```no_run
+    /// # async fn example(client: fsc::FileStoreClient) -> anyhow::Result<()> {
+    /// use fsc::client::TransferEvent;
+    /// client.on_progress(|event| {
+    ///     if let TransferEvent::Progress { file, position, size } = event {
+    ///         println!("{file} {position}/{size}");
+    ///     }
+    /// });
+    /// client.push_file("./file", "dir/file", Default::default()).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn on_progress(&self, callback: impl Fn(TransferEvent) + Send + Sync + 'static) {
+        *self.progress.lock().unwrap() = Arc::new(callback);
+    }
+
+    /// the transfer progress callback
+    #[inline]
+    fn progress(&self) -> ProgressCallback {
+        self.progress.lock().unwrap().clone()
+    }
+
+    /// the netx client
+    #[inline]
+    pub fn netx(&self) -> NetxClientArcDef {
+        self.client.lock().unwrap().clone()
+    }
+
+    /// write the audit log if enabled
+    #[inline]
+    fn audit(&self, action: &str, path: &str, to: Option<&str>, overwrite: bool) {
+        if let Some(ref audit) = self.audit {
+            audit.record(action, path, to, overwrite);
+        }
+    }
+
+    /// the rpc retry policy
+    #[inline]
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry
+    }
+
+    /// reconnect the server, used by the transfer failover
+    #[inline]
+    async fn reconnect(&self) -> Result<NetxClientArcDef> {
+        let client = self.reconnect.connect().await?;
+        *self.client.lock().unwrap() = client.clone();
+        Ok(client)
+    }
+
+    /// list remote directory contents
+    #[inline]
+    pub async fn list(&self, dir: impl AsRef<Path>) -> Result<Vec<Entry>> {
+        let client = self.netx();
+        let server = impl_struct!(client=>IFileStoreService);
+        self.retry
+            .run("list", || {
+                server.show_directory_contents(dir.as_ref().to_path_buf())
+            })
+            .await
+    }
+
+    /// stream the remote directory entries
+    #[inline]
+    pub fn read_dir(&self, dir: impl AsRef<Path>) -> impl Stream<Item = Result<Entry>> + '_ {
+        let dir = dir.as_ref().to_path_buf();
+        stream::once(self.list(dir)).flat_map(|result| {
+            stream::iter(match result {
+                Ok(entries) => entries.into_iter().map(Ok).collect::<Vec<_>>(),
+                Err(err) => vec![Err(err)],
+            })
+        })
+    }
+
+    /// walk the remote directory tree recursively, stream the entry path and entry,
+    /// the sub directory is listed when the stream reach it
+    ///
+    /// ```no_run
+    /// # async fn example(client: fsc::FileStoreClient) -> anyhow::Result<()> {
+    /// use futures::StreamExt;
+    /// let mut walk = std::pin::pin!(client.walk("./"));
+    /// while let Some(item) = walk.next().await {
+    ///     let (path, entry) = item?;
+    ///     println!("{} {}", path.display(), entry.size);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn walk(&self, dir: impl AsRef<Path>) -> impl Stream<Item = Result<(PathBuf, Entry)>> + '_ {
+        let dirs = vec![dir.as_ref().to_path_buf()];
+        stream::unfold(
+            (dirs, VecDeque::<(PathBuf, Entry)>::new()),
+            move |(mut dirs, mut pending)| async move {
+                loop {
+                    if let Some((path, entry)) = pending.pop_front() {
+                        if entry.file_type == 1 {
+                            dirs.push(path.clone());
+                        }
+                        return Some((Ok((path, entry)), (dirs, pending)));
+                    }
+                    let dir = dirs.pop()?;
+                    match self.list(&dir).await {
+                        Ok(entries) => pending.extend(entries.into_iter().map(|entry| {
+                            let path = dir.join(&entry.name).to_string_lossy().replace('\\', "/");
+                            (PathBuf::from(path), entry)
+                        })),
+                        Err(err) => return Some((Err(err), (dirs, pending))),
+                    }
+                }
+            },
+        )
+    }
+
+    /// list the remote directory tree in one call, the path is joined with the dir,
+    /// max depth none is unlimited
+    ///
+    /// ```no_run
+    /// # async fn example(client: fsc::FileStoreClient) -> anyhow::Result<()> {
+    /// for (path, entry) in client.list_recursive("./", Some(2)).await? {
+    ///     println!("{} {}", path.display(), entry.size);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub async fn list_recursive(
+        &self,
+        dir: impl AsRef<Path>,
+        max_depth: Option<u32>,
+    ) -> Result<Vec<(PathBuf, Entry)>> {
+        let client = self.netx();
+        let server = impl_struct!(client=>IFileStoreService);
+        self.retry
+            .run("list recursive", || {
+                server.list_recursive(dir.as_ref().to_path_buf(), max_depth.unwrap_or(0))
+            })
+            .await
+    }
+
+    /// subscribe the remote file changes of the dir
+    ///
+    /// ```no_run
+    /// # async fn example(client: fsc::FileStoreClient) -> anyhow::Result<()> {
+    /// let mut changes = client.subscribe("./").await?;
+    /// while let Ok(changed) = changes.recv().await {
+    ///     println!("{} {}", changed.kind.as_str(), changed.path);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub async fn subscribe(
+        &self,
+        dir: impl AsRef<Path>,
+    ) -> Result<broadcast::Receiver<FileChanged>> {
+        // receive before subscribe, not lose the first events
+        let changes = self.reconnect.changes();
+        let client = self.netx();
+        let server = impl_struct!(client=>IFileStoreService);
+        server.subscribe(dir.as_ref()).await?;
+        Ok(changes)
+    }
+
+    /// get remote file info, with blake3 and sha256
+    #[inline]
+    pub async fn info(&self, file: impl AsRef<Path>) -> Result<FileInfo> {
+        let client = self.netx();
+        let server = impl_struct!(client=>IFileStoreService);
+        self.retry
+            .run("get file info", || {
+                server.get_file_info(file.as_ref(), true, true)
+            })
+            .await
+    }
+
+    /// compute the remote file checksums of the algorithms on the server
+    ///
+    /// ```no_run
+    /// # async fn example(client: fsc::FileStoreClient) -> anyhow::Result<()> {
+    /// use fsc::checksum::HashAlgorithm;
+    /// for (algorithm, hex) in client.checksums("dir/file", &[HashAlgorithm::Sha512]).await? {
+    ///     println!("{algorithm}:{hex}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub async fn checksums(
+        &self,
+        file: impl AsRef<Path>,
+        algorithms: &[HashAlgorithm],
+    ) -> Result<Vec<(HashAlgorithm, String)>> {
+        let client = self.netx();
+        let server = impl_struct!(client=>IFileStoreService);
+        let checksums = self
+            .retry
+            .run("get file checksums", || {
+                server.get_file_checksums(file.as_ref(), algorithms.to_vec())
+            })
+            .await?;
+        ensure!(
+            checksums.len() == algorithms.len(),
+            "get file checksums return {} of {} algorithms",
+            checksums.len(),
+            algorithms.len()
+        );
+        Ok(algorithms.iter().copied().zip(checksums).collect())
+    }
+
+    /// get the remote files info by batch, the result of every path in order
+    #[inline]
+    pub async fn files_info(
+        &self,
+        paths: &[PathBuf],
+        blake3: bool,
+        sha256: bool,
+    ) -> Result<Vec<Result<FileInfo>>> {
+        let client = self.netx();
+        let server = impl_struct!(client=>IFileStoreService);
+        let mut infos = Vec::with_capacity(paths.len());
+        for chunk in paths.chunks(FILES_INFO_BATCH) {
+            let result = self
+                .retry
+                .run("get files info", || {
+                    server.get_files_info(chunk.to_vec(), blake3, sha256)
+                })
+                .await?;
+            ensure!(
+                result.len() == chunk.len(),
+                "get files info return {} of {} files",
+                result.len(),
+                chunk.len()
+            );
+            infos.extend(
+                result
+                    .into_iter()
+                    .map(|info| info.map_err(anyhow::Error::msg)),
+            );
+        }
+        Ok(infos)
+    }
+
+    /// open remote file, return AsyncRead + AsyncSeek reader
+    ///
+    /// ```no_run
+    /// # async fn example(client: fsc::FileStoreClient) -> anyhow::Result<()> {
+    /// let mut reader = client.open("dir/file").await?;
+    /// tokio::io::copy(&mut reader, &mut tokio::io::stdout()).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub async fn open(&self, path: impl AsRef<Path>) -> Result<RemoteReader> {
+        let client = self.netx();
+        let server = impl_struct!(client=>IFileStoreService);
+        let info = self
+            .retry
+            .run("get file info", || {
+                server.get_file_info(path.as_ref(), false, false)
+            })
+            .await?;
+        let key = self
+            .retry
+            .run("create pull", || server.create_pull(path.as_ref()))
+            .await?;
+        Ok(RemoteReader::new(
+            client, key, info.size, READ_BLOCK, self.retry,
+        ))
+    }
+
+    /// create remote file, return AsyncWrite writer,
+    /// must shutdown the writer to finish the file
+    ///
+    /// ```no_run
+    /// # async fn example(client: fsc::FileStoreClient) -> anyhow::Result<()> {
+    /// use tokio::io::AsyncWriteExt;
+    /// let mut writer = client.create("dir/file", Default::default()).await?;
+    /// tokio::io::copy(&mut tokio::io::stdin(), &mut writer).await?;
+    /// writer.shutdown().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub async fn create(&self, path: &str, options: TransferOptions) -> Result<RemoteWriter> {
+        let client = self.netx();
+        let server = impl_struct!(client=>IFileStoreService);
+        let key = server.create_push(path, options.overwrite).await?;
+        self.audit("create", path, None, options.overwrite);
+        Ok(RemoteWriter::new(
+            client,
+            key,
+            self.block(options.block),
+            options.r#async,
+        ))
+    }
+
+    /// remove remote file or directory
+    #[inline]
+    pub async fn remove(&self, path: impl AsRef<Path>) -> Result<()> {
+        let client = self.netx();
+        let server = impl_struct!(client=>IFileStoreService);
+        server.remove(path.as_ref()).await?;
+        self.audit("remove", &path.as_ref().to_string_lossy(), None, false);
+        Ok(())
+    }
+
+    /// rename remote file or directory
+    #[inline]
+    pub async fn rename(&self, from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<()> {
+        let client = self.netx();
+        let server = impl_struct!(client=>IFileStoreService);
+        server.rename(from.as_ref(), to.as_ref()).await?;
+        self.audit(
+            "rename",
+            &from.as_ref().to_string_lossy(),
+            Some(&to.as_ref().to_string_lossy()),
+            false,
+        );
+        Ok(())
+    }
+
+    /// get the remote space of the dir, not retry, the old server not support it
+    ///
+    /// ```no_run
+    /// # async fn example(client: fsc::FileStoreClient) -> anyhow::Result<()> {
+    /// let quota = client.quota("dir").await?;
+    /// println!("available:{} total:{}", quota.available, quota.total);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub async fn quota(&self, dir: impl AsRef<Path>) -> Result<Quota> {
+        let client = self.netx();
+        let server = impl_struct!(client=>IFileStoreService);
+        server.get_quota(dir.as_ref()).await
+    }
+
+    /// lock the filenames can be push, the lock expire after ttl secs if the client crashed,
+    /// renew the lease every third of the ttl until the lease drop, 0 the lock never expire,
+    /// the server not advertise the lock lease in the capabilities lock without the ttl,
+    /// return the lock error message if failed
+    #[inline]
+    pub async fn lock(
+        &self,
+        filenames: Vec<String>,
+        overwrite: bool,
+        ttl_secs: u64,
+    ) -> Result<std::result::Result<LockLease, String>> {
+        let client = self.netx();
+        let server = impl_struct!(client=>IFileStoreService);
+        let lease = if ttl_secs == 0 {
+            None
+        } else if self.capabilities.lock_lease {
+            Some(server.lock_lease(&filenames, overwrite, ttl_secs).await?)
+        } else {
+            log::warn!("the server not support the lock lease, lock without the ttl");
+            None
+        };
+        let ttl_secs = if lease.is_some() { ttl_secs } else { 0 };
+        let (success, msg) = match lease {
+            Some(lease) => lease,
+            None => server.lock(&filenames, overwrite).await?,
+        };
+        if !success {
+            return Ok(Err(msg));
+        }
+        let renew = (ttl_secs > 0).then(|| {
+            let shared = self.client.clone();
+            tokio::spawn(async move {
+                let period = Duration::from_secs((ttl_secs / 3).max(1));
+                loop {
+                    tokio::time::sleep(period).await;
+                    log::trace!("renew lock lease of {} files", filenames.len());
+                    // the client of the last reconnect
+                    let client = shared.lock().unwrap().clone();
+                    let server = impl_struct!(client=>IFileStoreService);
+                    if let Err(err) = server.renew_lock(&filenames, ttl_secs).await {
+                        log::warn!("renew lock lease error:{err:#}");
+                    }
+                }
+            })
+        });
+        Ok(Ok(LockLease { renew }))
+    }
+
+    /// the existing remote path of the file, the other case of the file name if case insensitive
+    #[inline]
+    async fn remote_existing(
+        &self,
+        remote: &str,
+        case_insensitive: bool,
+    ) -> Result<Option<String>> {
+        let infos = self
+            .files_info(&[PathBuf::from(remote)], false, false)
+            .await?;
+        if infos.into_iter().any(|info| info.is_ok()) {
+            return Ok(Some(remote.to_string()));
+        }
+        if !case_insensitive {
+            return Ok(None);
+        }
+        let (dir, name) = remote.rsplit_once('/').unwrap_or(("", remote));
+        let name = name.to_lowercase();
+        let client = self.netx();
+        let server = impl_struct!(client=>IFileStoreService);
+        // the missing dir has not the file
+        let entries = server
+            .show_directory_contents(PathBuf::from(if dir.is_empty() { "./" } else { dir }))
+            .await
+            .unwrap_or_default();
+        Ok(entries
+            .into_iter()
+            .find(|entry| entry.file_type != 1 && entry.name.to_lowercase() == name)
+            .map(|entry| match dir {
+                "" => entry.name,
+                dir => format!("{dir}/{}", entry.name),
+            }))
+    }
+
+    /// resolve the existing remote files by the conflict policy, none the skipped file,
+    /// set overwrite if any file is overwritten, the auto rename suffix the local file blake3
+    #[inline]
+    pub async fn resolve_conflicts(
+        &self,
+        locals: &[PathBuf],
+        remotes: Vec<String>,
+        options: &mut TransferOptions,
+    ) -> Result<Vec<Option<String>>> {
+        let policy = match options.on_conflict {
+            _ if options.auto_rename => None,
+            Some(policy) if !options.overwrite => Some(policy),
+            _ => return Ok(remotes.into_iter().map(Some).collect()),
+        };
+        let case_insensitive = options.case_insensitive;
+        // the taken names compare by the lowercase if case insensitive
+        let key = |path: &str| match case_insensitive {
+            true => path.to_lowercase(),
+            false => path.to_string(),
+        };
+        let paths = remotes.iter().map(PathBuf::from).collect::<Vec<_>>();
+        let infos = self.files_info(&paths, false, false).await?;
+        let timestamp = chrono::Local::now().format("%Y%m%d%H%M%S").to_string();
+        let mut taken = remotes
+            .iter()
+            .map(|remote| key(remote))
+            .collect::<HashSet<_>>();
+        let mut resolved = Vec::with_capacity(remotes.len());
+        for ((local, remote), info) in locals.iter().zip(remotes).zip(infos) {
+            let existing = match info {
+                Ok(_) => Some(remote.clone()),
+                Err(_) if case_insensitive => self.remote_existing(&remote, true).await?,
+                Err(_) => None,
+            };
+            let Some(existing) = existing else {
+                resolved.push(Some(remote));
+                continue;
+            };
+            let Some(policy) = policy else {
+                // the short blake3 name is the same of the same content, the timestamp if exists,
+                // the stdin not known the hash before the end
+                let mut candidates = vec![];
+                if local != Path::new("-") {
+                    let hash = checksum::file_checksum(local, HashAlgorithm::Blake3).await?;
+                    candidates.push(conflict::suffixed(&remote, &format!("-{}", &hash[..8])));
+                    candidates.push(conflict::suffixed(
+                        &remote,
+                        &format!("-{}-{timestamp}", &hash[..8]),
+                    ));
+                } else {
+                    candidates.push(conflict::suffixed(&remote, &format!("-{timestamp}")));
+                }
+                let mut unique = None;
+                for candidate in candidates {
+                    if !taken.contains(&key(&candidate))
+                        && self
+                            .remote_existing(&candidate, case_insensitive)
+                            .await?
+                            .is_none()
+                    {
+                        unique = Some(candidate);
+                        break;
+                    }
+                }
+                let unique = unique
+                    .with_context(|| format!("remote file:{remote} not found unique name"))?;
+                log::info!("remote file:{existing} already exists, auto rename to {unique}");
+                taken.insert(key(&unique));
+                resolved.push(Some(unique));
+                continue;
+            };
+            match policy.resolve(&existing)? {
+                Resolution::Skip => {
+                    log::info!("remote file:{existing} already exists, skip");
+                    resolved.push(None);
+                }
+                Resolution::Overwrite => {
+                    // overwrite the existing file of the other case, not push the second file
+                    options.overwrite = true;
+                    resolved.push(Some(existing));
+                }
+                Resolution::Rename => {
+                    let mut index = 1;
+                    let renamed = loop {
+                        let candidate = conflict::renamed(&remote, index);
+                        if !taken.contains(&key(&candidate))
+                            && self
+                                .remote_existing(&candidate, case_insensitive)
+                                .await?
+                                .is_none()
+                        {
+                            break candidate;
+                        }
+                        index += 1;
+                    };
+                    log::info!("remote file:{existing} already exists, rename to {renamed}");
+                    taken.insert(key(&renamed));
+                    resolved.push(Some(renamed));
+                }
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// copy remote file on the server, not transfer the data
+    #[inline]
+    pub async fn copy(
+        &self,
+        from: impl AsRef<Path>,
+        to: impl AsRef<Path>,
+        overwrite: bool,
+    ) -> Result<()> {
+        let client = self.netx();
+        let server = impl_struct!(client=>IFileStoreService);
+        server.copy(from.as_ref(), to.as_ref(), overwrite).await?;
+        self.audit(
+            "copy",
+            &from.as_ref().to_string_lossy(),
+            Some(&to.as_ref().to_string_lossy()),
+            overwrite,
+        );
+        Ok(())
+    }
+
+    /// push the stream of unknown size to remote path, example the pipe, return the size
+    ///
+    /// ```no_run
+    /// # async fn example(client: fsc::FileStoreClient) -> anyhow::Result<()> {
+    /// let size = client
+    ///     .push_stream(tokio::io::stdin(), "dir/file", Default::default())
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub async fn push_stream(
+        &self,
+        mut reader: impl AsyncRead + Unpin,
+        remote: &str,
+        options: TransferOptions,
+    ) -> Result<u64> {
+        ensure!(
+            options.encrypt_key.is_none() && options.encrypt_to.is_empty(),
+            "push stream:{remote} not support encrypt"
+        );
+        let progress = self.progress();
+        let mut writer = self.create(remote, options.clone()).await?;
+        progress(TransferEvent::Start {
+            file: remote,
+            size: 0,
+        });
+        // drop the writer cancel the push on the server
+        let mut buff = vec![0; options.block.unwrap_or(DefaultsConfig::BLOCK)];
+        loop {
+            let len = tokio::select! {
+                len = reader.read(&mut buff) => len?,
+                _ = options.cancel.cancelled() => bail!("push stream:{remote} cancelled"),
+            };
+            if len == 0 {
+                break;
+            }
+            writer.write_all(&buff[..len]).await?;
+            progress(TransferEvent::Progress {
+                file: remote,
+                position: writer.size(),
+                size: writer.size(),
+            });
+        }
+        writer.shutdown().await?;
+        let size = writer.size();
+        progress(TransferEvent::Hashed {
+            file: remote,
+            hash: &writer.hash(),
+            elapsed: Duration::ZERO,
+        });
+        progress(TransferEvent::Finish { file: remote, size });
+        Ok(size)
+    }
+
+    /// pull the remote file to the writer, example the stdout, verify the streaming hash,
+    /// return the file size, the encrypted file is written as it is
+    #[inline]
+    pub async fn pull_stream(
+        &self,
+        remote: impl AsRef<Path>,
+        mut writer: impl AsyncWrite + Unpin,
+        options: TransferOptions,
+    ) -> Result<u64> {
+        let remote = remote.as_ref();
+        let file = remote.to_string_lossy();
+        let file = file.as_ref();
+        let progress = self.progress();
+        let client = self.netx();
+        let server = impl_struct!(client=>IFileStoreService);
+        let info = server
+            .get_file_info(remote, true, options.verify == PullVerify::Sha256)
+            .await?;
+        let (algorithm, expected) = match options.verify {
+            PullVerify::Blake3 => (HashAlgorithm::Blake3, info.b3.clone()),
+            PullVerify::Sha256 => (HashAlgorithm::Sha256, info.sha256.clone()),
+            PullVerify::None => (HashAlgorithm::Blake3, None),
+        };
+        ensure!(
+            options.verify == PullVerify::None || expected.is_some(),
+            "currently unable to pull file:{} to verify {algorithm}",
+            remote.display()
+        );
+        let key = server.create_pull(remote).await?;
+        // drop the reader release the key on the server
+        let mut reader = RemoteReader::new(client.clone(), key, info.size, READ_BLOCK, self.retry);
+        let mut hasher = checksum::Hasher::new(algorithm);
+        progress(TransferEvent::Start {
+            file,
+            size: info.size,
+        });
+        let mut buff = vec![0; options.block.unwrap_or(DefaultsConfig::BLOCK)];
+        let mut position = 0;
+        loop {
+            let len = tokio::select! {
+                len = reader.read(&mut buff) => len?,
+                _ = options.cancel.cancelled() => bail!("pull file:{file} cancelled"),
+            };
+            if len == 0 {
+                break;
+            }
+            writer.write_all(&buff[..len]).await?;
+            hasher.update(&buff[..len]);
+            position += len as u64;
+            progress(TransferEvent::Progress {
+                file,
+                position,
+                size: info.size,
+            });
+        }
+        writer.flush().await?;
+        let hash = hasher.finalize();
+        progress(TransferEvent::Hashed {
+            file,
+            hash: &hash,
+            elapsed: Duration::ZERO,
+        });
+        if let Some(expected) = expected {
+            ensure!(
+                hash == expected,
+                "file read hash error remote {algorithm}:{expected} local {algorithm}:{hash}"
+            );
+        }
+        progress(TransferEvent::Finish {
+            file,
+            size: position,
+        });
+        Ok(position)
+    }
+
+    /// push local file to remote path, return the file size
+    #[inline]
+    pub async fn push_file(
+        &self,
+        local: impl AsRef<Path>,
+        remote: &str,
+        options: TransferOptions,
+    ) -> Result<u64> {
+        let local = local.as_ref();
+        ensure!(local.is_file(), "path:{} not file", local.display());
+        let source = local;
+        // push the encrypted temp file
+        let encrypted;
+        let local = if options.encrypt_key.is_some() || !options.encrypt_to.is_empty() {
+            let name = local.file_name().unwrap_or_default().to_string_lossy();
+            encrypted = TempFile::new(&format!("{name}.enc"));
+            let (src, dst) = (local.to_path_buf(), encrypted.path().to_path_buf());
+            let (key, recipients) = (options.encrypt_key.clone(), options.encrypt_to.clone());
+            tokio::task::spawn_blocking(move || match key {
+                Some(key) => crypto::encrypt_file(&key, &src, &dst),
+                None => crypto::age_encrypt_file(&recipients, &src, &dst),
+            })
+            .await??;
+            encrypted.path()
+        } else {
+            local
+        };
+        let progress = self.progress();
+        let mut file = File::open(local).await?;
+        let size = file.metadata().await?.len();
+        // the encrypted temp file not the same after the restart, not checkpoint
+        let checkpoint = local == source;
+        let resume = options.resume.clone().filter(|_| checkpoint);
+        let start_hash = Instant::now();
+        let hash = match resume {
+            Some(ref point) => point.hash.clone(),
+            None => tokio::select! {
+                hash = computer_b3(&mut file) => hash,
+                _ = options.cancel.cancelled() => bail!("push file:{remote} cancelled"),
+            },
+        };
+        progress(TransferEvent::Hashed {
+            file: remote,
+            hash: &hash,
+            elapsed: start_hash.elapsed(),
+        });
+        file.seek(SeekFrom::Start(0)).await?;
+        if options.parallel > 1 && size > PART_SIZE {
+            let size = self
+                .push_multipart(local, remote, size, hash.clone(), &options)
+                .await?;
+            if options.remove_source {
+                self.remove_source(source, remote, &hash).await?;
+            }
+            return Ok(size);
+        }
+
+        let mut client = self.netx();
+        let mut start = 0;
+        let mut key = {
+            let server = impl_struct!(client=>IFileStoreService);
+            let resumed = match resume {
+                Some(_) => match server
+                    .resume_push(remote, size, hash.clone(), options.overwrite)
+                    .await
+                {
+                    Ok(resumed) => Some(resumed),
+                    Err(err) => {
+                        log::warn!("resume push file:{remote} error:{err}, push from the start");
+                        None
+                    }
+                },
+                None => None,
+            };
+            match resumed {
+                Some((key, offset)) => {
+                    log::info!("resume push file:{remote} key:{key} offset:{offset}");
+                    start = offset;
+                    key
+                }
+                None => {
+                    server
+                        .push(remote, size, hash.clone(), options.overwrite)
+                        .await?
+                }
+            }
+        };
+        log::debug!("start write file:{remote} key:{key}");
+        progress(TransferEvent::Start { file: remote, size });
+
+        let mut buff = vec![0; self.block(options.block)];
+        // the holes of the sparse file not sent, the data written by the offset
+        let ranges = if options.sparse {
+            sparse::data_ranges(local, size)?
+        } else {
+            None
+        };
+        let r#async = options.r#async || ranges.is_some();
+        if let Some(ranges) = ranges {
+            log::debug!("push sparse file:{remote} data ranges:{}", ranges.len());
+            let server = impl_struct!(client=>IFileStoreService);
+            tokio::select! {
+                result = write_sparse(
+                    &server,
+                    key,
+                    &mut file,
+                    &mut buff,
+                    &ranges,
+                    size,
+                    |position| {
+                        progress(TransferEvent::Progress {
+                            file: remote,
+                            position,
+                            size,
+                        })
+                    },
+                ) => result?,
+                _ = options.cancel.cancelled() => {
+                    server.cancel_push(key).await;
+                    bail!("push file:{remote} cancelled");
+                }
+            }
+        } else {
+            let mut position = start;
+            file.seek(SeekFrom::Start(position)).await?;
+            let mut failover = 0;
+            // the retry count of the block at the retried position
+            let (mut block_retry, mut retried_at) = (0, 0);
+            // the async write not confirmed, not checkpoint
+            let checkpoint = checkpoint && !options.r#async;
+            let checkpointed = AtomicU64::new(position / CHECKPOINT_BYTES);
+            loop {
+                let result = {
+                    let server = impl_struct!(client=>IFileStoreService);
+                    tokio::select! {
+                        result = write_blocks(
+                            &server,
+                            key,
+                            &mut file,
+                            &mut buff,
+                            &mut position,
+                            options.r#async,
+                            |position| {
+                                progress(TransferEvent::Progress {
+                                    file: remote,
+                                    position: position.min(size),
+                                    size,
+                                });
+                                if checkpoint
+                                    && position / CHECKPOINT_BYTES
+                                        > checkpointed.load(Ordering::Relaxed)
+                                {
+                                    checkpointed.store(position / CHECKPOINT_BYTES, Ordering::Relaxed);
+                                    progress(TransferEvent::Checkpoint {
+                                        file: remote,
+                                        local: source,
+                                        push: true,
+                                        hash: &hash,
+                                        offset: position,
+                                        size,
+                                        overwrite: options.overwrite,
+                                    });
+                                }
+                            },
+                        ) => result,
+                        _ = options.cancel.cancelled() => {
+                            server.cancel_push(key).await;
+                            bail!("push file:{remote} cancelled");
+                        }
+                    }
+                };
+                match result {
+                    Ok(()) => break,
+                    // async write can't know the write error, so only sync write retry the block
+                    Err(err) if !options.r#async => {
+                        if position > retried_at {
+                            block_retry = 0;
+                        }
+                        retried_at = position;
+                        if block_retry < self.retry.retries {
+                            block_retry += 1;
+                            log::warn!(
+                                "push file:{remote} block:{position} error:{err}, retry {block_retry}/{}",
+                                self.retry.retries
+                            );
+                            progress(TransferEvent::BlockRetry {
+                                file: remote,
+                                offset: position,
+                                count: block_retry,
+                                error: &err,
+                            });
+                            tokio::time::sleep(self.retry.delay(block_retry)).await;
+                            // the server offset, the failed block maybe written
+                            let server = impl_struct!(client=>IFileStoreService);
+                            match server
+                                .resume_push(remote, size, hash.clone(), options.overwrite)
+                                .await
+                            {
+                                Ok((resume_key, offset)) => {
+                                    key = resume_key;
+                                    position = offset;
+                                    file.seek(SeekFrom::Start(offset)).await?;
+                                    continue;
+                                }
+                                Err(err) => log::warn!("resume push file:{remote} error:{err}"),
+                            }
+                        }
+                        if failover >= self.reconnect.retries() {
+                            return Err(err);
+                        }
+                        failover += 1;
+                        log::warn!(
+                            "push file:{remote} error:{err}, reconnect {failover}/{}",
+                            self.reconnect.retries()
+                        );
+                        progress(TransferEvent::Retry {
+                            file: remote,
+                            count: failover,
+                            retries: self.reconnect.retries(),
+                            error: &err,
+                        });
+                        tokio::time::sleep(self.retry.delay(failover)).await;
+                        client = self.reconnect().await?;
+                        let server = impl_struct!(client=>IFileStoreService);
+                        let (resume_key, offset) = server
+                            .resume_push(remote, size, hash.clone(), options.overwrite)
+                            .await?;
+                        log::info!("resume push file:{remote} key:{resume_key} offset:{offset}");
+                        key = resume_key;
+                        position = offset;
+                        file.seek(SeekFrom::Start(offset)).await?;
+                        progress(TransferEvent::Progress {
+                            file: remote,
+                            position: position.min(size),
+                            size,
+                        });
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+
+        let server = impl_struct!(client=>IFileStoreService);
+        if r#async {
+            let mut retry_count = 0;
+            while !server.check_finish(key).await? && retry_count < 20 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                retry_count += 1;
+            }
+        }
+        server.push_finish(key).await?;
+        self.audit("push", remote, None, options.overwrite);
+        if checkpoint {
+            progress(TransferEvent::Checkpoint {
+                file: remote,
+                local: source,
+                push: true,
+                hash: &hash,
+                offset: size,
+                size,
+                overwrite: options.overwrite,
+            });
+        }
+        progress(TransferEvent::Finish { file: remote, size });
+        if options.remove_source {
+            self.remove_source(source, remote, &hash).await?;
+        }
+        Ok(size)
+    }
+
+    /// remove the pushed local file if the remote blake3 is the pushed hash
+    #[inline]
+    pub async fn remove_source(&self, local: &Path, remote: &str, hash: &str) -> Result<()> {
+        let client = self.netx();
+        let server = impl_struct!(client=>IFileStoreService);
+        let info = server.get_file_info(Path::new(remote), true, false).await?;
+        ensure!(
+            info.b3.as_deref() == Some(hash),
+            "remote file:{remote} hash not verified, keep the source file:{}",
+            local.display()
+        );
+        std::fs::remove_file(local)
+            .with_context(|| format!("remove source file:{} error", local.display()))?;
+        log::info!("remove source file:{}", local.display());
+        Ok(())
+    }
+
+    /// push the file parts in parallel by the multipart push, the failed part is retried
+    #[inline]
+    async fn push_multipart(
+        &self,
+        local: &Path,
+        remote: &str,
+        size: u64,
+        hash: String,
+        options: &TransferOptions,
+    ) -> Result<u64> {
+        let progress = self.progress();
+        let client = self.netx();
+        let server = impl_struct!(client=>IFileStoreService);
+        let key = server
+            .create_multipart(remote, size, hash, PART_SIZE, options.overwrite)
+            .await?;
+        log::debug!(
+            "start multipart file:{remote} key:{key} parallel:{}",
+            options.parallel
+        );
+        progress(TransferEvent::Start { file: remote, size });
+
+        let parts = size.div_ceil(PART_SIZE);
+        let position = AtomicU64::new(0);
+        let policy = self.retry;
+        let upload = stream::iter(0..parts)
+            .map(|part| {
+                let client = client.clone();
+                let (progress, position) = (&progress, &position);
+                async move {
+                    let offset = part * PART_SIZE;
+                    let mut data = vec![0; PART_SIZE.min(size - offset) as usize];
+                    let mut file = File::open(local).await?;
+                    file.seek(SeekFrom::Start(offset)).await?;
+                    file.read_exact(&mut data).await?;
+                    let part_hash = blake3::hash(&data).to_hex().to_string();
+                    let server = impl_struct!(client=>IFileStoreService);
+                    let mut retry = 0;
+                    while let Err(err) = server.upload_part(key, part as u32, &data).await {
+                        ensure!(
+                            retry < PART_RETRIES,
+                            "push file:{remote} part:{part} error:{err}"
+                        );
+                        retry += 1;
+                        log::warn!("push file:{remote} part:{part} error:{err}, retry {retry}/{PART_RETRIES}");
+                        progress(TransferEvent::Retry {
+                            file: remote,
+                            count: retry,
+                            retries: PART_RETRIES,
+                            error: &err,
+                        });
+                        tokio::time::sleep(policy.delay(retry)).await;
+                    }
+                    let len = data.len() as u64;
+                    progress(TransferEvent::Progress {
+                        file: remote,
+                        position: position.fetch_add(len, Ordering::Relaxed) + len,
+                        size,
+                    });
+                    Ok::<_, anyhow::Error>((part, part_hash))
+                }
+            })
+            .buffer_unordered(options.parallel);
+
+        // the parts complete out of order
+        let mut upload = std::pin::pin!(upload);
+        let mut part_hashes = vec![String::new(); parts as usize];
+        loop {
+            let result = tokio::select! {
+                result = upload.next() => result,
+                _ = options.cancel.cancelled() => {
+                    server.cancel_push(key).await;
+                    bail!("push file:{remote} cancelled");
+                }
+            };
+            match result {
+                Some(Ok((part, part_hash))) => part_hashes[part as usize] = part_hash,
+                Some(Err(err)) => {
+                    server.cancel_push(key).await;
+                    return Err(err);
+                }
+                None => break,
+            }
+        }
+        server.complete_multipart(key, part_hashes).await?;
+        self.audit("push", remote, None, options.overwrite);
+        progress(TransferEvent::Finish { file: remote, size });
+        Ok(size)
+    }
+
+    /// pull remote file to local path and verify blake3, return the file size
+    #[inline]
+    pub async fn pull_file(
+        &self,
+        remote: impl AsRef<Path>,
+        local: impl AsRef<Path>,
+        options: TransferOptions,
+    ) -> Result<u64> {
+        let (remote, local) = (remote.as_ref(), local.as_ref());
+        let file = remote.to_string_lossy();
+        let file = file.as_ref();
+        let progress = self.progress();
+        let mut client = self.netx();
+        let server = impl_struct!(client=>IFileStoreService);
+        let info = server
+            .get_file_info(remote, true, options.verify == PullVerify::Sha256)
+            .await?;
+        let b3 = info
+            .b3
+            .with_context(|| format!("currently unable to pull file:{}", remote.display()))?;
+
+        // the seeked holes of the sparse pull not written, not checkpoint
+        let checkpoint = !options.r#async && !options.sparse;
+        // continue the interrupted pull if the remote file not changed
+        let resume = options.resume.clone().filter(|point| {
+            checkpoint
+                && point.hash == b3
+                && local
+                    .metadata()
+                    .map_or(false, |metadata| metadata.len() >= point.offset)
+        });
+        if resume.is_none() && local.exists() {
+            ensure!(options.overwrite, "file:{} already exists", local.display());
+            std::fs::remove_file(local)?;
+        }
+
+        let mut key = server.create_pull(remote).await?;
+        log::debug!("start read file:{file} key:{key}");
+        progress(TransferEvent::Start {
+            file,
+            size: info.size,
+        });
+        let mut fd = tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(local)
+            .await?;
+        let block = self.block(options.block);
+
+        if options.r#async {
+            let (tx, mut rx) = tokio::sync::mpsc::channel(1024);
+            self.wfs.create_wfs(key, WriteHandle::new(fd, tx)).await;
+            server.async_read(key, block).await;
+            let mut offset = 0;
+            loop {
+                let Some(len) = tokio::select! {
+                    len = rx.recv() => len,
+                    _ = options.cancel.cancelled() => {
+                        self.wfs.close_wfs(key).await?;
+                        server.finish_read_key(key).await;
+                        std::fs::remove_file(local)?;
+                        bail!("pull file:{file} cancelled");
+                    }
+                } else {
+                    break;
+                };
+                offset += len;
+                progress(TransferEvent::Progress {
+                    file,
+                    position: offset.min(info.size),
+                    size: info.size,
+                });
+                if offset >= info.size {
+                    break;
+                }
+            }
+            self.wfs.close_wfs(key).await?;
+        } else {
+            let mut offset = resume.map_or(0, |point| point.offset);
+            if offset > 0 {
+                log::info!("resume pull file:{file} offset:{offset}");
+                fd.set_len(offset).await?;
+                fd.seek(SeekFrom::Start(offset)).await?;
+            }
+            let mut checkpointed = offset / CHECKPOINT_BYTES;
+            let mut failover = 0;
+            let mut block_retry = 0;
+            loop {
+                let result = {
+                    let server = impl_struct!(client=>IFileStoreService);
+                    tokio::select! {
+                        result = server.read(key, offset, block) => result,
+                        _ = options.cancel.cancelled() => {
+                            server.finish_read_key(key).await;
+                            drop(fd);
+                            std::fs::remove_file(local)?;
+                            bail!("pull file:{file} cancelled");
+                        }
+                    }
+                };
+                match result {
+                    Ok(data) if !data.is_empty() => {
+                        block_retry = 0;
+                        offset += data.len() as u64;
+                        if options.sparse && sparse::is_zero(&data) {
+                            fd.seek(SeekFrom::Current(data.len() as i64)).await?;
+                        } else {
+                            fd.write_all(&data).await?;
+                        }
+                        progress(TransferEvent::Progress {
+                            file,
+                            position: offset.min(info.size),
+                            size: info.size,
+                        });
+                        if checkpoint && offset / CHECKPOINT_BYTES > checkpointed {
+                            checkpointed = offset / CHECKPOINT_BYTES;
+                            // the checkpoint offset is on the disk after the restart
+                            fd.flush().await?;
+                            fd.sync_data().await?;
+                            progress(TransferEvent::Checkpoint {
+                                file,
+                                local,
+                                push: false,
+                                hash: &b3,
+                                offset,
+                                size: info.size,
+                                overwrite: options.overwrite,
+                            });
+                        }
+                    }
+                    Ok(_) => break,
+                    // the read by the offset, retry the same block
+                    Err(err) if block_retry < self.retry.retries => {
+                        block_retry += 1;
+                        log::warn!(
+                            "pull file:{file} block:{offset} error:{err}, retry {block_retry}/{}",
+                            self.retry.retries
+                        );
+                        progress(TransferEvent::BlockRetry {
+                            file,
+                            offset,
+                            count: block_retry,
+                            error: &err,
+                        });
+                        tokio::time::sleep(self.retry.delay(block_retry)).await;
+                    }
+                    Err(err) if failover < self.reconnect.retries() => {
+                        failover += 1;
+                        log::warn!(
+                            "pull file:{file} error:{err}, reconnect {failover}/{}",
+                            self.reconnect.retries()
+                        );
+                        progress(TransferEvent::Retry {
+                            file,
+                            count: failover,
+                            retries: self.reconnect.retries(),
+                            error: &err,
+                        });
+                        tokio::time::sleep(self.retry.delay(failover)).await;
+                        client = self.reconnect().await?;
+                        let server = impl_struct!(client=>IFileStoreService);
+                        key = server.create_pull(remote).await?;
+                        log::info!("resume pull file:{file} offset:{offset}");
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            fd.flush().await?;
+            // the file end with the hole
+            if options.sparse {
+                fd.set_len(offset).await?;
+            }
+            if checkpoint {
+                progress(TransferEvent::Checkpoint {
+                    file,
+                    local,
+                    push: false,
+                    hash: &b3,
+                    offset: info.size,
+                    size: info.size,
+                    overwrite: options.overwrite,
+                });
+            }
+        }
+        impl_struct!(client=>IFileStoreService)
+            .finish_read_key(key)
+            .await;
+
+        let start_hash = Instant::now();
+        let (algorithm, expected) =
+            match options.verify {
+                PullVerify::Blake3 => (HashAlgorithm::Blake3, Some(b3)),
+                PullVerify::Sha256 => (
+                    HashAlgorithm::Sha256,
+                    Some(info.sha256.clone().with_context(|| {
+                        format!("file:{} not sha256 to verify", remote.display())
+                    })?),
+                ),
+                PullVerify::None => (HashAlgorithm::Blake3, None),
+            };
+        if let Some(expected) = expected {
+            let hash = checksum::file_checksum(local, algorithm).await?;
+            progress(TransferEvent::Hashed {
+                file,
+                hash: &hash,
+                elapsed: start_hash.elapsed(),
+            });
+            if hash != expected {
+                std::fs::remove_file(local)?;
+                bail!(
+                    "file read hash error remote {algorithm}:{expected} local {algorithm}:{hash}"
+                );
+            }
+        } else {
+            log::debug!("pull file:{file} skip verify");
+        }
+        // only decrypt by the configured key or identities, the stored encrypted file kept as it is
+        let decrypt = (options.encrypt_key.is_some() && crypto::is_encrypted(local)?)
+            || (!options.age_identities.is_empty() && crypto::is_age_encrypted(local)?);
+        let size = if decrypt {
+            self.decrypt(local, &options).await?
+        } else {
+            log::debug!("pull file:{file} not decrypt");
+            info.size
+        };
+        if let Some(ref expected) = options.checksum {
+            let actual = checksum::file_checksum(local, expected.algorithm).await?;
+            if actual != expected.hex {
+                std::fs::remove_file(local)?;
+                bail!(
+                    "file:{} checksum error expected {expected} actual {}:{actual}",
+                    local.display(),
+                    expected.algorithm
+                );
+            }
+        }
+        log::info!("pull file:{} success", local.display());
+        progress(TransferEvent::Finish { file, size });
+        Ok(size)
+    }
+
+    /// decrypt the pulled file in place by the encrypt key or the age identities,
+    /// return the decrypted size
+    #[inline]
+    async fn decrypt(&self, local: &Path, options: &TransferOptions) -> Result<u64> {
+        let encrypted = local.to_path_buf();
+        let mut decrypted = encrypted.clone().into_os_string();
+        decrypted.push(".decrypt");
+        let decrypted = PathBuf::from(decrypted);
+        let (src, dst) = (encrypted.clone(), decrypted.clone());
+        let (key, identities) = (options.encrypt_key.clone(), options.age_identities.clone());
+        let result = tokio::task::spawn_blocking(move || {
+            if crypto::is_age_encrypted(&src)? {
+                return crypto::age_decrypt_file(&identities, &src, &dst);
+            }
+            let key = key.with_context(|| {
+                format!("file:{} is encrypted, not found encrypt key", src.display())
+            })?;
+            crypto::decrypt_file(&key, &src, &dst)
+        })
+        .await?;
+        if let Err(err) = result {
+            let _ = std::fs::remove_file(&decrypted);
+            return Err(err);
+        }
+        std::fs::rename(&decrypted, &encrypted)?;
+        log::debug!("decrypt file:{}", encrypted.display());
+        Ok(std::fs::metadata(&encrypted)?.len())
+    }
+}
+
+/// write file blocks from the position to the end
+#[inline]
+pub async fn write_blocks<T: IFileStoreService>(
+    server: &T,
+    key: u64,
+    file: &mut File,
+    buff: &mut [u8],
+    position: &mut u64,
+    r#async: bool,
+    progress: impl Fn(u64),
+) -> Result<()> {
+    while let Ok(len) = file.read(buff).await {
+        if len > 0 {
+            if !r#async {
+                server.write(key, &buff[..len]).await?;
+            } else {
+                server.write_offset(key, *position, &buff[..len]).await;
+            }
+            *position += len as u64;
+            progress(*position);
+        } else {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// write the data ranges of the sparse file by the offset writes, the holes not sent,
+/// write the last zero byte if the file end with the hole, the server file keep the size
+#[inline]
+pub async fn write_sparse<T: IFileStoreService>(
+    server: &T,
+    key: u64,
+    file: &mut File,
+    buff: &mut [u8],
+    ranges: &[(u64, u64)],
+    size: u64,
+    progress: impl Fn(u64),
+) -> Result<()> {
+    for &(start, end) in ranges {
+        file.seek(SeekFrom::Start(start)).await?;
+        let mut position = start;
+        while position < end {
+            let len = buff.len().min((end - position) as usize);
+            file.read_exact(&mut buff[..len]).await?;
+            server.write_offset(key, position, &buff[..len]).await;
+            position += len as u64;
+            progress(position);
+        }
+    }
+    if ranges.last().map_or(0, |&(_, end)| end) < size {
+        server.write_offset(key, size - 1, &[0]).await;
+    }
+    progress(size);
+    Ok(())
+}
+
+/// computer file blake3
+#[inline]
+pub async fn computer_b3(file: &mut File) -> String {
+    let mut sha = blake3::Hasher::new();
+    let mut data = vec![0; 512 * 1024];
+    while let Ok(len) = file.read(&mut data).await {
+        if len > 0 {
+            sha.update(&data[..len]);
+        } else {
+            break;
+        }
+    }
+    hex::encode(sha.finalize().as_bytes())
+}
@@ -0,0 +1,126 @@
+use anyhow::{bail, Result};
+use console::style;
+use netxclient::prelude::*;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use fsc::interface_server::*;
+use fsc::io::READ_BLOCK;
+use fsc::output::print_json;
+use fsc::FileStoreClient;
+
+/// the compare result
+#[derive(Serialize)]
+struct CmpOutput<'a> {
+    a: &'a str,
+    b: &'a str,
+    size_a: u64,
+    size_b: u64,
+    b3_a: Option<String>,
+    b3_b: Option<String>,
+    same: bool,
+    /// the first differing offset, the ranged compare
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offset: Option<u64>,
+}
+
+/// read the two files block by block, return the first differing offset
+#[inline]
+async fn first_diff(client: &FileStoreClient, a: &Path, b: &Path) -> Result<Option<u64>> {
+    let netx = client.netx();
+    let server = impl_struct!(netx=>IFileStoreService);
+    let key_a = server.create_pull(a).await?;
+    let key_b = match server.create_pull(b).await {
+        Ok(key) => key,
+        Err(err) => {
+            server.finish_read_key(key_a).await;
+            return Err(err);
+        }
+    };
+    let result = async {
+        let mut offset = 0;
+        loop {
+            let (data_a, data_b) = tokio::try_join!(
+                server.read(key_a, offset, READ_BLOCK),
+                server.read(key_b, offset, READ_BLOCK)
+            )?;
+            if let Some(index) = data_a.iter().zip(data_b.iter()).position(|(a, b)| a != b) {
+                return Ok::<_, anyhow::Error>(Some(offset + index as u64));
+            }
+            if data_a.len() != data_b.len() {
+                return Ok(Some(offset + data_a.len().min(data_b.len()) as u64));
+            }
+            if data_a.is_empty() {
+                return Ok(None);
+            }
+            offset += data_a.len() as u64;
+        }
+    }
+    .await;
+    server.finish_read_key(key_a).await;
+    server.finish_read_key(key_b).await;
+    result
+}
+
+/// compare two remote files by the blake3, locate the first differing offset if range
+#[inline]
+pub async fn cmp(
+    client: &FileStoreClient,
+    a: PathBuf,
+    b: PathBuf,
+    range: bool,
+    json: bool,
+    quiet: bool,
+) -> Result<()> {
+    let mut infos = client
+        .files_info(&[a.clone(), b.clone()], true, false)
+        .await?
+        .into_iter();
+    let (Some(info_a), Some(info_b)) = (infos.next(), infos.next()) else {
+        bail!("get files info error");
+    };
+    let (info_a, info_b) = (info_a?, info_b?);
+    let same = info_a.size == info_b.size && info_a.b3.is_some() && info_a.b3 == info_b.b3;
+    let offset = if !same && range {
+        first_diff(client, &a, &b).await?
+    } else {
+        None
+    };
+    log::debug!(
+        "cmp {} {} same:{same} offset:{offset:?}",
+        a.display(),
+        b.display()
+    );
+
+    let (a, b) = (a.to_string_lossy(), b.to_string_lossy());
+    if json {
+        print_json(&CmpOutput {
+            a: &a,
+            b: &b,
+            size_a: info_a.size,
+            size_b: info_b.size,
+            b3_a: info_a.b3,
+            b3_b: info_b.b3,
+            same,
+            offset,
+        })?;
+    } else if !quiet {
+        if same {
+            println!("{a} {b} {}", style("same").green());
+        } else {
+            match offset {
+                Some(offset) => println!("{a} {b} {} at byte {offset}", style("differ").red()),
+                None => println!(
+                    "{a} {b} {} size:{}/{}",
+                    style("differ").red(),
+                    info_a.size,
+                    info_b.size
+                ),
+            }
+        }
+    }
+    if !same {
+        bail!("{a} {b} differ");
+    }
+    Ok(())
+}
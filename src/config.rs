@@ -1,19 +1,248 @@
-use anyhow::bail;
+use anyhow::{bail, ensure, Context};
 use netxclient::prelude::ServerOption;
 use serde::Deserialize;
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use toml_edit::{Document, Item};
 
-#[derive(Deserialize, Debug)]
+#[cfg(feature = "cli")]
+use crate::clap_struct::ServerArgs;
+use crate::conflict::ConflictPolicy;
+use crate::tune::Jobs;
+
+#[derive(Deserialize, Debug, Clone)]
 pub struct Config {
     pub server: ServerOption,
     pub tls: Option<TlsConfig>,
+    /// proxy url, socks5://host:port or http://host:port
+    pub proxy: Option<String>,
+    pub auth: Option<AuthConfig>,
+    #[serde(default)]
+    pub connect: ConnectConfig,
+    #[serde(default)]
+    pub defaults: DefaultsConfig,
+    #[serde(default)]
+    pub notify: NotifyConfig,
+    #[serde(default)]
+    pub encrypt: EncryptConfig,
+    #[serde(default)]
+    pub audit: AuditConfig,
+    /// scheduled sync jobs, the job name and config
+    #[serde(default)]
+    pub jobs: BTreeMap<String, JobConfig>,
+}
+
+/// sync direction
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncDirection {
+    /// push the local changed files
+    #[default]
+    Push,
+    /// pull the remote changed files
+    Pull,
+}
+
+/// scheduled sync job
+#[derive(Debug, Deserialize, Clone)]
+pub struct JobConfig {
+    /// cron expression with seconds: sec min hour day month weekday
+    pub cron: String,
+    /// local directory
+    pub local: PathBuf,
+    /// remote directory
+    pub remote: PathBuf,
+    #[serde(default)]
+    pub direction: SyncDirection,
+    /// transfer block size
+    pub block: Option<usize>,
+    /// async write
+    #[serde(default)]
+    pub r#async: bool,
+    /// compare the size and the xxh3 instead of the blake3, fast but not cryptographic
+    #[serde(default)]
+    pub fast_verify: bool,
+}
+
+/// notify when the transfer finished
+#[derive(Debug, Deserialize, Clone)]
+pub struct NotifyConfig {
+    /// webhook url, post the transfer summary json
+    pub url: Option<String>,
+    /// desktop notification
+    #[serde(default)]
+    pub desktop: bool,
+    /// only desktop notify the transfer longer than seconds, default 60
+    #[serde(default = "NotifyConfig::default_desktop_min_secs")]
+    pub desktop_min_secs: u64,
+}
+
+impl NotifyConfig {
+    #[inline]
+    fn default_desktop_min_secs() -> u64 {
+        60
+    }
+}
+
+impl Default for NotifyConfig {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            url: None,
+            desktop: false,
+            desktop_min_secs: Self::default_desktop_min_secs(),
+        }
+    }
+}
+
+/// client side file encryption
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct EncryptConfig {
+    /// 32 bytes hex key, used first if set, the key read from env FS_ENCRYPT_KEY or os keyring if not set
+    pub key: Option<String>,
+    /// age identity files, decrypt the age encrypted file on pull
+    #[serde(default)]
+    pub identities: Vec<PathBuf>,
+}
+
+/// local audit log of the mutating operations
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct AuditConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// audit log file, default audit.log next to the config file
+    pub path: Option<PathBuf>,
+}
+
+/// command defaults, used when the command line flag not set
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct DefaultsConfig {
+    /// transfer block size
+    pub block: Option<usize>,
+    /// async write
+    #[serde(default)]
+    pub r#async: bool,
+    /// if exists file, over write file
+    #[serde(default)]
+    pub overwrite: bool,
+    /// the image push lock lease secs, the lock expire if not renewed, 0 never expire
+    pub lock_ttl_secs: Option<u64>,
+    /// if exists file, skip, overwrite, rename or ask
+    pub on_conflict: Option<ConflictPolicy>,
+    /// match the paths case insensitive: the glob, the sync compare and the conflict detection
+    #[serde(default)]
+    pub case_insensitive: bool,
+    /// the concurrent file transfers of the multiple files, a number or "auto"
+    pub jobs: Option<Jobs>,
+}
+
+impl DefaultsConfig {
+    /// the default transfer block size
+    pub const BLOCK: usize = 65536;
+    /// the default lock lease secs
+    pub const LOCK_TTL_SECS: u64 = 60;
+
+    #[inline]
+    pub fn block(&self, block: Option<usize>) -> usize {
+        block.or(self.block).unwrap_or(Self::BLOCK)
+    }
+
+    #[inline]
+    pub fn r#async(&self, r#async: bool) -> bool {
+        r#async || self.r#async
+    }
+
+    #[inline]
+    pub fn overwrite(&self, overwrite: bool) -> bool {
+        overwrite || self.overwrite
+    }
+
+    #[inline]
+    pub fn lock_ttl_secs(&self) -> u64 {
+        self.lock_ttl_secs.unwrap_or(Self::LOCK_TTL_SECS)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ConnectConfig {
+    /// connect timeout ms
+    #[serde(default = "ConnectConfig::default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// tcp keepalive seconds
+    pub keepalive_secs: Option<u64>,
+    /// heartbeat interval seconds
+    pub heartbeat_secs: Option<u64>,
+    /// if the connection broken during transfer,
+    /// reconnect the server addr list and resume the transfer, default 0 not reconnect,
+    /// also the retries of the failed rpc
+    #[serde(default)]
+    pub failover_retries: u32,
+    /// the delay ms before the first retry
+    #[serde(default = "ConnectConfig::default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+    /// the delay multiplier of the next retry
+    #[serde(default = "ConnectConfig::default_retry_backoff")]
+    pub retry_backoff: f64,
+}
+
+impl ConnectConfig {
+    #[inline]
+    fn default_timeout_ms() -> u64 {
+        5000
+    }
+
+    #[inline]
+    fn default_retry_delay_ms() -> u64 {
+        1000
+    }
+
+    #[inline]
+    fn default_retry_backoff() -> f64 {
+        2.0
+    }
+}
+
+impl Default for ConnectConfig {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            timeout_ms: Self::default_timeout_ms(),
+            keepalive_secs: None,
+            heartbeat_secs: None,
+            failover_retries: 0,
+            retry_delay_ms: Self::default_retry_delay_ms(),
+            retry_backoff: Self::default_retry_backoff(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct AuthConfig {
+    /// bearer token, send to server after connect
+    pub token: Option<String>,
+    /// login user, the password read from env FS_PASSWORD or prompt input
+    pub user: Option<String>,
+    /// custom credential kind, send to server with the credential on connect
+    pub kind: Option<String>,
+    /// custom credential, read from env FS_CREDENTIAL if not set
+    pub credential: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct TlsConfig {
     pub ca: Option<PathBuf>,
-    pub cert: PathBuf,
-    pub key: PathBuf,
+    /// server certificate sha256 fingerprint
+    pub pinned_sha256: Option<String>,
+    /// tls server name, default is the server addr host
+    pub server_name: Option<String>,
+    /// tls min version, 1.2 or 1.3, default 1.2
+    pub min_version: Option<String>,
+    /// allowed tls cipher suites, example: TLS13_AES_256_GCM_SHA384
+    pub cipher_suites: Option<Vec<String>>,
+    /// client cert, if not set, not use client certificate auth
+    pub cert: Option<PathBuf>,
+    /// client key
+    pub key: Option<PathBuf>,
 }
 
 #[inline]
@@ -33,21 +262,339 @@ pub fn get_current_exec_path() -> std::io::Result<PathBuf> {
     })
 }
 
+impl Config {
+    /// create config by command line server args
+    /// return none if not set --server
+    #[cfg(feature = "cli")]
+    #[inline]
+    pub fn from_server_args(args: ServerArgs) -> Option<Self> {
+        let addr = args.server?;
+        let tls = if args.ca.is_some()
+            || args.pinned_sha256.is_some()
+            || args.tls_server_name.is_some()
+            || args.cert.is_some()
+            || args.key.is_some()
+        {
+            Some(TlsConfig {
+                ca: args.ca,
+                pinned_sha256: args.pinned_sha256,
+                server_name: args.tls_server_name,
+                min_version: None,
+                cipher_suites: None,
+                cert: args.cert,
+                key: args.key,
+            })
+        } else {
+            None
+        };
+        Some(Self {
+            server: ServerOption::new(
+                addr,
+                args.service_name,
+                args.verify_key,
+                args.request_out_time_ms,
+            ),
+            tls,
+            proxy: None,
+            auth: None,
+            connect: Default::default(),
+            defaults: Default::default(),
+            notify: Default::default(),
+            encrypt: Default::default(),
+            audit: Default::default(),
+            jobs: Default::default(),
+        })
+    }
+}
+
+/// config file names, find in order
+const CONFIG_FILES: [&str; 5] = [
+    "config",
+    "config.toml",
+    "config.yaml",
+    "config.yml",
+    "config.json",
+];
+
+/// find config file path
+/// first work directory, second current exec path
 #[inline]
-pub async fn load_config() -> anyhow::Result<Config> {
-    let config_file = PathBuf::from("./config");
-    if config_file.exists() {
-        let config = tokio::fs::read_to_string(config_file).await?;
-        Ok(toml::from_str(&config)?)
+pub fn get_config_path() -> anyhow::Result<PathBuf> {
+    let current_exec_path = get_current_exec_path()?;
+    for dir in [PathBuf::from("./"), current_exec_path] {
+        for name in CONFIG_FILES {
+            let config_file = dir.join(name);
+            if config_file.exists() {
+                return Ok(config_file);
+            }
+        }
+    }
+    bail!("not found config");
+}
+
+/// load config, the command line args override config file
+#[cfg(feature = "cli")]
+#[inline]
+pub async fn load_config(mut args: ServerArgs) -> anyhow::Result<Config> {
+    let proxy = args.proxy.take();
+    let token = args.token.take();
+    let user = args.user.take();
+    let notify_url = args.notify_url.take();
+    let desktop_notify = args.notify;
+    let (retries, retry_delay, retry_backoff) =
+        (args.retries, args.retry_delay, args.retry_backoff);
+    let mut config = match Config::from_server_args(args) {
+        Some(config) => config,
+        None => {
+            let path = get_config_path()?;
+            let config = tokio::fs::read_to_string(&path).await?;
+            parse_config(&config, ConfigFormat::detect(&path, &config))
+                .with_context(|| format!("config:{} parse error", path.display()))?
+        }
+    };
+    if proxy.is_some() {
+        config.proxy = proxy;
+    }
+    if token.is_some() {
+        config.auth.get_or_insert_with(Default::default).token = token;
+    }
+    if user.is_some() {
+        config.auth.get_or_insert_with(Default::default).user = user;
+    }
+    if notify_url.is_some() {
+        config.notify.url = notify_url;
+    }
+    if desktop_notify {
+        config.notify.desktop = true;
+    }
+    if let Some(retries) = retries {
+        config.connect.failover_retries = retries;
+    }
+    if let Some(retry_delay) = retry_delay {
+        config.connect.retry_delay_ms = retry_delay;
+    }
+    if let Some(retry_backoff) = retry_backoff {
+        config.connect.retry_backoff = retry_backoff;
+    }
+    Ok(config)
+}
+
+/// config file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// detect format by file extension, if not found by content
+    #[inline]
+    pub fn detect(path: &Path, config: &str) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::Toml,
+            Some("yaml") | Some("yml") => Self::Yaml,
+            Some("json") => Self::Json,
+            _ => {
+                if config.trim_start().starts_with('{') {
+                    Self::Json
+                } else if toml::from_str::<toml::Table>(config).is_ok() {
+                    Self::Toml
+                } else {
+                    Self::Yaml
+                }
+            }
+        }
+    }
+}
+
+/// parse config
+/// server.addr can be a list, it will be joined by ','
+#[inline]
+pub fn parse_config(config: &str, format: ConfigFormat) -> anyhow::Result<Config> {
+    let mut value: serde_json::Value = match format {
+        ConfigFormat::Toml => serde_json::to_value(toml::from_str::<toml::Table>(config)?)?,
+        ConfigFormat::Yaml => serde_yaml::from_str(config)?,
+        ConfigFormat::Json => serde_json::from_str(config)?,
+    };
+    if let Some(addr) = value.pointer_mut("/server/addr") {
+        if let Some(addrs) = addr.as_array() {
+            let addrs = addrs
+                .iter()
+                .filter_map(|addr| addr.as_str())
+                .collect::<Vec<_>>()
+                .join(",");
+            *addr = serde_json::Value::String(addrs);
+        }
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+#[inline]
+fn load_config_document() -> anyhow::Result<(PathBuf, Document)> {
+    let path = get_config_path()?;
+    let config = std::fs::read_to_string(&path)?;
+    ensure!(
+        ConfigFormat::detect(&path, &config) == ConfigFormat::Toml,
+        "config:{} is not toml, only support edit toml config",
+        path.display()
+    );
+    let document = config
+        .parse::<Document>()
+        .with_context(|| format!("config:{} parse error", path.display()))?;
+    Ok((path, document))
+}
+
+/// get config value by key, example: server.addr
+#[inline]
+pub fn config_get(key: &str) -> anyhow::Result<()> {
+    let (_, document) = load_config_document()?;
+    let mut item = document.as_item();
+    for name in key.split('.') {
+        item = item
+            .get(name)
+            .with_context(|| format!("not found config key:{key}"))?;
+    }
+    match item {
+        Item::Value(value) => match value.as_str() {
+            Some(value) => println!("{value}"),
+            None => println!("{}", value.to_string().trim()),
+        },
+        _ => println!("{}", item.to_string().trim()),
+    }
+    Ok(())
+}
+
+/// set config value by key, example: server.addr 127.0.0.1:7556
+/// the config will be checked before save
+#[inline]
+pub fn config_set(key: &str, value: &str) -> anyhow::Result<()> {
+    let (path, mut document) = load_config_document()?;
+    let mut item = document.as_item_mut();
+    for name in key.split('.') {
+        ensure!(!name.is_empty(), "config key:{key} error");
+        // the index of the not table value panic
+        ensure!(
+            item.is_none() || item.is_table_like(),
+            "config key:{key} error, the parent of {name} is not a table"
+        );
+        item = &mut item[name];
+    }
+    // numbers and booleans keep their type, other are string
+    *item = match value.parse::<toml_edit::Value>() {
+        Ok(value) if !value.is_str() => Item::Value(value),
+        _ => toml_edit::value(value),
+    };
+
+    let config = document.to_string();
+    parse_config(&config, ConfigFormat::Toml)
+        .with_context(|| format!("config {key}={value} error"))?;
+    std::fs::write(&path, config)?;
+    Ok(())
+}
+
+/// show config file
+#[inline]
+pub fn config_show() -> anyhow::Result<()> {
+    let path = get_config_path()?;
+    println!("# {}", path.display());
+    println!("{}", std::fs::read_to_string(&path)?);
+    Ok(())
+}
+
+/// print check step result
+#[cfg(feature = "cli")]
+#[inline]
+fn check_step<T>(name: &str, result: anyhow::Result<T>) -> anyhow::Result<T> {
+    use console::style;
+    match result {
+        Ok(value) => {
+            println!("{} {}", style("[ok]").green().bold(), name);
+            Ok(value)
+        }
+        Err(err) => {
+            println!("{} {}: {:#}", style("[error]").red().bold(), name, err);
+            Err(err)
+        }
+    }
+}
+
+/// check config, tls files and try connect server
+#[cfg(feature = "cli")]
+#[inline]
+pub async fn config_check(args: ServerArgs) -> anyhow::Result<()> {
+    use crate::connect::{probe_first, split_addrs};
+    use crate::proxy::Proxy;
+    use crate::tls;
+    use std::time::Duration;
+
+    let config = check_step("load config", load_config(args).await)?;
+
+    let connector = if let Some(ref tls) = config.tls {
+        if let (Some(cert), Some(key)) = (tls.cert.as_ref(), tls.key.as_ref()) {
+            let cert_path = check_step("cert path", tls::resolve_path(cert))?;
+            let certs = check_step(
+                &format!("cert file:{}", cert_path.display()),
+                tls::load_certs(&cert_path),
+            )?;
+            let key_path = check_step("key path", tls::resolve_path(key))?;
+            let key = check_step(
+                &format!("key file:{}", key_path.display()),
+                tls::load_private_key(&key_path),
+            )?;
+            check_step(
+                &format!("client certificate chain:{}", certs.len()),
+                tls::check_client_cert(&certs, &key),
+            )?;
+        }
+        if let Some(ref ca) = tls.ca {
+            let ca_path = check_step("ca path", tls::resolve_path(ca))?;
+            check_step(
+                &format!("ca file:{}", ca_path.display()),
+                tls::load_root_store(&ca_path),
+            )?;
+        }
+        Some(check_step("tls config", tls::build_connector(tls))?)
     } else {
-        let mut current_exec_path = get_current_exec_path()?;
-        current_exec_path.push("./config");
-        if current_exec_path.exists() {
-            Ok(toml::from_str(&std::fs::read_to_string(
-                current_exec_path,
-            )?)?)
-        } else {
-            bail!("not found config");
+        None
+    };
+
+    let timeout = Duration::from_millis(config.connect.timeout_ms);
+    let proxy = check_step("proxy", Proxy::find(config.proxy.as_deref()))?;
+    let addrs = split_addrs(&config.server.addr);
+    let connected = check_step(
+        &format!("connect server:{}", addrs.join(",")),
+        probe_first(&addrs, proxy.as_ref(), timeout).await,
+    )?;
+    println!(
+        "connected: {} -> {}",
+        connected.addr, connected.connect_addr
+    );
+
+    if let Some(connector) = connector {
+        let domain = check_step(
+            "tls server name",
+            tls::server_name(config.tls.as_ref().unwrap(), &connected.addr),
+        )?;
+        let stream = check_step(
+            "tls handshake",
+            async {
+                anyhow::Ok(
+                    tokio::time::timeout(timeout, connector.connect(domain, connected.stream))
+                        .await
+                        .context("handshake timeout")??,
+                )
+            }
+            .await,
+        )?;
+        if let Some(cert) = stream
+            .get_ref()
+            .1
+            .peer_certificates()
+            .and_then(|c| c.first())
+        {
+            println!("server cert sha256: {}", tls::fingerprint(cert));
         }
     }
+    Ok(())
 }
@@ -0,0 +1,149 @@
+use anyhow::{bail, ensure, Result};
+use serde::Deserialize;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+/// the policy when the target file already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ConflictPolicy {
+    /// keep the existing file, skip the transfer
+    Skip,
+    /// overwrite the existing file
+    Overwrite,
+    /// transfer to the free name, example `file (1).bin`
+    Rename,
+    /// prompt on the terminal, fail if stdin is not a terminal
+    Ask,
+}
+
+/// the resolution of the existing target file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Skip,
+    Overwrite,
+    Rename,
+}
+
+impl ConflictPolicy {
+    /// resolve the existing target file, prompt if ask
+    #[inline]
+    pub fn resolve(self, target: &str) -> Result<Resolution> {
+        match self {
+            ConflictPolicy::Skip => Ok(Resolution::Skip),
+            ConflictPolicy::Overwrite => Ok(Resolution::Overwrite),
+            ConflictPolicy::Rename => Ok(Resolution::Rename),
+            ConflictPolicy::Ask => ask(target),
+        }
+    }
+}
+
+/// prompt the resolution of the existing file until the answer is valid
+#[inline]
+fn ask(target: &str) -> Result<Resolution> {
+    ensure!(
+        std::io::stdin().is_terminal(),
+        "file:{target} already exists, can't ask, stdin is not a terminal, set --on-conflict"
+    );
+    loop {
+        print!("file:{target} already exists, [o]verwrite/[s]kip/[r]ename? ");
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer)? == 0 {
+            bail!("file:{target} already exists, not answered");
+        }
+        match answer.trim() {
+            "o" | "O" | "overwrite" => return Ok(Resolution::Overwrite),
+            "s" | "S" | "skip" => return Ok(Resolution::Skip),
+            "r" | "R" | "rename" => return Ok(Resolution::Rename),
+            _ => continue,
+        }
+    }
+}
+
+/// the suffixed name of the path before the extension, `dir/file.bin` -> `dir/file-suffix.bin`
+#[inline]
+pub fn suffixed(path: &str, suffix: &str) -> String {
+    let (dir, name) = match path.rfind(['/', '\\']) {
+        Some(position) => path.split_at(position + 1),
+        None => ("", path),
+    };
+    match name.rfind('.') {
+        Some(position) if position > 0 => {
+            let (stem, ext) = name.split_at(position);
+            format!("{dir}{stem}{suffix}{ext}")
+        }
+        _ => format!("{dir}{name}{suffix}"),
+    }
+}
+
+/// the indexed name of the path, `dir/file.bin` -> `dir/file (1).bin`
+#[inline]
+pub fn renamed(path: &str, index: u32) -> String {
+    suffixed(path, &format!(" ({index})"))
+}
+
+/// the existing local path of the file, the other case of the file name if case insensitive
+#[inline]
+pub fn local_existing(local: &Path, case_insensitive: bool) -> Option<PathBuf> {
+    if local.exists() {
+        return Some(local.to_path_buf());
+    }
+    if !case_insensitive {
+        return None;
+    }
+    let name = local.file_name()?.to_string_lossy().to_lowercase();
+    let dir = match local.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .find(|entry| entry.file_name().to_string_lossy().to_lowercase() == name)
+        .map(|entry| local.with_file_name(entry.file_name()))
+}
+
+/// resolve the existing local file by the conflict policy, none if skip,
+/// set overwrite if the file is overwritten
+#[inline]
+pub fn resolve_local(
+    local: &Path,
+    policy: Option<ConflictPolicy>,
+    overwrite: &mut bool,
+    case_insensitive: bool,
+) -> Result<Option<PathBuf>> {
+    let Some(policy) = policy else {
+        return Ok(Some(local.to_path_buf()));
+    };
+    if *overwrite {
+        return Ok(Some(local.to_path_buf()));
+    }
+    let Some(local) = local_existing(local, case_insensitive) else {
+        return Ok(Some(local.to_path_buf()));
+    };
+    let local = local.as_path();
+    let text = local.to_string_lossy();
+    match policy.resolve(&text)? {
+        Resolution::Skip => {
+            log::info!("file:{text} already exists, skip");
+            Ok(None)
+        }
+        Resolution::Overwrite => {
+            *overwrite = true;
+            Ok(Some(local.to_path_buf()))
+        }
+        Resolution::Rename => {
+            let renamed = (1..)
+                .map(|index| PathBuf::from(renamed(&text, index)))
+                .find(|path| local_existing(path, case_insensitive).is_none())
+                .unwrap_or_default();
+            log::info!(
+                "file:{text} already exists, rename to {}",
+                renamed.display()
+            );
+            Ok(Some(renamed))
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use anyhow::{bail, Result};
+use netxclient::client::NetxClientArcDef;
+use netxclient::prelude::*;
+use socket2::{SockRef, TcpKeepalive};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{lookup_host, TcpListener, TcpStream};
+use tokio::sync::broadcast;
+
+use crate::auth;
+use crate::config::Config;
+use crate::controller::{ClientController, FileChanged, FileWriteService};
+use crate::interface_server::*;
+use crate::proxy::Proxy;
+use crate::tls;
+
+/// the server can be connected, found by the probe
+pub struct Connected {
+    /// the addr in config
+    pub addr: String,
+    /// the resolved addr, netx client connect it
+    pub connect_addr: String,
+    /// the tcp stream of the probe, only the tls check use it
+    pub stream: TcpStream,
+}
+
+/// split config server addr, support "host1:port,host2:port"
+#[inline]
+pub fn split_addrs(addr: &str) -> Vec<String> {
+    addr.split(',')
+        .map(|addr| addr.trim().to_string())
+        .filter(|addr| !addr.is_empty())
+        .collect()
+}
+
+/// probe the addrs in order by the tcp connect, every resolved ip is tried,
+/// return the first can connect, the netx client connect the selected addr again
+#[inline]
+pub async fn probe_first(
+    addrs: &[String],
+    proxy: Option<&Proxy>,
+    timeout: Duration,
+) -> Result<Connected> {
+    let mut errors = vec![];
+    for addr in addrs {
+        if let Some(proxy) = proxy.filter(|proxy| !proxy.bypass(addr)) {
+            match tokio::time::timeout(timeout, proxy.connect(addr)).await {
+                Ok(Ok(stream)) => {
+                    return Ok(Connected {
+                        addr: addr.clone(),
+                        connect_addr: addr.clone(),
+                        stream,
+                    })
+                }
+                Ok(Err(err)) => errors.push(format!("{addr}:{err}")),
+                Err(_) => errors.push(format!("{addr}:connect timeout")),
+            }
+            continue;
+        }
+
+        let socket_addrs = match lookup_host(addr.as_str()).await {
+            Ok(socket_addrs) => socket_addrs,
+            Err(err) => {
+                errors.push(format!("{addr}:{err}"));
+                continue;
+            }
+        };
+        for socket_addr in socket_addrs {
+            log::debug!("try connect {addr} -> {socket_addr}");
+            match tokio::time::timeout(timeout, TcpStream::connect(socket_addr)).await {
+                Ok(Ok(stream)) => {
+                    return Ok(Connected {
+                        addr: addr.clone(),
+                        connect_addr: socket_addr.to_string(),
+                        stream,
+                    })
+                }
+                Ok(Err(err)) => errors.push(format!("{socket_addr}:{err}")),
+                Err(_) => errors.push(format!("{socket_addr}:connect timeout")),
+            }
+        }
+    }
+    bail!("not found server can connect, {}", errors.join(", "))
+}
+
+/// set tcp keepalive
+#[inline]
+fn set_keepalive(stream: &TcpStream, keepalive: Duration) -> Result<()> {
+    SockRef::from(stream).set_tcp_keepalive(&TcpKeepalive::new().with_time(keepalive))?;
+    Ok(())
+}
+
+/// connect target, by proxy if set
+#[inline]
+async fn connect_target(
+    target: &str,
+    proxy: Option<&Proxy>,
+    timeout: Duration,
+) -> Result<TcpStream> {
+    Ok(match proxy {
+        Some(proxy) => tokio::time::timeout(timeout, proxy.connect(target)).await??,
+        None => tokio::time::timeout(timeout, TcpStream::connect(target)).await??,
+    })
+}
+
+/// listen a local port, forward every connection to target by proxy and set keepalive
+/// return the local addr, netx client connect it instead of target
+#[inline]
+pub async fn start_forward(
+    target: String,
+    proxy: Option<Proxy>,
+    timeout: Duration,
+    keepalive: Option<Duration>,
+) -> Result<SocketAddr> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let local_addr = listener.local_addr()?;
+    log::debug!("forward {} -> {}", local_addr, target);
+    tokio::spawn(async move {
+        while let Ok((mut local, _)) = listener.accept().await {
+            let proxy = proxy.clone();
+            let target = target.clone();
+            tokio::spawn(async move {
+                let mut remote = match connect_target(&target, proxy.as_ref(), timeout).await {
+                    Ok(remote) => remote,
+                    Err(err) => {
+                        log::error!("connect {target} error:{err:#}");
+                        return;
+                    }
+                };
+                if let Some(keepalive) = keepalive {
+                    if let Err(err) = set_keepalive(&remote, keepalive) {
+                        log::warn!("set tcp keepalive error:{err}");
+                    }
+                }
+                if let Err(err) = tokio::io::copy_bidirectional(&mut local, &mut remote).await {
+                    log::debug!("forward disconnect:{err}");
+                }
+            });
+        }
+    });
+    Ok(local_addr)
+}
+
+/// connect server, init netx client and authenticate
+#[inline]
+pub async fn create_client(
+    mut config: Config,
+    controller: ClientController,
+) -> Result<NetxClientArcDef> {
+    let server_addr = config.server.addr.clone();
+    let timeout = Duration::from_millis(config.connect.timeout_ms);
+    let keepalive = config.connect.keepalive_secs.map(Duration::from_secs);
+
+    // find the first server can connect
+    let proxy = Proxy::find(config.proxy.as_deref())?;
+    let connected = probe_first(&split_addrs(&server_addr), proxy.as_ref(), timeout).await?;
+    // netx client create its own connection, close the probe
+    drop(connected.stream);
+    let proxy = proxy.filter(|proxy| !proxy.bypass(&connected.addr));
+    log::debug!(
+        "select server:{} -> {}",
+        connected.addr,
+        connected.connect_addr
+    );
+    let domain = match config.tls {
+        Some(ref tls) => Some(tls::server_name(tls, &connected.addr)?),
+        None => None,
+    };
+
+    // netx client can't use proxy stream or set socket option,
+    // so connect the local forward port
+    config.server.addr = if proxy.is_some() || keepalive.is_some() {
+        let target = if proxy.is_some() {
+            connected.addr
+        } else {
+            connected.connect_addr
+        };
+        start_forward(target, proxy, timeout, keepalive)
+            .await?
+            .to_string()
+    } else {
+        connected.connect_addr
+    };
+
+    // create netx client
+    let client = {
+        if let (Some(tls), Some(domain)) = (config.tls, domain) {
+            let connector = tls::build_connector(&tls)?;
+            NetXClient::new_tls(
+                config.server,
+                DefaultSessionStore::default(),
+                domain,
+                connector,
+            )
+        } else {
+            NetXClient::new(config.server, DefaultSessionStore::default())
+        }
+    };
+    client.init(controller).await?;
+
+    // without the [auth] section, the token saved by `fsc login` in the keyring is still sent
+    auth::authenticate(&client, config.auth.unwrap_or_default(), &server_addr).await?;
+
+    if let Some(heartbeat) = config.connect.heartbeat_secs {
+        start_heartbeat(&client, Duration::from_secs(heartbeat));
+    }
+    Ok(client)
+}
+
+/// reconnect the server when the connection broken during transfer,
+/// the server addr list is tried in order, so the secondary server is used if the primary is dead
+pub struct Reconnect {
+    config: Config,
+    wfs: Arc<Actor<FileWriteService>>,
+    /// the remote file change events, shared by the reconnected clients
+    changes: broadcast::Sender<FileChanged>,
+}
+
+impl Reconnect {
+    #[inline]
+    pub fn new(config: Config, wfs: Arc<Actor<FileWriteService>>) -> Self {
+        Self {
+            config,
+            wfs,
+            changes: broadcast::channel(1024).0,
+        }
+    }
+
+    /// receive the remote file change events
+    #[inline]
+    pub fn changes(&self) -> broadcast::Receiver<FileChanged> {
+        self.changes.subscribe()
+    }
+
+    /// failover retries count
+    #[inline]
+    pub fn retries(&self) -> u32 {
+        self.config.connect.failover_retries
+    }
+
+    /// connect the server and create netx client
+    #[inline]
+    pub async fn connect(&self) -> Result<NetxClientArcDef> {
+        create_client(
+            self.config.clone(),
+            ClientController::new(self.wfs.clone(), self.changes.clone()),
+        )
+        .await
+    }
+}
+
+/// the heartbeat stop after the ping failed times, the dead client replaced by the reconnect
+const HEARTBEAT_FAILURES: u32 = 3;
+
+/// ping the server every interval, keep the session alive,
+/// stop when the client dropped or the ping keep failing
+#[inline]
+fn start_heartbeat(client: &NetxClientArcDef, interval: Duration) {
+    // not keep the client alive
+    let weak = Arc::downgrade(client);
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(interval);
+        tick.tick().await;
+        let mut failures = 0;
+        loop {
+            tick.tick().await;
+            let Some(client) = weak.upgrade() else {
+                log::debug!("the client dropped, stop heartbeat");
+                break;
+            };
+            let server = impl_struct!(client=>IFileStoreService);
+            match server.ping().await {
+                Ok(()) => failures = 0,
+                Err(err) => {
+                    failures += 1;
+                    log::warn!("heartbeat error:{err}");
+                    if failures >= HEARTBEAT_FAILURES {
+                        log::warn!("heartbeat failed {failures} times, stop");
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
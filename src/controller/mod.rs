@@ -1,10 +1,41 @@
 use anyhow::{bail, Result};
 use netxclient::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::io::SeekFrom;
 use std::sync::Arc;
 use tokio::fs::File;
 use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::broadcast;
+
+/// remote file change kind
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Create,
+    Modify,
+    Remove,
+    Rename,
+}
+
+impl ChangeKind {
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::Create => "create",
+            ChangeKind::Modify => "modify",
+            ChangeKind::Remove => "remove",
+            ChangeKind::Rename => "rename",
+        }
+    }
+}
+
+/// remote file change event
+#[derive(Serialize, Debug, Clone)]
+pub struct FileChanged {
+    pub path: String,
+    pub kind: ChangeKind,
+}
 
 /// client rpc interface
 #[build(ClientController)]
@@ -12,15 +43,19 @@ pub trait IClientController {
     /// write buff to file by key
     #[tag(2001)]
     async fn write_file_by_key(&self, key: u64, offset: u64, data: Vec<u8>);
+    /// the file of the subscribed dir changed
+    #[tag(2002)]
+    async fn file_changed(&self, path: String, kind: ChangeKind);
 }
 
 pub struct ClientController {
     fs: Arc<Actor<FileWriteService>>,
+    changes: broadcast::Sender<FileChanged>,
 }
 
 impl ClientController {
-    pub fn new(fs: Arc<Actor<FileWriteService>>) -> Self {
-        Self { fs }
+    pub fn new(fs: Arc<Actor<FileWriteService>>, changes: broadcast::Sender<FileChanged>) -> Self {
+        Self { fs, changes }
     }
 }
 
@@ -32,6 +67,13 @@ impl IClientController for ClientController {
             log::error!("write_file_by_key err:{err}");
         }
     }
+
+    #[inline]
+    async fn file_changed(&self, path: String, kind: ChangeKind) {
+        log::debug!("file changed:{path} kind:{}", kind.as_str());
+        // no subscriber, drop the event
+        let _ = self.changes.send(FileChanged { path, kind });
+    }
 }
 
 /// store fs and pipe
@@ -0,0 +1,309 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use chacha20poly1305::aead::generic_array::GenericArray;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::OsRng;
+use chacha20poly1305::{KeyInit, XChaCha20Poly1305};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::auth::{keyring_get, ENCRYPT_KEY_ENTRY};
+use crate::config::Config;
+
+/// the encrypted file header magic, followed by the stream nonce
+pub const MAGIC: &[u8; 8] = b"FSCENC01";
+/// the age encrypted file header magic
+const AGE_MAGIC: &[u8] = b"age-encryption.org/v1\n";
+/// xchacha20poly1305 stream nonce, 24 bytes nonce without the 4 bytes counter and 1 byte last flag
+const NONCE_LEN: usize = 19;
+/// plaintext chunk size
+const CHUNK: usize = 64 * 1024;
+/// poly1305 tag size
+const TAG_LEN: usize = 16;
+
+/// 256 bits file encrypt key
+#[derive(Clone)]
+pub struct Key([u8; 32]);
+
+impl std::fmt::Debug for Key {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Key(..)")
+    }
+}
+
+impl Key {
+    /// parse the 64 hex chars key
+    #[inline]
+    pub fn from_hex(key: &str) -> Result<Self> {
+        let key = hex::decode(key.trim()).context("encrypt key is not hex")?;
+        let key: [u8; 32] = key
+            .try_into()
+            .map_err(|_| anyhow!("encrypt key must be 32 bytes, 64 hex chars"))?;
+        Ok(Self(key))
+    }
+
+    #[inline]
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(GenericArray::from_slice(&self.0))
+    }
+}
+
+/// the encrypt key read from env FS_ENCRYPT_KEY, then config encrypt.key, then os keyring,
+/// none if not found
+#[inline]
+pub fn encrypt_key(config: &Config) -> Result<Option<Key>> {
+    let key = std::env::var("FS_ENCRYPT_KEY")
+        .ok()
+        .or_else(|| config.encrypt.key.clone())
+        .or_else(|| keyring_get(ENCRYPT_KEY_ENTRY));
+    if let Some(ref key) = key {
+        crate::redact::add_secret(key);
+    }
+    key.map(|key| Key::from_hex(&key)).transpose()
+}
+
+/// read until the buffer is full or eof, return the read len
+#[inline]
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..])? {
+            0 => break,
+            n => len += n,
+        }
+    }
+    Ok(len)
+}
+
+/// the file starts with the encrypted header
+#[inline]
+pub fn is_encrypted(path: &Path) -> Result<bool> {
+    let mut magic = [0; MAGIC.len()];
+    let len = read_full(&mut File::open(path)?, &mut magic)?;
+    Ok(len == MAGIC.len() && &magic == MAGIC)
+}
+
+/// encrypt the file by xchacha20poly1305 stream, the header is magic and nonce,
+/// every 64k chunk is authenticated
+#[inline]
+pub fn encrypt_file(key: &Key, src: &Path, dst: &Path) -> Result<()> {
+    let mut reader = BufReader::new(File::open(src)?);
+    let mut writer = BufWriter::new(File::create(dst)?);
+    let mut nonce = [0; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&nonce)?;
+
+    let mut encryptor = EncryptorBE32::from_aead(key.cipher(), GenericArray::from_slice(&nonce));
+    let (mut chunk, mut next) = (vec![0; CHUNK], vec![0; CHUNK]);
+    let mut len = read_full(&mut reader, &mut chunk)?;
+    loop {
+        let next_len = read_full(&mut reader, &mut next)?;
+        if next_len == 0 {
+            let block = encryptor
+                .encrypt_last(&chunk[..len])
+                .map_err(|_| anyhow!("encrypt file:{} error", src.display()))?;
+            writer.write_all(&block)?;
+            break;
+        }
+        let block = encryptor
+            .encrypt_next(&chunk[..len])
+            .map_err(|_| anyhow!("encrypt file:{} error", src.display()))?;
+        writer.write_all(&block)?;
+        std::mem::swap(&mut chunk, &mut next);
+        len = next_len;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// decrypt the encrypted file, error if the key is wrong or the file is modified
+#[inline]
+pub fn decrypt_file(key: &Key, src: &Path, dst: &Path) -> Result<()> {
+    let mut reader = BufReader::new(File::open(src)?);
+    let mut header = [0; MAGIC.len() + NONCE_LEN];
+    let len = read_full(&mut reader, &mut header)?;
+    ensure!(
+        len == header.len() && header.starts_with(MAGIC),
+        "file:{} is not encrypted",
+        src.display()
+    );
+    let nonce = GenericArray::from_slice(&header[MAGIC.len()..]);
+    let mut decryptor = DecryptorBE32::from_aead(key.cipher(), nonce);
+
+    let mut writer = BufWriter::new(File::create(dst)?);
+    let (mut chunk, mut next) = (vec![0; CHUNK + TAG_LEN], vec![0; CHUNK + TAG_LEN]);
+    let mut len = read_full(&mut reader, &mut chunk)?;
+    loop {
+        let next_len = read_full(&mut reader, &mut next)?;
+        if next_len == 0 {
+            let block = decryptor
+                .decrypt_last(&chunk[..len])
+                .map_err(|_| anyhow!("decrypt file:{} error, wrong key", src.display()))?;
+            writer.write_all(&block)?;
+            break;
+        }
+        let block = decryptor
+            .decrypt_next(&chunk[..len])
+            .map_err(|_| anyhow!("decrypt file:{} error, wrong key", src.display()))?;
+        writer.write_all(&block)?;
+        std::mem::swap(&mut chunk, &mut next);
+        len = next_len;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// age x25519 recipients, `age1...`
+#[derive(Clone, Default)]
+pub struct AgeRecipients(Vec<age::x25519::Recipient>);
+
+impl std::fmt::Debug for AgeRecipients {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(ToString::to_string))
+            .finish()
+    }
+}
+
+impl AgeRecipients {
+    #[inline]
+    pub fn parse(recipients: &[String]) -> Result<Self> {
+        recipients
+            .iter()
+            .map(|recipient| {
+                recipient
+                    .parse()
+                    .map_err(|err| anyhow!("age recipient:{recipient} error:{err}"))
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(Self)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// age x25519 identities, `AGE-SECRET-KEY-1...`
+#[derive(Clone, Default)]
+pub struct AgeIdentities(Vec<age::x25519::Identity>);
+
+impl std::fmt::Debug for AgeIdentities {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AgeIdentities({})", self.0.len())
+    }
+}
+
+impl AgeIdentities {
+    /// load the identity files, one identity per line, skip the empty and `#` comment line
+    #[inline]
+    pub fn load(files: &[PathBuf]) -> Result<Self> {
+        let mut identities = vec![];
+        for file in files {
+            let path = crate::tls::resolve_path(file)?;
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("read age identity file:{} error", path.display()))?;
+            for line in content.lines().map(str::trim) {
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let identity = line
+                    .parse()
+                    .map_err(|err| anyhow!("age identity file:{} error:{err}", path.display()))?;
+                identities.push(identity);
+            }
+        }
+        Ok(Self(identities))
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// the file starts with the age header
+#[inline]
+pub fn is_age_encrypted(path: &Path) -> Result<bool> {
+    let mut magic = [0; AGE_MAGIC.len()];
+    let len = read_full(&mut File::open(path)?, &mut magic)?;
+    Ok(len == AGE_MAGIC.len() && magic == AGE_MAGIC)
+}
+
+/// encrypt the file to the age recipients
+#[inline]
+pub fn age_encrypt_file(recipients: &AgeRecipients, src: &Path, dst: &Path) -> Result<()> {
+    let recipients = recipients
+        .0
+        .iter()
+        .map(|recipient| Box::new(recipient.clone()) as Box<dyn age::Recipient + Send>)
+        .collect();
+    let encryptor = age::Encryptor::with_recipients(recipients).context("not set age recipient")?;
+    let mut reader = BufReader::new(File::open(src)?);
+    let mut writer = encryptor.wrap_output(BufWriter::new(File::create(dst)?))?;
+    std::io::copy(&mut reader, &mut writer)?;
+    writer.finish()?.flush()?;
+    Ok(())
+}
+
+/// decrypt the age encrypted file by the identities
+#[inline]
+pub fn age_decrypt_file(identities: &AgeIdentities, src: &Path, dst: &Path) -> Result<()> {
+    ensure!(
+        !identities.0.is_empty(),
+        "file:{} is age encrypted, not found age identity, set config encrypt.identities",
+        src.display()
+    );
+    let decryptor = match age::Decryptor::new(BufReader::new(File::open(src)?))? {
+        age::Decryptor::Recipients(decryptor) => decryptor,
+        _ => bail!("file:{} is age passphrase encrypted", src.display()),
+    };
+    let mut reader = decryptor
+        .decrypt(
+            identities
+                .0
+                .iter()
+                .map(|identity| identity as &dyn age::Identity),
+        )
+        .with_context(|| format!("age decrypt file:{} error", src.display()))?;
+    let mut writer = BufWriter::new(File::create(dst)?);
+    std::io::copy(&mut reader, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// temp file removed on drop
+pub struct TempFile(PathBuf);
+
+impl TempFile {
+    /// temp file path in the os temp dir
+    #[inline]
+    pub fn new(name: &str) -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+        Self(std::env::temp_dir().join(format!("fsc-{}-{count}-{name}", std::process::id())))
+    }
+
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    #[inline]
+    fn drop(&mut self) {
+        if self.0.exists() {
+            if let Err(err) = std::fs::remove_file(&self.0) {
+                log::warn!("remove temp file:{} error:{err}", self.0.display());
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use anyhow::{ensure, Result};
+use console::style;
+use regex::bytes::{Regex, RegexBuilder};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio_util::sync::CancellationToken;
+
+use fsc::output::print_json;
+use fsc::FileStoreClient;
+
+/// the matched line
+#[derive(Serialize)]
+struct GrepMatch {
+    path: String,
+    line: u64,
+    text: String,
+}
+
+/// the grep options
+pub struct Grep {
+    pub regex: Regex,
+    /// skip the larger files of the recursive search
+    pub max_size: u64,
+    pub json: bool,
+    pub quiet: bool,
+}
+
+impl Grep {
+    #[inline]
+    pub fn new(
+        pattern: &str,
+        ignore_case: bool,
+        max_size: u64,
+        json: bool,
+        quiet: bool,
+    ) -> Result<Self> {
+        Ok(Self {
+            regex: RegexBuilder::new(pattern)
+                .case_insensitive(ignore_case)
+                .build()?,
+            max_size,
+            json,
+            quiet,
+        })
+    }
+
+    /// stream the remote file lines through the regex, stop at the binary data
+    #[inline]
+    async fn grep_file(
+        &self,
+        client: &FileStoreClient,
+        path: &Path,
+        matches: &mut Vec<GrepMatch>,
+    ) -> Result<()> {
+        let mut reader = BufReader::new(client.open(path).await?);
+        let mut data = vec![];
+        let mut line = 0;
+        loop {
+            data.clear();
+            if reader.read_until(b'\n', &mut data).await? == 0 {
+                break;
+            }
+            line += 1;
+            if data.contains(&0) {
+                log::debug!("grep skip binary file:{}", path.display());
+                break;
+            }
+            if !self.regex.is_match(&data) {
+                continue;
+            }
+            let text = String::from_utf8_lossy(&data)
+                .trim_end_matches(['\r', '\n'])
+                .to_string();
+            if !self.json && !self.quiet {
+                println!(
+                    "{}:{}:{text}",
+                    style(path.display()).magenta(),
+                    style(line).green()
+                );
+            }
+            matches.push(GrepMatch {
+                path: path.to_string_lossy().to_string(),
+                line,
+                text,
+            });
+        }
+        Ok(())
+    }
+
+    /// search the remote file, or the files of the directory tree if recursive
+    #[inline]
+    pub async fn run(
+        &self,
+        client: &FileStoreClient,
+        path: PathBuf,
+        recursive: bool,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        let files = if recursive {
+            client
+                .list_recursive(&path, None)
+                .await?
+                .into_iter()
+                .filter(|(_, entry)| entry.file_type != 1 && entry.size <= self.max_size)
+                .map(|(path, _)| path)
+                .collect()
+        } else {
+            vec![path.clone()]
+        };
+        let mut matches = vec![];
+        for file in files.iter() {
+            ensure!(!cancel.is_cancelled(), "grep cancelled");
+            if let Err(err) = self.grep_file(client, file, &mut matches).await {
+                if !recursive {
+                    return Err(err);
+                }
+                log::warn!("grep file:{} error:{err:#}", file.display());
+            }
+        }
+        if self.json {
+            print_json(&matches)?;
+        }
+        ensure!(
+            !matches.is_empty(),
+            "pattern:{} not found in {}",
+            self.regex,
+            path.display()
+        );
+        Ok(())
+    }
+}
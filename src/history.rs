@@ -0,0 +1,195 @@
+use anyhow::{Context, Result};
+use chrono::Local;
+use console::style;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use fsc::config::{get_config_path, get_current_exec_path};
+use fsc::output::TransferSummary;
+
+/// env of the run id, example the ci job url
+const RUN_ID_ENV: &str = "FS_RUN_ID";
+
+/// the transfer record
+#[derive(Serialize)]
+pub struct Record {
+    pub id: i64,
+    pub time: String,
+    pub action: String,
+    pub local: String,
+    pub remote: String,
+    pub files: i64,
+    pub size: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    pub elapsed_ms: i64,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// the command line of the run
+    pub command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+}
+
+impl Record {
+    #[inline]
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            time: row.get("time")?,
+            action: row.get("action")?,
+            local: row.get("local")?,
+            remote: row.get("remote")?,
+            files: row.get("files")?,
+            size: row.get("size")?,
+            hash: row.get("hash")?,
+            elapsed_ms: row.get("elapsed_ms")?,
+            success: row.get("success")?,
+            error: row.get("error")?,
+            command: row.get("command")?,
+            run_id: row.get("run_id")?,
+        })
+    }
+
+    /// print the record line
+    #[inline]
+    pub fn print_line(&self) {
+        use humansize::{format_size, WINDOWS};
+        let result = if self.success {
+            style("ok").green()
+        } else {
+            style("failed").red()
+        };
+        println!(
+            "{:>5} {} {:<10} {:<6} {:>10} {} -> {}",
+            style(self.id).cyan(),
+            self.time,
+            self.action,
+            result,
+            format_size(self.size as u64, WINDOWS),
+            self.local,
+            self.remote
+        );
+    }
+
+    /// print the record detail
+    #[inline]
+    pub fn print(&self) {
+        println!("id:{}", self.id);
+        println!("time:{}", self.time);
+        println!("action:{}", self.action);
+        println!("local:{}", self.local);
+        println!("remote:{}", self.remote);
+        println!("files:{}", self.files);
+        println!("size:{}", self.size);
+        println!("hash:{}", self.hash.as_deref().unwrap_or_default());
+        println!("elapsed:{:.1}s", self.elapsed_ms as f64 / 1000.0);
+        println!("success:{}", self.success);
+        if let Some(ref error) = self.error {
+            println!("error:{}", style(error).red());
+        }
+        println!("command:{}", self.command);
+        println!("run id:{}", self.run_id.as_deref().unwrap_or_default());
+    }
+}
+
+/// the history db file, next to the config file
+#[inline]
+fn db_path() -> Result<PathBuf> {
+    let dir = match get_config_path() {
+        Ok(config) => config.parent().map(Path::to_path_buf).unwrap_or_default(),
+        Err(_) => get_current_exec_path()?,
+    };
+    Ok(dir.join("history.db"))
+}
+
+/// open the history db, create the table if not exists
+#[inline]
+fn open() -> Result<Connection> {
+    let path = db_path()?;
+    let conn = Connection::open(&path)
+        .with_context(|| format!("open history db:{} error", path.display()))?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS transfers (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            time TEXT NOT NULL,
+            action TEXT NOT NULL,
+            local TEXT NOT NULL,
+            remote TEXT NOT NULL,
+            files INTEGER NOT NULL,
+            size INTEGER NOT NULL,
+            hash TEXT,
+            elapsed_ms INTEGER NOT NULL,
+            success INTEGER NOT NULL,
+            error TEXT,
+            command TEXT NOT NULL,
+            run_id TEXT
+        );
+        CREATE INDEX IF NOT EXISTS transfers_remote ON transfers (remote);",
+    )?;
+    Ok(conn)
+}
+
+/// save the transfer summary to the history db
+#[inline]
+fn insert(summary: &TransferSummary) -> Result<()> {
+    let command = std::env::args().collect::<Vec<_>>().join(" ");
+    let run_id = std::env::var(RUN_ID_ENV).ok();
+    open()?.execute(
+        "INSERT INTO transfers (time, action, local, remote, files, size, hash, elapsed_ms, success, error, command, run_id)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            summary.action,
+            summary.local,
+            summary.remote,
+            summary.files as i64,
+            summary.size as i64,
+            summary.hash,
+            summary.elapsed_ms as i64,
+            summary.success,
+            summary.error,
+            command,
+            run_id
+        ],
+    )?;
+    Ok(())
+}
+
+/// record the transfer summary, the history error not fail the transfer
+#[inline]
+pub fn record(summary: &TransferSummary) {
+    if let Err(err) = insert(summary) {
+        log::warn!("record transfer history error:{err:#}");
+    }
+}
+
+/// the last records, filter the local or remote path contains the path
+#[inline]
+pub fn list(path: Option<&str>, limit: usize) -> Result<Vec<Record>> {
+    let conn = open()?;
+    let mut statement = conn.prepare(
+        "SELECT * FROM transfers
+        WHERE ?1 IS NULL OR instr(local, ?1) > 0 OR instr(remote, ?1) > 0
+        ORDER BY id DESC LIMIT ?2",
+    )?;
+    let records = statement
+        .query_map(params![path, limit as i64], Record::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(records)
+}
+
+/// the record of the id
+#[inline]
+pub fn show(id: i64) -> Result<Option<Record>> {
+    let record = open()?
+        .query_row(
+            "SELECT * FROM transfers WHERE id = ?1",
+            params![id],
+            Record::from_row,
+        )
+        .optional()?;
+    Ok(record)
+}
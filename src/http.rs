@@ -0,0 +1,163 @@
+use anyhow::Result;
+use hyper::header::{ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, RANGE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, HeaderMap, Method, Request, Response, StatusCode};
+use percent_encoding::percent_decode_str;
+use std::convert::Infallible;
+use std::io::SeekFrom;
+use std::net::SocketAddr;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+use fsc::interface_server::content_type;
+use fsc::output::EntryOutput;
+use fsc::FileStoreClient;
+
+/// http gateway of the remote dir
+struct Gateway {
+    client: Arc<FileStoreClient>,
+    root: PathBuf,
+}
+
+impl Gateway {
+    /// remote path of the relative path in root, the parent dir is not allowed
+    #[inline]
+    fn remote_path(&self, path: &str) -> Option<PathBuf> {
+        let path = Path::new(path.trim_start_matches('/'));
+        if path
+            .components()
+            .any(|component| matches!(component, Component::ParentDir))
+        {
+            return None;
+        }
+        Some(PathBuf::from(
+            self.root.join(path).to_string_lossy().replace('\\', "/"),
+        ))
+    }
+
+    /// GET /files/<path> download file, GET /ls/<dir> list dir as json
+    #[inline]
+    async fn handle(&self, req: Request<Body>) -> Result<Response<Body>> {
+        if req.method() != Method::GET && req.method() != Method::HEAD {
+            return Ok(status(StatusCode::METHOD_NOT_ALLOWED, "only GET and HEAD"));
+        }
+        let path = percent_decode_str(req.uri().path()).decode_utf8()?;
+        let (file, dir) = (path.strip_prefix("/files/"), path.strip_prefix("/ls"));
+        match (file, dir) {
+            (Some(file), _) => match self.remote_path(file) {
+                Some(file) => {
+                    let head = req.method() == Method::HEAD;
+                    self.file(&file, req.headers(), head).await
+                }
+                None => Ok(status(StatusCode::BAD_REQUEST, "invalid path")),
+            },
+            (None, Some(dir)) if dir.is_empty() || dir.starts_with('/') => {
+                match self.remote_path(dir) {
+                    Some(dir) => self.list(&dir).await,
+                    None => Ok(status(StatusCode::BAD_REQUEST, "invalid path")),
+                }
+            }
+            _ => Ok(status(StatusCode::NOT_FOUND, "not found")),
+        }
+    }
+
+    /// download the file, support single byte range
+    #[inline]
+    async fn file(&self, file: &Path, headers: &HeaderMap, head: bool) -> Result<Response<Body>> {
+        let mut reader = match self.client.open(file).await {
+            Ok(reader) => reader,
+            Err(err) => return Ok(status(StatusCode::NOT_FOUND, err.to_string())),
+        };
+        let size = reader.size();
+        let range = headers.get(RANGE).and_then(|range| range.to_str().ok());
+        let (start, end) = match range {
+            Some(range) => match parse_range(range, size) {
+                Some(range) => range,
+                None => {
+                    return Ok(Response::builder()
+                        .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                        .header(CONTENT_RANGE, format!("bytes */{size}"))
+                        .body(Body::empty())?)
+                }
+            },
+            None => (0, size.saturating_sub(1)),
+        };
+        let len = if size == 0 { 0 } else { end - start + 1 };
+
+        let mut response = Response::builder()
+            .header(ACCEPT_RANGES, "bytes")
+            .header(CONTENT_TYPE, content_type(file))
+            .header(CONTENT_LENGTH, len);
+        if range.is_some() {
+            response = response
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(CONTENT_RANGE, format!("bytes {start}-{end}/{size}"));
+        }
+        if head {
+            return Ok(response.body(Body::empty())?);
+        }
+        log::debug!("http pull file:{} range:{start}-{end}", file.display());
+        reader.seek(SeekFrom::Start(start)).await?;
+        Ok(response.body(Body::wrap_stream(ReaderStream::new(reader.take(len))))?)
+    }
+
+    /// list the dir entries as json
+    #[inline]
+    async fn list(&self, dir: &Path) -> Result<Response<Body>> {
+        let mut entries = match self.client.list(dir).await {
+            Ok(entries) => entries,
+            Err(err) => return Ok(status(StatusCode::NOT_FOUND, err.to_string())),
+        };
+        entries.sort_by(|a, b| b.file_type.cmp(&a.file_type).then(a.name.cmp(&b.name)));
+        let json = serde_json::to_vec(&entries.iter().map(EntryOutput::from).collect::<Vec<_>>())?;
+        Ok(Response::builder()
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(json))?)
+    }
+}
+
+/// parse the single byte range, return the start and end (inclusive)
+#[inline]
+fn parse_range(range: &str, size: u64) -> Option<(u64, u64)> {
+    let (start, end) = range.trim().strip_prefix("bytes=")?.split_once('-')?;
+    let last = size.checked_sub(1)?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => (size - suffix.parse::<u64>().ok()?.min(size), last),
+        (start, "") => (start.parse().ok()?, last),
+        (start, end) => (start.parse().ok()?, end.parse::<u64>().ok()?.min(last)),
+    };
+    (start <= end).then_some((start, end))
+}
+
+#[inline]
+fn status(code: StatusCode, msg: impl Into<Body>) -> Response<Body> {
+    let mut response = Response::new(msg.into());
+    *response.status_mut() = code;
+    response
+}
+
+/// serve the remote dir over http, GET /files/<path> and GET /ls/<dir>
+#[inline]
+pub async fn serve(client: Arc<FileStoreClient>, root: PathBuf, addr: SocketAddr) -> Result<()> {
+    let gateway = Arc::new(Gateway { client, root });
+    let make_service = make_service_fn(move |_| {
+        let gateway = gateway.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let gateway = gateway.clone();
+                async move {
+                    let response = gateway.handle(req).await.unwrap_or_else(|err| {
+                        log::error!("http gateway error:{err}");
+                        status(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+                    });
+                    Ok::<_, Infallible>(response)
+                }
+            }))
+        }
+    });
+    log::info!("http gateway listen on http://{addr}");
+    hyper::Server::try_bind(&addr)?.serve(make_service).await?;
+    Ok(())
+}
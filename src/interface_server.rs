@@ -4,6 +4,8 @@ use std::path::Path;
 use std::path::PathBuf;
 use std::time::SystemTime;
 
+use crate::checksum::HashAlgorithm;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Entry {
     /// 0=file 1=directory
@@ -11,6 +13,18 @@ pub struct Entry {
     pub name: String,
     pub size: u64,
     pub create_time: SystemTime,
+    /// the last modified time, none if the server not know it,
+    /// the struct is serialized by the field order, the server must send the field
+    #[serde(default)]
+    pub modify_time: Option<SystemTime>,
+}
+
+impl Entry {
+    /// the modified time, the create time if the server not know it
+    #[inline]
+    pub fn modified(&self) -> SystemTime {
+        self.modify_time.unwrap_or(self.create_time)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -21,6 +35,56 @@ pub struct FileInfo {
     pub b3: Option<String>,
     pub sha256: Option<String>,
     pub can_modify: bool,
+    /// none if the server not compute it, the client fill it by get_file_checksums,
+    /// the struct is serialized by the field order, the server must send the field
+    #[serde(default)]
+    pub sha512: Option<String>,
+    /// the server detected content type by the magic bytes, none guess by the file extension,
+    /// the struct is serialized by the field order, the server must send the field
+    #[serde(default)]
+    pub content_type: Option<String>,
+}
+
+impl FileInfo {
+    /// the server detected content type, or guess by the file extension
+    #[inline]
+    pub fn content_type(&self) -> String {
+        match self.content_type {
+            Some(ref content_type) => content_type.clone(),
+            None => content_type(Path::new(&self.name)),
+        }
+    }
+}
+
+/// the remote space of the dir
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Quota {
+    /// the bytes can be pushed, the quota left or the free disk space
+    pub available: u64,
+    /// the quota or the disk size
+    pub total: u64,
+}
+
+/// the server capabilities, learned when connected
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct Capabilities {
+    /// the block size of the best throughput, 0 not set
+    #[serde(default)]
+    pub preferred_block: u64,
+    /// the max block size of the write and read, 0 not limit
+    #[serde(default)]
+    pub max_block: u64,
+    /// the server support lock_lease and renew_lock
+    #[serde(default)]
+    pub lock_lease: bool,
+}
+
+/// guess the content type by the file extension, application/octet-stream if unknown
+#[inline]
+pub fn content_type(path: &Path) -> String {
+    mime_guess::from_path(path)
+        .first_or_octet_stream()
+        .to_string()
 }
 
 /// service interface
@@ -90,4 +154,109 @@ pub trait IFileStoreService {
     /// finish write key
     #[tag(1012)]
     async fn finish_read_key(&self, key: u64);
+    /// authenticate by bearer token
+    #[tag(1013)]
+    async fn authenticate(&self, token: &str) -> anyhow::Result<()>;
+    /// login by user and password
+    #[tag(1014)]
+    async fn login(&self, user: &str, password: &str) -> anyhow::Result<()>;
+    /// heartbeat, keep the session alive
+    #[tag(1015)]
+    async fn ping(&self) -> anyhow::Result<()>;
+    /// resume push file, if the file is pushing, return the write key and the written size,
+    /// otherwise same as push
+    #[tag(1016)]
+    async fn resume_push(
+        &self,
+        filename: &str,
+        size: u64,
+        hash: String,
+        overwrite: bool,
+    ) -> anyhow::Result<(u64, u64)>;
+    /// session handshake after connect, send the custom credential kind and credential
+    #[tag(1017)]
+    async fn on_connect(&self, kind: &str, credential: &str) -> anyhow::Result<()>;
+    /// remove file or directory
+    #[tag(1018)]
+    async fn remove(&self, path: &Path) -> anyhow::Result<()>;
+    /// rename file or directory
+    #[tag(1019)]
+    async fn rename(&self, from: &Path, to: &Path) -> anyhow::Result<()>;
+    /// create push file, the size and hash is unknown until finish,
+    /// used to stream the pipe or generated data, then write and create_push_finish
+    /// return: file write key
+    #[tag(1020)]
+    async fn create_push(&self, filename: &str, overwrite: bool) -> anyhow::Result<u64>;
+    /// finish the created push, check the size and BLAKE3 hash
+    #[tag(1021)]
+    async fn create_push_finish(&self, key: u64, size: u64, hash: String) -> anyhow::Result<()>;
+    /// cancel the push, remove the unfinished file and release the write key
+    #[tag(1022)]
+    async fn cancel_push(&self, key: u64);
+    /// subscribe the file changes of the dir, the server call the client file_changed
+    #[tag(1023)]
+    async fn subscribe(&self, dir: &Path) -> anyhow::Result<()>;
+    /// recompute the file BLAKE3 from the disk, not use the stored hash
+    #[tag(1024)]
+    async fn rehash(&self, path: &Path) -> anyhow::Result<String>;
+    /// create multipart push, the part n is written at n * part_size
+    /// return: file write key, used by upload_part and complete_multipart, cancel_push abort it
+    #[tag(1025)]
+    async fn create_multipart(
+        &self,
+        filename: &str,
+        size: u64,
+        hash: String,
+        part_size: u64,
+        overwrite: bool,
+    ) -> anyhow::Result<u64>;
+    /// upload the part, the parts can be uploaded in any order and upload again
+    #[tag(1026)]
+    async fn upload_part(&self, key: u64, part: u32, data: &[u8]) -> anyhow::Result<()>;
+    /// complete the multipart push, check the BLAKE3 of every part and the file
+    #[tag(1027)]
+    async fn complete_multipart(&self, key: u64, part_hashes: Vec<String>) -> anyhow::Result<()>;
+    /// get the files info in one call, the error message of the missing file
+    #[tag(1028)]
+    async fn get_files_info(
+        &self,
+        paths: Vec<PathBuf>,
+        blake3: bool,
+        sha256: bool,
+    ) -> anyhow::Result<Vec<Result<FileInfo, String>>>;
+    /// list the directory tree in one call, the path is joined with the directory path
+    /// max_depth: 1 only the directory entries, 0 unlimited
+    #[tag(1029)]
+    async fn list_recursive(
+        &self,
+        path: PathBuf,
+        max_depth: u32,
+    ) -> anyhow::Result<Vec<(PathBuf, Entry)>>;
+    /// compute the file checksums of the algorithms, return the hex in the algorithms order
+    #[tag(1030)]
+    async fn get_file_checksums(
+        &self,
+        path: &Path,
+        algorithms: Vec<HashAlgorithm>,
+    ) -> anyhow::Result<Vec<String>>;
+    /// copy the file on the server, the target keep the source size and hash
+    #[tag(1031)]
+    async fn copy(&self, from: &Path, to: &Path, overwrite: bool) -> anyhow::Result<()>;
+    /// lock the filenames can be push, the lock expire after ttl secs if not renewed
+    #[tag(1032)]
+    async fn lock_lease(
+        &self,
+        filenames: &[String],
+        overwrite: bool,
+        ttl_secs: u64,
+    ) -> anyhow::Result<(bool, String)>;
+    /// renew the lock lease of the filenames for ttl secs
+    #[tag(1033)]
+    async fn renew_lock(&self, filenames: &[String], ttl_secs: u64) -> anyhow::Result<()>;
+    /// get the available space of the dir
+    #[tag(1034)]
+    async fn get_quota(&self, dir: &Path) -> anyhow::Result<Quota>;
+    /// get the server capabilities, the old server not support
+    #[tag(1035)]
+    async fn get_capabilities(&self) -> anyhow::Result<Capabilities>;
 }
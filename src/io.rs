@@ -0,0 +1,325 @@
+use netxclient::client::NetxClientArcDef;
+use netxclient::prelude::*;
+use std::future::Future;
+use std::io::SeekFrom;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+
+use crate::interface_server::*;
+use crate::retry::RetryPolicy;
+
+/// remote read block size, the next block is read ahead
+pub const READ_BLOCK: usize = 256 * 1024;
+
+type ReadFuture = Pin<Box<dyn Future<Output = anyhow::Result<Vec<u8>>> + Send>>;
+type WriteFuture = Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>>;
+
+/// remote file reader, implement AsyncRead and AsyncSeek
+/// read the file by block, and read ahead the next block
+pub struct RemoteReader {
+    client: NetxClientArcDef,
+    key: u64,
+    size: u64,
+    block: usize,
+    /// current read position
+    position: u64,
+    /// the read block and the offset of it
+    buffer: Vec<u8>,
+    buffer_offset: u64,
+    /// the reading block offset and future
+    reading: Option<(u64, ReadFuture)>,
+    /// the read block retry
+    retry: RetryPolicy,
+}
+
+impl RemoteReader {
+    #[inline]
+    pub(crate) fn new(
+        client: NetxClientArcDef,
+        key: u64,
+        size: u64,
+        block: usize,
+        retry: RetryPolicy,
+    ) -> Self {
+        Self {
+            client,
+            key,
+            size,
+            block,
+            retry,
+            position: 0,
+            buffer: vec![],
+            buffer_offset: 0,
+            reading: None,
+        }
+    }
+
+    /// remote file size
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// start read the block at offset
+    #[inline]
+    fn start_read(&mut self, offset: u64) {
+        let client = self.client.clone();
+        let (key, block, retry) = (self.key, self.block, self.retry);
+        self.reading = Some((
+            offset,
+            Box::pin(async move {
+                let server = impl_struct!(client=>IFileStoreService);
+                retry.run("read", || server.read(key, offset, block)).await
+            }),
+        ));
+    }
+
+    /// read ahead the next block of the buffer
+    #[inline]
+    fn read_ahead(&mut self) {
+        let next = self.buffer_offset + self.buffer.len() as u64;
+        if self.reading.is_none() && next < self.size {
+            self.start_read(next);
+        }
+    }
+}
+
+impl AsyncRead for RemoteReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.position >= this.size || buf.remaining() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+
+            let buffer_end = this.buffer_offset + this.buffer.len() as u64;
+            if this.position >= this.buffer_offset && this.position < buffer_end {
+                let start = (this.position - this.buffer_offset) as usize;
+                let len = buf.remaining().min(this.buffer.len() - start);
+                buf.put_slice(&this.buffer[start..start + len]);
+                this.position += len as u64;
+                this.read_ahead();
+                return Poll::Ready(Ok(()));
+            }
+
+            // the reading block is not the position, seek happened
+            if !matches!(this.reading, Some((offset, _)) if offset == this.position) {
+                let position = this.position;
+                this.start_read(position);
+            }
+            let (offset, reading) = this.reading.as_mut().unwrap();
+            let offset = *offset;
+            let result = ready!(reading.as_mut().poll(cx));
+            this.reading = None;
+            match result {
+                Ok(data) if data.is_empty() => return Poll::Ready(Ok(())),
+                Ok(data) => {
+                    this.buffer = data;
+                    this.buffer_offset = offset;
+                    this.read_ahead();
+                }
+                Err(err) => return Poll::Ready(Err(to_io_error(err))),
+            }
+        }
+    }
+}
+
+impl AsyncSeek for RemoteReader {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> std::io::Result<()> {
+        let this = self.get_mut();
+        let position = match position {
+            SeekFrom::Start(position) => Some(position),
+            SeekFrom::End(offset) => this.size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => this.position.checked_add_signed(offset),
+        };
+        match position {
+            Some(position) => {
+                this.position = position;
+                Ok(())
+            }
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+
+    #[inline]
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<u64>> {
+        Poll::Ready(Ok(self.position))
+    }
+}
+
+impl Drop for RemoteReader {
+    #[inline]
+    fn drop(&mut self) {
+        // release the read key on the server
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let client = self.client.clone();
+            let key = self.key;
+            handle.spawn(async move {
+                let server = impl_struct!(client=>IFileStoreService);
+                server.finish_read_key(key).await;
+            });
+        }
+    }
+}
+
+/// remote file writer, implement AsyncWrite
+/// buffer the data into blocks and write, shutdown finish the file with size and BLAKE3 hash,
+/// drop without shutdown cancel the push
+pub struct RemoteWriter {
+    client: NetxClientArcDef,
+    key: u64,
+    block: usize,
+    r#async: bool,
+    buffer: Vec<u8>,
+    /// the written size, include the buffer
+    size: u64,
+    hasher: blake3::Hasher,
+    writing: Option<WriteFuture>,
+    finishing: Option<WriteFuture>,
+    finished: bool,
+}
+
+impl RemoteWriter {
+    #[inline]
+    pub(crate) fn new(client: NetxClientArcDef, key: u64, block: usize, r#async: bool) -> Self {
+        Self {
+            client,
+            key,
+            block,
+            r#async,
+            buffer: Vec::with_capacity(block),
+            size: 0,
+            hasher: blake3::Hasher::new(),
+            writing: None,
+            finishing: None,
+            finished: false,
+        }
+    }
+
+    /// the written size
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// the BLAKE3 of the written data
+    #[inline]
+    pub fn hash(&self) -> String {
+        self.hasher.finalize().to_hex().to_string()
+    }
+
+    /// start write the buffer block
+    #[inline]
+    fn start_write(&mut self) {
+        let client = self.client.clone();
+        let (key, r#async) = (self.key, self.r#async);
+        let offset = self.size - self.buffer.len() as u64;
+        let data = std::mem::replace(&mut self.buffer, Vec::with_capacity(self.block));
+        self.writing = Some(Box::pin(async move {
+            let server = impl_struct!(client=>IFileStoreService);
+            if r#async {
+                server.write_offset(key, offset, &data).await;
+                Ok(())
+            } else {
+                server.write(key, &data).await
+            }
+        }));
+    }
+
+    /// wait the writing block finish
+    #[inline]
+    fn poll_writing(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        if let Some(ref mut writing) = self.writing {
+            let result = ready!(writing.as_mut().poll(cx));
+            self.writing = None;
+            result.map_err(to_io_error)?;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for RemoteWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_writing(cx))?;
+        let len = buf.len().min(this.block - this.buffer.len());
+        this.buffer.extend_from_slice(&buf[..len]);
+        this.hasher.update(&buf[..len]);
+        this.size += len as u64;
+        if this.buffer.len() >= this.block {
+            this.start_write();
+        }
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_writing(cx))?;
+        if !this.buffer.is_empty() {
+            this.start_write();
+            ready!(this.poll_writing(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        if this.finishing.is_none() {
+            ready!(Pin::new(&mut *this).poll_flush(cx))?;
+            let client = this.client.clone();
+            let (key, size, r#async) = (this.key, this.size, this.r#async);
+            let hash = this.hasher.finalize().to_hex().to_string();
+            this.finishing = Some(Box::pin(async move {
+                let server = impl_struct!(client=>IFileStoreService);
+                if r#async {
+                    let mut retry_count = 0;
+                    while !server.check_finish(key).await? && retry_count < 20 {
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                        retry_count += 1;
+                    }
+                }
+                server.create_push_finish(key, size, hash).await
+            }));
+        }
+        let finishing = this.finishing.as_mut().unwrap();
+        ready!(finishing.as_mut().poll(cx)).map_err(to_io_error)?;
+        this.finished = true;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for RemoteWriter {
+    #[inline]
+    fn drop(&mut self) {
+        // drop without shutdown, cancel the push on the server
+        if self.finished {
+            return;
+        }
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let client = self.client.clone();
+            let key = self.key;
+            handle.spawn(async move {
+                let server = impl_struct!(client=>IFileStoreService);
+                server.cancel_push(key).await;
+            });
+        }
+    }
+}
+
+#[inline]
+fn to_io_error(err: anyhow::Error) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::Other, err)
+}
@@ -0,0 +1,26 @@
+pub mod audit;
+pub mod auth;
+pub mod checksum;
+#[cfg(feature = "cli")]
+pub mod clap_struct;
+pub mod client;
+pub mod config;
+pub mod conflict;
+pub mod connect;
+pub mod controller;
+pub mod crypto;
+pub mod interface_server;
+pub mod io;
+pub mod manifest;
+pub mod output;
+pub mod proxy;
+#[cfg(feature = "python")]
+mod python;
+pub mod redact;
+pub mod retry;
+pub mod rewrite;
+pub mod sparse;
+pub mod tls;
+pub mod tune;
+
+pub use client::{FileStoreClient, TransferEvent, TransferOptions};
@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use log::LevelFilter;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use fsc::clap_struct::{ColorMode, LogArgs};
+use fsc::redact::redact;
+
+/// rotate the log file when it is larger than 10MB
+const LOG_FILE_MAX_SIZE: u64 = 10 * 1024 * 1024;
+/// keep the rotated log files: log.1 .. log.5
+const LOG_FILE_KEEP: usize = 5;
+
+/// init logger by command line flags, env RUST_LOG override it
+/// default warn, -v info, -vv debug, -vvv trace, -q error
+#[inline]
+pub fn init(args: &LogArgs, quiet: bool, color: ColorMode) -> Result<()> {
+    let level = match args.log_level {
+        Some(level) => level,
+        None if quiet => LevelFilter::Error,
+        None => match args.verbose {
+            0 => LevelFilter::Warn,
+            1 => LevelFilter::Info,
+            2 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        },
+    };
+
+    let mut builder = env_logger::Builder::new();
+    builder
+        .filter_level(level)
+        .filter_module("rustls", LevelFilter::Debug.min(level))
+        .filter_module("mio", LevelFilter::Debug.min(level))
+        .parse_default_env()
+        .write_style(match color {
+            ColorMode::Always => env_logger::WriteStyle::Always,
+            ColorMode::Never => env_logger::WriteStyle::Never,
+            // the pipe target is not a terminal, so force the color of the stderr
+            ColorMode::Auto if console::colors_enabled_stderr() => env_logger::WriteStyle::Always,
+            ColorMode::Auto => env_logger::WriteStyle::Never,
+        });
+
+    if let Some(ref path) = args.log_file {
+        let file = RotateFile::open(path.clone())?;
+        builder
+            .write_style(env_logger::WriteStyle::Never)
+            .target(env_logger::Target::Pipe(Box::new(Redact(file))));
+    } else {
+        builder.target(env_logger::Target::Pipe(Box::new(
+            Redact(std::io::stderr()),
+        )));
+    }
+    builder.init();
+    Ok(())
+}
+
+/// log file, rotate when the size is larger than LOG_FILE_MAX_SIZE
+struct RotateFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl RotateFile {
+    #[inline]
+    fn open(path: PathBuf) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("open log file:{} error", path.display()))?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size })
+    }
+
+    /// rotated file path, log -> log.1
+    #[inline]
+    fn rotated(path: &Path, index: usize) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{index}"));
+        PathBuf::from(name)
+    }
+
+    /// log.4 -> log.5, .. , log -> log.1, then create new log
+    #[inline]
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+        for index in (1..LOG_FILE_KEEP).rev() {
+            let from = Self::rotated(&self.path, index);
+            if from.exists() {
+                std::fs::rename(&from, Self::rotated(&self.path, index + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, Self::rotated(&self.path, 1))?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotateFile {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.size + buf.len() as u64 > LOG_FILE_MAX_SIZE && self.size > 0 {
+            self.rotate()?;
+        }
+        let len = self.file.write(buf)?;
+        self.size += len as u64;
+        Ok(len)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// redact the secrets of the formatted log record, the record is written once
+struct Redact<W>(W);
+
+impl<W: Write> Write for Redact<W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .write_all(redact(&String::from_utf8_lossy(buf)).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
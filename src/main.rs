@@ -1,162 +1,371 @@
-mod clap_struct;
-mod config;
-mod controller;
-mod interface_server;
+mod batch;
+mod browse;
+mod checkpoint;
+mod cmp;
+mod grep;
+mod history;
+mod http;
+mod logger;
+mod metrics;
+mod notify;
+mod preview;
+mod queue;
+mod resume;
+mod schedule;
+mod scrub;
+mod verify;
+mod watch;
+mod webdav;
 
-use anyhow::{bail, ensure, Context};
-use chrono::{DateTime, Local};
+use anyhow::{ensure, Context};
 use clap::Parser;
-use indicatif::{MultiProgress, ProgressBar, ProgressState, ProgressStyle};
-use log::LevelFilter;
+use indicatif::{ProgressBar, ProgressState, ProgressStyle};
 use netxclient::client::NetxClientArcDef;
 use netxclient::prelude::*;
-use rustls_pemfile::{certs, rsa_private_keys};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsString;
 use std::fmt::Write;
-use std::io::{BufReader, SeekFrom};
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use tokio::fs::File;
-use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
-use tokio_rustls::rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore, ServerName};
+use tokio_util::sync::CancellationToken;
 
-use crate::clap_struct::{ImageArgs, ImageCommands, Opt};
-use crate::config::{get_current_exec_path, load_config};
-use crate::controller::{ClientController, FileWriteService, IFileWS, WriteHandle};
-use crate::interface_server::*;
+use fsc::checksum::{self, HashAlgorithm, PullVerify};
+use fsc::clap_struct::{
+    AuditArgs, AuditCommands, Cli, ConfigArgs, ConfigCommands, DisplayArgs, FlattenCollision,
+    HistoryArgs, HistoryCommands, ImageArgs, ImageCommands, JobAction, JobsArgs, JobsCommands,
+    ListFormat, Opt,
+};
+use fsc::client::{TransferEvent, TransferOptions};
+use fsc::config::{
+    config_check, config_get, config_set, config_show, get_config_path, load_config, Config,
+    DefaultsConfig,
+};
+use fsc::conflict;
+use fsc::crypto::{self, AgeIdentities, AgeRecipients, Key};
+use fsc::interface_server::*;
+use fsc::manifest::{self, Manifest, ManifestFile, MANIFEST_FILE, SIGNATURE_FILE};
+use fsc::output::{
+    confirm, multi_progress, print_entries_separated, print_json, progress_bar, set_color,
+    set_progress, EntryOutput, FileInfoOutput, Progress, TransferSummary, WorkerProgress,
+};
+use fsc::redact::redact;
+use fsc::rewrite;
+use fsc::tune::{Concurrency, Jobs};
+use fsc::FileStoreClient;
+use fsc::{audit, auth};
+use resume::ImageResume;
 
-#[tokio::main(worker_threads = 2)]
-async fn main() -> anyhow::Result<()> {
-    env_logger::builder()
-        .filter_level(LevelFilter::Trace)
-        .filter_module("rustls", LevelFilter::Debug)
-        .filter_module("mio", LevelFilter::Debug)
-        .init();
-    let opt = Opt::parse();
-
-    if let Opt::Create = opt {
-        let config = include_str!("../config.toml");
-        std::fs::write("./config", config)?;
-        return Ok(());
-    }
-
-    let config = load_config().await?;
-    log::trace!("config:{:#?}", config);
+/// the error chain is redacted, safe to attach the output to the issue
+fn main() -> anyhow::Result<()> {
+    run().map_err(|err| anyhow::anyhow!("{}", redact(&format!("{err:?}"))))
+}
 
-    // create netx client
-    let client = {
-        if let Some(tls) = config.tls {
-            let cert_path = if tls.cert.exists() {
-                tls.cert
-            } else {
-                let mut current_exec_path = get_current_exec_path()?;
-                current_exec_path.push(&tls.cert);
-                ensure!(
-                    current_exec_path.exists(),
-                    "not found file:{:?}",
-                    current_exec_path
-                );
-                current_exec_path
-            };
+#[tokio::main(worker_threads = 2)]
+async fn run() -> anyhow::Result<()> {
+    let Cli {
+        server,
+        log,
+        json,
+        quiet,
+        progress,
+        progress_file,
+        color,
+        summary_file,
+        yes,
+        case_insensitive,
+        command: opt,
+    } = Cli::parse();
+    set_color(color);
+    logger::init(&log, quiet, color)?;
+    set_progress(progress, quiet, progress_file.as_deref())?;
 
-            let key_path = if tls.key.exists() {
-                tls.key
+    match opt {
+        Opt::Create => {
+            let config = include_str!("../config.toml");
+            std::fs::write("./config", config)?;
+            return Ok(());
+        }
+        Opt::Config(ConfigArgs { command }) => {
+            match command {
+                ConfigCommands::Get { key } => config_get(&key)?,
+                ConfigCommands::Set { key, value } => config_set(&key, &value)?,
+                ConfigCommands::Show => config_show()?,
+                ConfigCommands::Check => config_check(server).await?,
+            }
+            return Ok(());
+        }
+        Opt::History(HistoryArgs { command }) => {
+            match command {
+                HistoryCommands::List { path, limit } => {
+                    let records = history::list(path.as_deref(), limit)?;
+                    if json {
+                        print_json(&records)?;
+                    } else {
+                        records.iter().for_each(history::Record::print_line);
+                    }
+                }
+                HistoryCommands::Show { id } => {
+                    let record =
+                        history::show(id)?.with_context(|| format!("not found history:{id}"))?;
+                    if json {
+                        print_json(&record)?;
+                    } else {
+                        record.print();
+                    }
+                }
+            }
+            return Ok(());
+        }
+        Opt::Jobs(JobsArgs { command }) => {
+            match command {
+                JobsCommands::Add {
+                    action,
+                    from,
+                    to,
+                    priority,
+                    overwrite,
+                } => {
+                    let id = match action {
+                        JobAction::Push => {
+                            queue::add("push", &from, &to.to_string_lossy(), priority, overwrite)?
+                        }
+                        JobAction::Pull => {
+                            queue::add("pull", &to, &from.to_string_lossy(), priority, overwrite)?
+                        }
+                    };
+                    if json {
+                        print_json(&serde_json::json!({ "id": id }))?;
+                    } else {
+                        println!("queued job:{id}");
+                    }
+                }
+                JobsCommands::List { all } => {
+                    let jobs = queue::list(all)?;
+                    if json {
+                        print_json(&jobs)?;
+                    } else {
+                        jobs.iter().for_each(queue::Job::print_line);
+                    }
+                }
+                JobsCommands::Cancel { id } => queue::cancel(id)?,
+                JobsCommands::Pause { id } => queue::pause(id)?,
+                JobsCommands::Resume { id } => queue::resume(id)?,
+            }
+            return Ok(());
+        }
+        Opt::Checksum {
+            files,
+            hashes,
+            dir: None,
+            output,
+        } => return local_checksums(files, hashes, output, json).await,
+        Opt::Verify {
+            manifest,
+            root,
+            hash,
+            jobs,
+        } => return verify::verify(&manifest, &root, hash, jobs, json, quiet).await,
+        Opt::Resume { list: true } => {
+            let checkpoints = checkpoint::list()?;
+            if json {
+                print_json(&checkpoints)?;
             } else {
-                let mut current_exec_path = get_current_exec_path()?;
-                current_exec_path.push(&tls.key);
-                ensure!(
-                    current_exec_path.exists(),
-                    "not found file:{:?}",
-                    current_exec_path
-                );
-                current_exec_path
-            };
+                checkpoints
+                    .iter()
+                    .for_each(checkpoint::Checkpoint::print_line);
+            }
+            return Ok(());
+        }
+        Opt::Resume { list: false } if checkpoint::list()?.is_empty() && !queue::has_pending()? => {
+            if !quiet {
+                println!("not found the interrupted transfer");
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
 
-            let cert_file = &mut BufReader::new(std::fs::File::open(cert_path)?);
-            let key_file = &mut BufReader::new(std::fs::File::open(key_path)?);
+    // the config file is not used if the server addr is set
+    let config_file = server
+        .server
+        .is_none()
+        .then(get_config_path)
+        .and_then(Result::ok);
+    ensure!(
+        !(matches!(opt, Opt::Login { .. }) && server.token.is_some()),
+        "login not accept --token, the token is kept in the shell history, input it on the prompt"
+    );
+    let mut config = load_config(server).await?;
+    config.defaults.case_insensitive |= case_insensitive;
+    log::trace!("config:{:#?}", config);
 
-            let keys = PrivateKey(rsa_private_keys(key_file)?.remove(0));
-            let cert_chain = certs(cert_file)
-                .unwrap()
-                .iter()
-                .map(|c| Certificate(c.to_vec()))
-                .collect::<Vec<_>>();
+    match opt {
+        Opt::Login {
+            key_passphrase,
+            encrypt_key,
+        } => return auth::login(&config, key_passphrase, encrypt_key),
+        Opt::Logout => return auth::logout(&config),
+        Opt::Audit(AuditArgs {
+            command: AuditCommands::Verify { path },
+        }) => return audit_verify(&config, path, json),
+        Opt::External(args) => return external(&config, config_file, args).await,
+        _ => {}
+    }
+    // the local commands not connect the server, even the server is not reachable
+    if !opt.needs_connection() {
+        return Ok(());
+    }
 
-            if let Some(ca) = tls.ca {
-                let ca_path = if ca.exists() {
-                    ca
-                } else {
-                    let mut current_exec_path = get_current_exec_path()?;
-                    current_exec_path.push(ca);
-                    ensure!(
-                        current_exec_path.exists(),
-                        "not found file:{:?}",
-                        current_exec_path
-                    );
-                    current_exec_path
-                };
-
-                let ca = &mut BufReader::new(std::fs::File::open(ca_path)?);
-                let ca_certs = certs(ca)?;
-                let mut server_auth_roots = RootCertStore::empty();
-                server_auth_roots.add_parsable_certificates(&ca_certs);
-
-                let tls_config = ClientConfig::builder()
-                    .with_safe_defaults()
-                    .with_root_certificates(server_auth_roots)
-                    .with_client_auth_cert(cert_chain, keys)
-                    .expect("bad certificate/key");
-
-                let connector = tokio_rustls::TlsConnector::from(Arc::new(tls_config));
-                let domain = ServerName::try_from(config.server.addr.as_str())?;
-                NetXClient::new_tls(
-                    config.server,
-                    DefaultSessionStore::default(),
-                    domain,
-                    connector,
-                )
-            } else {
-                let tls_config = ClientConfig::builder()
-                    .with_safe_defaults()
-                    .with_custom_certificate_verifier(Arc::new(RustlsAcceptAnyCertVerifier))
-                    .with_client_auth_cert(cert_chain, keys)
-                    .expect("bad certificate/key");
-                let connector = tokio_rustls::TlsConnector::from(Arc::new(tls_config));
-                let domain = ServerName::try_from(config.server.addr.split(':').next().unwrap())?;
-                NetXClient::new_tls(
-                    config.server,
-                    DefaultSessionStore::default(),
-                    domain,
-                    connector,
-                )
+    let defaults = config.defaults.clone();
+    let notify = config.notify.clone();
+    let jobs = config.jobs.clone();
+    let encrypt_key = crypto::encrypt_key(&config)?;
+    let age_identities = AgeIdentities::load(&config.encrypt.identities)?;
+    let client = match FileStoreClient::connect(config).await {
+        Ok(client) => client,
+        Err(err) => match opt {
+            Opt::Push {
+                dir,
+                file,
+                name,
+                overwrite,
+                no_overwrite,
+                rename_prefix,
+                queue_offline: true,
+                ..
+            } => {
+                log::warn!("connect server error:{err:#}, queue the push");
+                let ids = queue_offline(
+                    dir,
+                    file,
+                    name,
+                    !no_overwrite && defaults.overwrite(overwrite),
+                    &rename_prefix,
+                    defaults.case_insensitive,
+                )?;
+                if json {
+                    print_json(&serde_json::json!({ "queued": ids }))?;
+                } else if !quiet {
+                    ids.iter().for_each(|id| println!("queued job:{id}"));
+                }
+                return Ok(());
             }
-        } else {
-            NetXClient::new(config.server, DefaultSessionStore::default())
-        }
+            _ => return Err(err),
+        },
     };
 
-    let wfs = FileWriteService::new();
-    let controller = ClientController::new(wfs.clone());
-    client.init(controller).await?;
+    // ctrl-c cancel the transfer and release the key on the server, press again to exit
+    let cancel = CancellationToken::new();
+    if matches!(
+        opt,
+        Opt::Push { .. }
+            | Opt::Pull { .. }
+            | Opt::Image(_)
+            | Opt::Batch { .. }
+            | Opt::Watch { .. }
+            | Opt::Events { .. }
+            | Opt::Schedule { .. }
+            | Opt::Scrub { .. }
+            | Opt::Resume { .. }
+    ) {
+        let cancel = cancel.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                log::warn!("cancel the transfer, press ctrl-c again to exit");
+                cancel.cancel();
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    std::process::exit(130);
+                }
+            }
+        });
+    }
 
-    match opt {
+    let action = match opt {
+        Opt::Push { .. } => "push",
+        Opt::Pull { .. } => "pull",
+        Opt::Resume { .. } => "resume",
+        _ => "image push",
+    };
+    let start = Instant::now();
+    let result = match opt {
         Opt::Push {
             dir,
             file,
+            name,
+            files_from,
             r#async,
+            no_async,
             block,
             overwrite,
+            no_overwrite,
+            on_conflict,
+            jobs,
+            encrypt,
+            encrypt_to,
+            parallel,
+            remove_source,
+            auto_rename,
+            sparse,
+            rename_prefix,
+            ..
         } => {
-            push(client, dir, file, r#async, block, overwrite).await?;
+            let options = TransferOptions::new(&defaults, r#async, block, overwrite)
+                .with_no_async(no_async)
+                .with_no_overwrite(no_overwrite)
+                .with_on_conflict(on_conflict)
+                .with_jobs(jobs)
+                .with_cancel(cancel.clone())
+                .with_encrypt_key(push_encrypt_key(encrypt_key.as_ref(), encrypt)?)
+                .with_encrypt_to(AgeRecipients::parse(&encrypt_to)?)
+                .with_parallel(parallel)
+                .with_remove_source(remove_source)
+                .with_auto_rename(auto_rename)
+                .with_sparse(sparse)
+                .with_rename_prefix(rename_prefix);
+            match files_from {
+                Some(list) => push_files_from(&client, dir, &list, options).await,
+                None => push_args(&client, dir, file, name, options).await,
+            }
         }
         Opt::Pull {
             file,
             save,
             r#async,
+            no_async,
             block,
             overwrite,
+            no_overwrite,
+            on_conflict,
+            jobs,
+            checksum,
+            verify,
+            no_verify,
+            check,
+            sparse,
+            rename_prefix,
         } => {
-            pull_file(&client, wfs, file, save, r#async, block, overwrite).await?;
+            let verify = if no_verify { PullVerify::None } else { verify };
+            pull_pattern(
+                &client,
+                file,
+                save,
+                check,
+                TransferOptions::new(&defaults, r#async, block, overwrite)
+                    .with_no_async(no_async)
+                    .with_no_overwrite(no_overwrite)
+                    .with_on_conflict(on_conflict)
+                    .with_jobs(jobs)
+                    .with_cancel(cancel.clone())
+                    .with_encrypt_key(encrypt_key.clone())
+                    .with_age_identities(age_identities.clone())
+                    .with_checksum(checksum)
+                    .with_verify(verify)
+                    .with_sparse(sparse)
+                    .with_rename_prefix(rename_prefix),
+            )
+            .await
         }
         Opt::Image(ImageArgs {
             command:
@@ -164,114 +373,782 @@ async fn main() -> anyhow::Result<()> {
                     dir,
                     path,
                     r#async,
+                    no_async,
                     block,
                     overwrite,
+                    no_overwrite,
+                    on_conflict,
+                    jobs,
+                    encrypt,
+                    encrypt_to,
+                    manifest,
+                    sign_key,
+                    resume,
+                    dedupe,
+                    flatten,
+                    remove_source,
+                    rename_prefix,
                 },
         }) => {
-            push_image(client, dir, path, r#async, block, overwrite).await?;
+            push_image(
+                &client,
+                dir,
+                path,
+                TransferOptions::new(&defaults, r#async, block, overwrite)
+                    .with_no_async(no_async)
+                    .with_no_overwrite(no_overwrite)
+                    .with_on_conflict(on_conflict)
+                    .with_jobs(jobs)
+                    .with_cancel(cancel.clone())
+                    .with_encrypt_key(push_encrypt_key(encrypt_key.as_ref(), encrypt)?)
+                    .with_encrypt_to(AgeRecipients::parse(&encrypt_to)?)
+                    .with_remove_source(remove_source)
+                    .with_rename_prefix(rename_prefix),
+                manifest,
+                sign_key,
+                resume,
+                dedupe,
+                flatten,
+            )
+            .await
         }
-        Opt::ShowDir { dir } => {
-            show_dir(client, dir).await?;
+        Opt::Resume { .. } => {
+            resume_checkpoints(&client, &defaults, encrypt_key, age_identities, cancel).await
         }
-        Opt::Info { file } => {
-            show_file_info(client, file).await?;
+        Opt::Image(ImageArgs {
+            command:
+                ImageCommands::Verify {
+                    path,
+                    public_key,
+                    require_signature,
+                },
+        }) => return verify_image(&client, path, public_key, require_signature, json).await,
+        Opt::ShowDir {
+            dir,
+            format,
+            display,
+        } => return show_dir(client.netx(), dir, format, display, json).await,
+        Opt::Info {
+            file,
+            hashes,
+            sha512,
+            display,
+        } => return show_file_info(client.netx(), file, hashes, sha512, display, json).await,
+        Opt::Rm { path } => return remove(&client, path, yes).await,
+        Opt::Grep {
+            pattern,
+            path,
+            recursive,
+            ignore_case,
+            max_size,
+        } => {
+            let grep = grep::Grep::new(&pattern, ignore_case, max_size, json, quiet)?;
+            return grep.run(&client, path, recursive, cancel).await;
+        }
+        Opt::Cmp { a, b, range } => return cmp::cmp(&client, a, b, range, json, quiet).await,
+        Opt::Checksum {
+            hashes,
+            dir: Some(dir),
+            output,
+            ..
+        } => return remote_checksums(&client, dir, hashes, output, json, cancel).await,
+        Opt::Preview { file, bytes } => return preview::preview(&client, file, bytes, json).await,
+        Opt::Browse { dir } => return browse::browse(&client, defaults, &dir).await,
+        Opt::Events { dir } => return events(&client, dir, json, cancel).await,
+        Opt::Scrub { dir } => return scrub::scrub(&client, dir, json, quiet, cancel).await,
+        Opt::Schedule {
+            metrics,
+            fast_verify,
+            queue_concurrency,
+        } => {
+            start_metrics(metrics)?;
+            let queue = queue::run(&client, &defaults, queue_concurrency, cancel.clone());
+            let schedule =
+                schedule::schedule(&client, jobs, &defaults, &notify, fast_verify, cancel);
+            tokio::try_join!(schedule, queue)?;
+            return Ok(());
+        }
+        Opt::Watch {
+            local,
+            remote_dir,
+            debounce,
+            metrics,
+            r#async,
+            no_async,
+            block,
+            fast_verify,
+            queue_concurrency,
+        } => {
+            start_metrics(metrics)?;
+            let queue = queue::run(&client, &defaults, queue_concurrency, cancel.clone());
+            // the changed file over write the remote file
+            let options = TransferOptions::new(&defaults, r#async, block, true)
+                .with_no_async(no_async)
+                .with_cancel(cancel);
+            let watch = watch::watch(
+                &client,
+                local,
+                remote_dir,
+                options,
+                Duration::from_secs(debounce),
+                fast_verify,
+                quiet,
+            );
+            tokio::try_join!(watch, queue)?;
+            return Ok(());
+        }
+        Opt::Batch { script, keep_going } => {
+            let batch = batch::Batch {
+                client: &client,
+                defaults: &defaults,
+                cancel,
+                encrypt_key,
+                age_identities,
+                json,
+                quiet,
+                yes,
+            };
+            return batch.run(&script, keep_going).await;
+        }
+        Opt::Serve { webdav, http, dir } => return serve(client, dir, webdav, http).await,
+        _ => return Ok(()),
+    };
+
+    let summary = match result {
+        Ok(summary) => summary,
+        Err(err) => {
+            let summary = TransferSummary::new(action, String::new(), String::new())
+                .with_start(start)
+                .fail(1, format!("{err:#}"));
+            history::record(&summary);
+            notify::notify(&notify, &summary).await;
+            return Err(err);
+        }
+    };
+    history::record(&summary);
+    notify::notify(&notify, &summary).await;
+
+    if action == "pull" && summary.local == "-" {
+        // the stdout is the pulled data
+        log::info!("pull to stdout size:{}", summary.size);
+    } else if json {
+        print_json(&summary)?;
+    } else if !quiet {
+        summary.print();
+    }
+    if let Some(summary_file) = summary_file {
+        summary.write(&summary_file)?;
+    }
+
+    Ok(())
+}
+
+/// the push encrypt key if set --encrypt, error if not found the key
+#[inline]
+fn push_encrypt_key(key: Option<&Key>, encrypt: bool) -> anyhow::Result<Option<Key>> {
+    if !encrypt {
+        return Ok(None);
+    }
+    key.cloned().map(Some).context(
+        "not found encrypt key, set env FS_ENCRYPT_KEY, config encrypt.key or run fsc login --encrypt-key",
+    )
+}
+
+/// the last file hash, the hash of the files, the sum of the hash time and the block retries
+#[derive(Default)]
+struct Hashed {
+    hash: Option<String>,
+    files: HashMap<String, String>,
+    time: Duration,
+    block_retries: u32,
+}
+
+impl Hashed {
+    #[inline]
+    fn add(&mut self, file: &str, hash: &str, elapsed: Duration) {
+        self.hash = Some(hash.to_string());
+        self.files.insert(file.to_string(), hash.to_string());
+        self.time += elapsed;
+    }
+}
+
+/// show the transfer events of the file on the progress, sum the hash time
+#[inline]
+fn progress_events(
+    progress: Arc<Progress>,
+    hashed: Arc<Mutex<Hashed>>,
+) -> impl Fn(TransferEvent) + Send + Sync + 'static {
+    move |event| match event {
+        TransferEvent::Hashed {
+            file,
+            hash,
+            elapsed,
+        } => hashed.lock().unwrap().add(file, hash, elapsed),
+        TransferEvent::Start { file, size } => progress.start_file(file, size),
+        TransferEvent::Progress { position, .. } => progress.set_position(position),
+        TransferEvent::Retry { .. } => {}
+        TransferEvent::BlockRetry { .. } => hashed.lock().unwrap().block_retries += 1,
+        TransferEvent::Checkpoint { .. } => checkpoint::record(&event),
+        TransferEvent::Finish { .. } => progress.finish(),
+    }
+}
+
+/// show the concurrent transfer events of the multiple files, one bar per active worker,
+/// the finished bar is removed, the total progress sum the bytes of all files
+#[inline]
+fn worker_events(
+    workers: WorkerProgress,
+    total_progress: Arc<Progress>,
+    hashed: Arc<Mutex<Hashed>>,
+) -> impl Fn(TransferEvent) + Send + Sync + 'static {
+    // the counted position of the transferring files
+    let positions = Mutex::new(HashMap::new());
+    move |event| match event {
+        TransferEvent::Hashed {
+            file,
+            hash,
+            elapsed,
+        } => hashed.lock().unwrap().add(file, hash, elapsed),
+        TransferEvent::Start { file, size } => {
+            workers.start(file, size);
+            positions.lock().unwrap().insert(file.to_string(), 0);
+        }
+        TransferEvent::Progress { file, position, .. } => {
+            workers.set_position(file, position);
+            let mut positions = positions.lock().unwrap();
+            let counted = positions.entry(file.to_string()).or_insert(0);
+            // the resumed position after the retry is counted
+            if position > *counted {
+                total_progress.set_position(total_progress.position() + position - *counted);
+                *counted = position;
+            }
+        }
+        TransferEvent::Retry { .. } => {}
+        TransferEvent::BlockRetry { .. } => hashed.lock().unwrap().block_retries += 1,
+        TransferEvent::Checkpoint { .. } => checkpoint::record(&event),
+        TransferEvent::Finish { file, .. } => {
+            positions.lock().unwrap().remove(file);
+            workers.finish(file);
         }
-        _ => {}
     }
+}
+
+/// the worker progress style of the concurrent transfers
+#[inline]
+fn worker_style() -> ProgressStyle {
+    ProgressStyle::with_template("{msg} {spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+        .unwrap()
+        .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
+        .progress_chars("#>-")
+}
 
+/// the concurrency of the multiple files transfer, measure the transfer events for the auto tune
+#[inline]
+fn concurrency_events(
+    client: &FileStoreClient,
+    jobs: Jobs,
+    events: impl Fn(TransferEvent) + Send + Sync + 'static,
+) -> Arc<Concurrency> {
+    let concurrency = Arc::new(Concurrency::new(jobs));
+    let tune = concurrency.clone();
+    client.on_progress(move |event| {
+        tune.event(&event);
+        events(event)
+    });
+    concurrency
+}
+
+/// fail before the push if the size not fit the remote space of the dir,
+/// skip the check if the server not support the quota
+#[inline]
+async fn check_quota(client: &FileStoreClient, dir: &str, size: u64) -> anyhow::Result<()> {
+    use humansize::{format_size, WINDOWS};
+    let quota = match client.quota(dir).await {
+        Ok(quota) => quota,
+        Err(err) => {
+            log::debug!("get quota of dir:{dir} error:{err:#}, skip the quota check");
+            return Ok(());
+        }
+    };
+    ensure!(
+        quota.available >= size,
+        "not enough remote space of dir:{dir}, need {} available {}, short {}",
+        format_size(size, WINDOWS),
+        format_size(quota.available, WINDOWS),
+        format_size(size - quota.available, WINDOWS)
+    );
     Ok(())
 }
 
 /// push file to server
 #[inline]
 async fn push(
-    client: NetxClientArcDef,
+    client: &FileStoreClient,
     dir: Option<PathBuf>,
     file: PathBuf,
-    r#async: bool,
-    block: usize,
-    overwrite: bool,
-) -> anyhow::Result<()> {
-    ensure!(file.is_file(), "path:{} not file", file.display());
-    ensure!(file.exists(), "not found file:{}", file.to_string_lossy());
-    let file_name = file
-        .file_name()
-        .with_context(|| format!("file:{} not name", file.to_string_lossy()))?
-        .to_string_lossy();
+    name: Option<String>,
+    mut options: TransferOptions,
+) -> anyhow::Result<TransferSummary> {
+    let stdin = file == Path::new("-");
+    ensure!(stdin || file.is_file(), "path:{} not file", file.display());
+    ensure!(
+        stdin || file.exists(),
+        "not found file:{}",
+        file.to_string_lossy()
+    );
+    let local = file.display().to_string();
+    let file_name = match name {
+        Some(name) => name,
+        None => {
+            ensure!(!stdin, "push stdin must set the remote file name by --name");
+            match rewrite::to_remote(&options.rename_prefix, &file) {
+                Some(remote) => remote,
+                None => file
+                    .file_name()
+                    .with_context(|| format!("file:{} not name", file.to_string_lossy()))?
+                    .to_string_lossy()
+                    .to_string(),
+            }
+        }
+    };
 
     let push_file_name = {
         if let Some(mut dir) = dir {
-            dir.push(&*file_name);
+            dir.push(&file_name);
             dir.to_string_lossy().replace('\\', "/").to_string()
         } else {
             file_name.to_string()
         }
     };
 
-    let mut file = File::open(file).await?;
-    let size = file.metadata().await?.len();
-    let start_hash = Instant::now();
-    let hash = computer_b3(&mut file).await;
-    log::trace!("hash computer time:{}", start_hash.elapsed().as_secs_f64());
-    log::trace!(
-        "start push file name:{} size:{}B hash:{}",
-        push_file_name,
-        size,
-        hash
+    let mut summary = TransferSummary::new("push", local, push_file_name.clone());
+    let Some(resolved) = client
+        .resolve_conflicts(&[file.clone()], vec![push_file_name.clone()], &mut options)
+        .await?
+        .pop()
+        .flatten()
+    else {
+        summary.skipped = 1;
+        return Ok(summary.finish(0, 0));
+    };
+    if resolved != push_file_name {
+        summary.renamed.push(resolved.clone());
+    }
+    let push_file_name = resolved;
+    summary.remote = push_file_name.clone();
+    if !stdin {
+        let dir = Path::new(&push_file_name)
+            .parent()
+            .map_or_else(String::new, |dir| dir.to_string_lossy().to_string());
+        check_quota(client, &dir, file.metadata()?.len()).await?;
+    }
+    let pb = progress_bar(&push_file_name, 0);
+    if stdin {
+        pb.set_style(
+            ProgressStyle::with_template(
+                "{spinner:.green} [{elapsed_precise}] {bytes} ({bytes_per_sec})",
+            )
+            .unwrap(),
+        );
+    } else {
+        pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+            .unwrap()
+            .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
+            .progress_chars("#>-"));
+    }
+
+    let hashed = Arc::new(Mutex::new(Hashed::default()));
+    client.on_progress(progress_events(Arc::new(pb), hashed.clone()));
+    let size = if stdin {
+        client
+            .push_stream(tokio::io::stdin(), &push_file_name, options)
+            .await?
+    } else {
+        client.push_file(&file, &push_file_name, options).await?
+    };
+    let Hashed {
+        hash,
+        time,
+        block_retries,
+        ..
+    } = std::mem::take(&mut *hashed.lock().unwrap());
+    summary.add_hash_time(time);
+    summary.add_block_retries(block_retries);
+    summary.hash = hash;
+    Ok(summary.finish(1, size))
+}
+
+/// continue the interrupted push and pull of the saved checkpoints one by one,
+/// the checkpoint of the changed local file is removed, then run the pending queued jobs
+#[inline]
+async fn resume_checkpoints(
+    client: &FileStoreClient,
+    defaults: &DefaultsConfig,
+    encrypt_key: Option<Key>,
+    age_identities: AgeIdentities,
+    cancel: CancellationToken,
+) -> anyhow::Result<TransferSummary> {
+    let mut summary = TransferSummary::new("resume", String::new(), String::new());
+    let hashed = Arc::new(Mutex::new(Hashed::default()));
+    let (mut files, mut size) = (0, 0);
+    for point in checkpoint::list()? {
+        let Some(resume) = point.resume_point() else {
+            log::warn!(
+                "local file:{} changed, remove the checkpoint of {}",
+                point.local,
+                point.remote
+            );
+            checkpoint::remove(&point)?;
+            summary.skipped += 1;
+            continue;
+        };
+        log::info!(
+            "resume {} file:{} offset:{}",
+            point.action,
+            point.remote,
+            point.offset
+        );
+        let options = TransferOptions::new(defaults, false, None, point.overwrite)
+            .with_cancel(cancel.clone())
+            .with_resume(Some(resume));
+        let pb = progress_bar(&point.remote, point.size);
+        pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+            .unwrap()
+            .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
+            .progress_chars("#>-"));
+        client.on_progress(progress_events(Arc::new(pb), hashed.clone()));
+        size += if point.action == "push" {
+            client
+                .push_file(&point.local, &point.remote, options)
+                .await?
+        } else {
+            let options = options
+                .with_encrypt_key(encrypt_key.clone())
+                .with_age_identities(age_identities.clone());
+            client
+                .pull_file(&point.remote, &point.local, options)
+                .await?
+        };
+        files += 1;
+    }
+    // the offline queued push
+    for job in queue::drain(client, defaults, cancel).await? {
+        if job.success {
+            files += 1;
+            size += job.size;
+        } else {
+            summary.failed += 1;
+        }
+    }
+    let hashed = hashed.lock().unwrap();
+    summary.add_hash_time(hashed.time);
+    summary.add_block_retries(hashed.block_retries);
+    Ok(summary.finish(files, size))
+}
+
+/// queue the push of the files when the server is unreachable, return the job ids
+#[inline]
+fn queue_offline(
+    dir: Option<PathBuf>,
+    files: Vec<PathBuf>,
+    name: Option<String>,
+    overwrite: bool,
+    rename_prefix: &[rewrite::RenamePrefix],
+    case_insensitive: bool,
+) -> anyhow::Result<Vec<i64>> {
+    let mut expanded = vec![];
+    for file in files {
+        ensure!(file != Path::new("-"), "push stdin can't be queued");
+        match expand_glob(&file, case_insensitive)? {
+            Some(files) => expanded.extend(files),
+            None => expanded.push(file),
+        }
+    }
+    ensure!(
+        name.is_none() || expanded.len() == 1,
+        "push multiple files or pattern can't set the --name"
     );
-    file.seek(SeekFrom::Start(0)).await?;
+    let mut ids = Vec::with_capacity(expanded.len());
+    for file in expanded {
+        let file_name = match name {
+            Some(ref name) => name.clone(),
+            None => match rewrite::to_remote(rename_prefix, &file) {
+                Some(remote) => remote,
+                None => file
+                    .file_name()
+                    .with_context(|| format!("file:{} not name", file.display()))?
+                    .to_string_lossy()
+                    .to_string(),
+            },
+        };
+        let remote = match dir {
+            Some(ref dir) => dir.join(file_name),
+            None => PathBuf::from(file_name),
+        };
+        let remote = remote.to_string_lossy().replace('\\', "/");
+        ids.push(queue::add("push", &file, &remote, 0, overwrite)?);
+    }
+    Ok(ids)
+}
 
-    let server = impl_struct!(client=>IFileStoreService);
-    let key = server.push(&push_file_name, size, hash, overwrite).await?;
-    log::debug!("start write file:{push_file_name} key:{key}");
-    let mut position = 0;
-    let pb = ProgressBar::new(size);
-    pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
-        .unwrap()
-        .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
-        .progress_chars("#>-"));
+/// expand the glob pattern to the matched files, none if not a pattern or the file exists
+#[inline]
+fn expand_glob(pattern: &Path, case_insensitive: bool) -> anyhow::Result<Option<Vec<PathBuf>>> {
+    let text = pattern.to_string_lossy();
+    if pattern.exists() || !text.contains(['*', '?', '[']) {
+        return Ok(None);
+    }
+    let match_options = glob::MatchOptions {
+        case_sensitive: !case_insensitive,
+        ..Default::default()
+    };
+    let mut files = vec![];
+    for path in
+        glob::glob_with(&text, match_options).with_context(|| format!("pattern:{text} error"))?
+    {
+        let path = path?;
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    ensure!(!files.is_empty(), "not found file match pattern:{text}");
+    files.sort();
+    Ok(Some(files))
+}
 
-    let mut buff = vec![0; block];
-    while let Ok(len) = file.read(&mut buff).await {
-        if len > 0 {
-            if !r#async {
-                server.write(key, &buff[..len]).await?;
-            } else {
-                server.write_offset(key, position, &buff[..len]).await;
+/// push the file arguments, the glob pattern push all the matched files,
+/// the shell of windows not glob, multiple files push over one lock
+#[inline]
+async fn push_args(
+    client: &FileStoreClient,
+    dir: Option<PathBuf>,
+    files: Vec<PathBuf>,
+    name: Option<String>,
+    options: TransferOptions,
+) -> anyhow::Result<TransferSummary> {
+    if let [file] = files.as_slice() {
+        if expand_glob(file, options.case_insensitive)?.is_none() {
+            return push(client, dir, file.clone(), name, options).await;
+        }
+    }
+    ensure!(
+        name.is_none(),
+        "push multiple files or pattern can't set the --name"
+    );
+    let mut expanded = vec![];
+    for file in files {
+        match expand_glob(&file, options.case_insensitive)? {
+            Some(files) => expanded.extend(files),
+            None => {
+                ensure!(file != Path::new("-"), "push stdin can't with other files");
+                expanded.push(file);
             }
-            position += len as u64;
-            pb.set_position(position.min(size));
-        } else {
-            break;
         }
     }
+    push_files(client, dir, expanded, options).await
+}
+
+/// read the file list, one path per line, `-` read from stdin, then push the files
+#[inline]
+async fn push_files_from(
+    client: &FileStoreClient,
+    dir: Option<PathBuf>,
+    list: &Path,
+    options: TransferOptions,
+) -> anyhow::Result<TransferSummary> {
+    let content = if list == Path::new("-") {
+        use tokio::io::AsyncReadExt;
+        let mut content = String::new();
+        tokio::io::stdin().read_to_string(&mut content).await?;
+        content
+    } else {
+        tokio::fs::read_to_string(list)
+            .await
+            .with_context(|| format!("read file list:{} error", list.display()))?
+    };
+    let files = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    push_files(client, dir, files, options).await
+}
+
+/// push the files to the dir over one connection, lock all the remote files first
+#[inline]
+async fn push_files(
+    client: &FileStoreClient,
+    dir: Option<PathBuf>,
+    files: Vec<PathBuf>,
+    mut options: TransferOptions,
+) -> anyhow::Result<TransferSummary> {
+    ensure!(!files.is_empty(), "not found file to push");
+    let mut remotes = Vec::with_capacity(files.len());
+    let mut names = HashSet::new();
+    for file in files.iter() {
+        ensure!(file.is_file(), "path:{} not file", file.display());
+        let name = match rewrite::to_remote(&options.rename_prefix, file) {
+            Some(remote) => PathBuf::from(remote),
+            None => PathBuf::from(
+                file.file_name()
+                    .with_context(|| format!("file:{} not name", file.display()))?,
+            ),
+        };
+        let remote = match dir {
+            Some(ref dir) => dir.join(name),
+            None => PathBuf::from(name),
+        };
+        let remote = remote.to_string_lossy().replace('\\', "/");
+        ensure!(
+            names.insert(remote.clone()),
+            "the files has the same name:{remote}"
+        );
+        remotes.push(remote);
+    }
+
+    let mut summary = TransferSummary::new(
+        "push",
+        format!("{} files", files.len()),
+        dir.map_or_else(String::new, |dir| dir.to_string_lossy().replace('\\', "/")),
+    );
+    // drop the skipped files of the conflict policy
+    let resolved = client
+        .resolve_conflicts(&files, remotes.clone(), &mut options)
+        .await?;
+    summary.skipped = resolved.iter().filter(|remote| remote.is_none()).count();
+    summary.renamed = remotes
+        .iter()
+        .zip(resolved.iter())
+        .filter_map(|(remote, resolved)| resolved.clone().filter(|resolved| resolved != remote))
+        .collect();
+    let (files, remotes): (Vec<_>, Vec<_>) = files
+        .into_iter()
+        .zip(resolved)
+        .filter_map(|(file, remote)| Some((file, remote?)))
+        .unzip();
+    if files.is_empty() {
+        return Ok(summary.finish(0, 0));
+    }
+    let total_size = files
+        .iter()
+        .map(|file| std::fs::metadata(file).map_or(0, |metadata| metadata.len()))
+        .sum();
+    check_quota(client, &summary.remote, total_size).await?;
+    let _lease = match client
+        .lock(remotes.clone(), options.overwrite, options.lock_ttl_secs)
+        .await?
+    {
+        Ok(lease) => lease,
+        Err(msg) => {
+            log::error!("check files error:{msg}");
+            return Ok(summary.fail(files.len(), msg));
+        }
+    };
+
+    let multi_progress = multi_progress();
+    let file_pb = multi_progress.add(ProgressBar::new(files.len() as u64));
+    file_pb.set_style(
+        ProgressStyle::with_template(
+            "[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}",
+        )
+        .unwrap()
+        .progress_chars("##-"),
+    );
+    let total_pb = progress_bar("", total_size).add_to(&multi_progress);
+    total_pb.set_style(ProgressStyle::with_template("total {spinner:.green} [{elapsed_precise}] [{wide_bar:.green/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+        .unwrap()
+        .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
+        .progress_chars("#>-"));
 
-    pb.finish_with_message("upload success");
+    let total_pb = Arc::new(total_pb);
+    let hashed = Arc::new(Mutex::new(Hashed::default()));
+    let concurrency = concurrency_events(
+        client,
+        options.jobs,
+        worker_events(
+            WorkerProgress::new(&multi_progress, worker_style()),
+            total_pb.clone(),
+            hashed.clone(),
+        ),
+    );
+    let file_pb = &file_pb;
+    let sizes = concurrency
+        .run(
+            files.iter().zip(remotes.iter()).collect(),
+            move |(file, remote)| {
+                file_pb.set_message(format!("start push file:{remote}"));
+                let options = options.clone();
+                async move {
+                    let size = client.push_file(file, remote, options).await?;
+                    file_pb.inc(1);
+                    Ok::<_, anyhow::Error>(size)
+                }
+            },
+        )
+        .await?;
+    let size = sizes.into_iter().sum();
+    file_pb.finish_with_message("push finish");
+    total_pb.finish();
+    let hashed = hashed.lock().unwrap();
+    summary.add_hash_time(hashed.time);
+    summary.add_block_retries(hashed.block_retries);
+    Ok(summary.finish(files.len(), size))
+}
 
-    if r#async {
-        let mut retry_count = 0;
-        while !server.check_finish(key).await? && retry_count < 20 {
-            tokio::time::sleep(Duration::from_millis(10)).await;
-            retry_count += 1;
+/// the hard linked file index -> the first file index of the same device and inode
+#[cfg(unix)]
+#[inline]
+fn hard_links(files: &[PathBuf]) -> HashMap<usize, usize> {
+    use std::os::unix::fs::MetadataExt;
+    let mut first = HashMap::new();
+    let mut links = HashMap::new();
+    for (index, file) in files.iter().enumerate() {
+        let Ok(metadata) = std::fs::metadata(file) else {
+            continue;
+        };
+        if metadata.nlink() < 2 {
+            continue;
+        }
+        match first.get(&(metadata.dev(), metadata.ino())) {
+            Some(&origin) => {
+                links.insert(index, origin);
+            }
+            None => {
+                first.insert((metadata.dev(), metadata.ino()), index);
+            }
         }
     }
+    links
+}
 
-    server.push_finish(key).await?;
-    Ok(())
+/// the hard link not detected on the platform
+#[cfg(not(unix))]
+#[inline]
+fn hard_links(_files: &[PathBuf]) -> HashMap<usize, usize> {
+    HashMap::new()
 }
 
 /// push image path
 #[inline]
+#[allow(clippy::too_many_arguments)]
 async fn push_image(
-    client: NetxClientArcDef,
+    client: &FileStoreClient,
     dir: Option<PathBuf>,
     path: PathBuf,
-    r#async: bool,
-    block: usize,
-    overwrite: bool,
-) -> anyhow::Result<()> {
+    mut options: TransferOptions,
+    manifest: bool,
+    sign_key: Option<PathBuf>,
+    resume: bool,
+    dedupe: bool,
+    flatten: Option<FlattenCollision>,
+) -> anyhow::Result<TransferSummary> {
     ensure!(path.is_dir(), "path:{} not dir", path.display());
     ensure!(path.exists(), "not found path:{}", path.display());
+    let sign_key = sign_key
+        .as_deref()
+        .map(manifest::load_signing_key)
+        .transpose()?;
 
     #[inline]
     fn visit_dirs(dir: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
@@ -315,134 +1192,522 @@ async fn push_image(
         })
         .collect::<Vec<_>>();
 
-    let check_files = relative_files
+    // the rewritten prefix of the local file under the dir
+    let mut check_files = relative_files
         .iter()
         .zip(files.iter())
         .map(|(base, file)| {
-            base.join(file.file_name().unwrap())
-                .to_string_lossy()
-                .replace('\\', "/")
+            let remote = match rewrite::to_remote(&options.rename_prefix, file) {
+                Some(remote) => dir
+                    .as_ref()
+                    .map_or_else(|| PathBuf::from(&remote), |dir| dir.join(&remote)),
+                None => base.join(file.file_name().unwrap()),
+            };
+            remote.to_string_lossy().replace('\\', "/")
         })
         .collect::<Vec<_>>();
 
-    let server = impl_struct!(client=>IFileStoreService);
-
-    log::debug!("start check path:{}", path.display());
-    let (success, msg) = server.lock(&check_files, overwrite).await?;
-
-    if success {
-        /// push file
-        #[inline]
-        async fn push_file(
-            client: NetxClientArcDef,
-            progress: &ProgressBar,
-            push_file_name: String,
-            file: PathBuf,
-            r#async: bool,
-            block: usize,
-            overwrite: bool,
-        ) -> anyhow::Result<()> {
-            ensure!(file.is_file(), "path:{} not file", file.display());
-            ensure!(file.exists(), "not found file:{}", file.to_string_lossy());
-            let mut file = File::open(file).await?;
-            let size = file.metadata().await?.len();
-            let hash = computer_b3(&mut file).await;
-            file.seek(SeekFrom::Start(0)).await?;
-            let server = impl_struct!(client=>IFileStoreService);
-            let key = server.push(&push_file_name, size, hash, overwrite).await?;
-
-            let mut position = 0;
-            progress.set_length(size);
-            progress.reset();
-
-            let mut buff = vec![0; block];
-            while let Ok(len) = file.read(&mut buff).await {
-                if len > 0 {
-                    if !r#async {
-                        server.write(key, &buff[..len]).await?;
-                    } else {
-                        server.write_offset(key, position, &buff[..len]).await;
+    // the files of the nested tree in the image root, the same names by the collision policy
+    if let Some(collision) = flatten {
+        let name = path
+            .file_name()
+            .with_context(|| format!("path:{} not name", path.display()))?;
+        let root = match dir {
+            Some(ref dir) => dir.join(name),
+            None => PathBuf::from(name),
+        };
+        let mut taken = HashSet::new();
+        let mut flat = Vec::with_capacity(files.len());
+        for (file, remote) in files.into_iter().zip(check_files) {
+            let file_name = Path::new(&remote).file_name().unwrap_or_default();
+            let mut remote = root.join(file_name).to_string_lossy().replace('\\', "/");
+            if !taken.insert(remote.clone()) {
+                match collision {
+                    FlattenCollision::Fail => anyhow::bail!(
+                        "flatten file:{} has the same name of {remote}, set --flatten rename or skip",
+                        file.display()
+                    ),
+                    FlattenCollision::Skip => {
+                        log::info!("flatten skip file:{} same name of {remote}", file.display());
+                        continue;
+                    }
+                    FlattenCollision::Rename => {
+                        let renamed = (1..)
+                            .map(|index| conflict::renamed(&remote, index))
+                            .find(|candidate| !taken.contains(candidate))
+                            .unwrap_or_default();
+                        log::info!("flatten file:{} rename to {renamed}", file.display());
+                        taken.insert(renamed.clone());
+                        remote = renamed;
                     }
-                    position += len as u64;
-                    progress.set_position(position.min(size));
-                } else {
-                    break;
                 }
             }
+            flat.push((file, remote));
+        }
+        (files, check_files) = flat.into_iter().unzip();
+    }
 
-            progress.finish();
-            if r#async {
-                let mut retry_count = 0;
-                while !server.check_finish(key).await? && retry_count < 20 {
-                    tokio::time::sleep(Duration::from_millis(10)).await;
-                    retry_count += 1;
+    // the manifest and signature file in the remote image root
+    let mut manifest_files = vec![];
+    if manifest || sign_key.is_some() {
+        let name = path
+            .file_name()
+            .with_context(|| format!("path:{} not name", path.display()))?;
+        let root = match dir {
+            Some(ref dir) => dir.join(name),
+            None => PathBuf::from(name),
+        };
+        let root = root.to_string_lossy().replace('\\', "/");
+        manifest_files.push(format!("{root}/{MANIFEST_FILE}"));
+        if sign_key.is_some() {
+            manifest_files.push(format!("{root}/{SIGNATURE_FILE}"));
+        }
+    }
+
+    let remote_dir = dir.map_or_else(String::new, |dir| dir.to_string_lossy().replace('\\', "/"));
+    // skip the files of the done markers, the remote blake3 confirmed
+    let mut resume_state = ImageResume::open(&path, &remote_dir, resume)?;
+    let mut done = HashMap::new();
+    if resume {
+        let markers = files
+            .iter()
+            .zip(check_files.iter())
+            .enumerate()
+            .filter_map(|(index, (file, remote))| {
+                resume_state
+                    .take(file, remote)
+                    .map(|marker| (index, marker))
+            })
+            .collect::<Vec<_>>();
+        let remotes = markers
+            .iter()
+            .map(|(_, marker)| PathBuf::from(&marker.remote))
+            .collect::<Vec<_>>();
+        let infos = client.files_info(&remotes, true, false).await?;
+        for ((index, marker), info) in markers.into_iter().zip(infos) {
+            match info {
+                Ok(info) if info.b3.as_deref() == Some(marker.file.b3.as_str()) => {
+                    done.insert(index, marker);
                 }
+                _ => log::debug!("resume file:{} not confirmed, push again", marker.remote),
             }
-            server.push_finish(key).await?;
-            Ok(())
         }
-
-        let multi_progress = MultiProgress::new();
-        let file_pb = multi_progress.add(ProgressBar::new(files.len() as u64));
-        file_pb.set_style(
-            ProgressStyle::with_template(
-                "[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}",
-            )
-            .unwrap()
-            .progress_chars("##-"),
+        log::info!(
+            "resume image push:{} skip {} of {} files",
+            path.display(),
+            done.len(),
+            files.len()
         );
+    }
 
-        let write_pb = multi_progress.add(ProgressBar::new(0));
-        write_pb.set_style(ProgressStyle::with_template("{msg} {spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
-            .unwrap()
-            .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
-            .progress_chars("#>-"));
-
-        for (file, push_file_name) in files.into_iter().zip(check_files.into_iter()) {
-            file_pb.set_message(format!("start push file:{}", push_file_name));
-            push_file(
-                client.clone(),
-                &write_pb,
-                push_file_name,
-                file,
-                r#async,
-                block,
-                overwrite,
-            )
+    // resolve the existing remote files by the conflict policy, the skipped files not pushed
+    let pending = (0..check_files.len())
+        .filter(|index| !done.contains_key(index))
+        .collect::<Vec<_>>();
+    let resolved = client
+        .resolve_conflicts(
+            &pending
+                .iter()
+                .map(|&index| files[index].clone())
+                .collect::<Vec<_>>(),
+            pending
+                .iter()
+                .map(|&index| check_files[index].clone())
+                .collect(),
+            &mut options,
+        )
+        .await?;
+    let mut skipped = HashSet::new();
+    for (index, remote) in pending.into_iter().zip(resolved) {
+        match remote {
+            Some(remote) => check_files[index] = remote,
+            None => {
+                skipped.insert(index);
+            }
+        }
+    }
+
+    // the hard linked files pushed once, copied on the server
+    let links = hard_links(&files);
+    // the duplicate file index -> the first file index of the same local blake3 or inode
+    let mut duplicates = links
+        .iter()
+        .map(|(&index, &origin)| (index, origin))
+        .filter(|(index, origin)| {
+            !done.contains_key(index) && !skipped.contains(index) && !skipped.contains(origin)
+        })
+        .collect::<HashMap<_, _>>();
+    if !links.is_empty() {
+        log::info!(
+            "image push:{} {} hard linked files",
+            path.display(),
+            links.len()
+        );
+    }
+    if dedupe {
+        let mut first = HashMap::new();
+        for (index, file) in files.iter().enumerate() {
+            if skipped.contains(&index) || duplicates.contains_key(&index) {
+                continue;
+            }
+            let hash = checksum::file_checksum(file, HashAlgorithm::Blake3).await?;
+            match first.get(&hash) {
+                Some(&origin) if !done.contains_key(&index) => {
+                    duplicates.insert(index, origin);
+                }
+                Some(_) => {}
+                None => {
+                    first.insert(hash, index);
+                }
+            }
+        }
+        log::info!(
+            "dedupe image push:{} {} duplicate files",
+            path.display(),
+            duplicates.len()
+        );
+    }
+
+    let mut summary = TransferSummary::new("image push", path.display().to_string(), remote_dir);
+    log::debug!("start check path:{}", path.display());
+    let lock_files = check_files
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !done.contains_key(index) && !skipped.contains(index))
+        .map(|(_, file)| file)
+        .chain(manifest_files.iter())
+        .cloned()
+        .collect::<Vec<_>>();
+    // pre-sum the image size, check the remote space, show the image bytes progress and eta
+    let image_size = files
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| {
+            !done.contains_key(index) && !duplicates.contains_key(index) && !skipped.contains(index)
+        })
+        .map(|(_, file)| std::fs::metadata(file).map_or(0, |metadata| metadata.len()))
+        .sum();
+    check_quota(client, &summary.remote, image_size).await?;
+    // the lease renew the locks until the image push finish
+    let _lease = match client
+        .lock(lock_files, options.overwrite, options.lock_ttl_secs)
+        .await?
+    {
+        Ok(lease) => lease,
+        Err(msg) => {
+            log::error!("check path:{} error:{}", path.display(), msg);
+            return Ok(summary.fail(check_files.len(), msg));
+        }
+    };
+
+    let multi_progress = multi_progress();
+    let file_pb = multi_progress.add(ProgressBar::new(files.len() as u64));
+    file_pb.set_style(
+        ProgressStyle::with_template(
+            "[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}",
+        )
+        .unwrap()
+        .progress_chars("##-"),
+    );
+
+    let total_pb = progress_bar(&path.to_string_lossy(), image_size).add_to(&multi_progress);
+    total_pb.set_style(ProgressStyle::with_template("total {spinner:.green} [{elapsed_precise}] [{wide_bar:.green/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+        .unwrap()
+        .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
+        .progress_chars("#>-"));
+
+    let total_pb = Arc::new(total_pb);
+    let hashed = Arc::new(Mutex::new(Hashed::default()));
+    let concurrency = concurrency_events(
+        client,
+        options.jobs,
+        worker_events(
+            WorkerProgress::new(&multi_progress, worker_style()),
+            total_pb.clone(),
+            hashed.clone(),
+        ),
+    );
+
+    let count = files.len();
+    let mut total_size = 0;
+    // the manifest entry of the pushed file, write the resume marker
+    let push_entry = |file: &Path,
+                      push_file_name: &str,
+                      size: u64,
+                      b3: String|
+     -> anyhow::Result<ManifestFile> {
+        // the renamed remote file name of the conflict policy, the flattened file in the root
+        let file_name = Path::new(push_file_name).file_name().unwrap_or_default();
+        let entry_path = match flatten {
+            Some(_) => PathBuf::from(file_name),
+            None => file.strip_prefix(&path)?.with_file_name(file_name),
+        };
+        let entry = ManifestFile {
+            path: entry_path.to_string_lossy().replace('\\', "/"),
+            size,
+            b3,
+            link: None,
+        };
+        // the removed source file not need resume
+        if !options.remove_source {
+            if let Err(err) = resume_state.done(file, push_file_name, &entry) {
+                log::warn!("write image resume marker error:{err:#}");
+            }
+        }
+        file_pb.inc(1);
+        Ok(entry)
+    };
+    // the file index -> the manifest entry, the manifest in the file order
+    let mut entries = BTreeMap::new();
+    // the file index -> the remote path, size and blake3, the copy source of the duplicates
+    let mut pushed = HashMap::new();
+    for (index, marker) in done.drain() {
+        total_size += marker.file.size;
+        pushed.insert(
+            index,
+            (marker.remote, marker.file.size, marker.file.b3.clone()),
+        );
+        entries.insert(index, marker.file);
+        file_pb.inc(1);
+    }
+    file_pb.inc(skipped.len() as u64);
+
+    // push the files concurrently, then copy the duplicates of the pushed files
+    let uploads = (0..count)
+        .filter(|index| {
+            !pushed.contains_key(index)
+                && !skipped.contains(index)
+                && !duplicates.contains_key(index)
+        })
+        .collect::<Vec<_>>();
+    let uploaded = {
+        let (files, check_files, hashed, file_pb, options, push_entry) = (
+            &files,
+            &check_files,
+            &hashed,
+            &file_pb,
+            &options,
+            &push_entry,
+        );
+        concurrency
+            .run(uploads, move |index| {
+                let (file, push_file_name) = (&files[index], &check_files[index]);
+                file_pb.set_message(format!("start push file:{push_file_name}"));
+                let options = options.clone();
+                async move {
+                    let size = client.push_file(file, push_file_name, options).await?;
+                    let b3 = hashed
+                        .lock()
+                        .unwrap()
+                        .files
+                        .remove(push_file_name.as_str())
+                        .unwrap_or_default();
+                    Ok::<_, anyhow::Error>((index, push_entry(file, push_file_name, size, b3)?))
+                }
+            })
+            .await?
+    };
+    for (index, entry) in uploaded {
+        total_size += entry.size;
+        pushed.insert(
+            index,
+            (check_files[index].clone(), entry.size, entry.b3.clone()),
+        );
+        entries.insert(index, entry);
+    }
+    let mut duplicates = duplicates.into_iter().collect::<Vec<_>>();
+    duplicates.sort();
+    for (index, origin) in duplicates {
+        let (file, push_file_name) = (&files[index], &check_files[index]);
+        let (from, size, b3) = pushed
+            .get(&origin)
+            .cloned()
+            .with_context(|| format!("not found the pushed file of {push_file_name}"))?;
+        file_pb.set_message(format!("copy file:{from} to {push_file_name}"));
+        client
+            .copy(&from, push_file_name, options.overwrite)
             .await?;
-            file_pb.inc(1);
+        if options.remove_source {
+            client.remove_source(file, push_file_name, &b3).await?;
         }
-        file_pb.finish_with_message("image push finish");
-    } else {
-        log::error!("check path:{} error:{}", path.display(), msg);
+        total_size += size;
+        // the copy source of the later duplicates
+        pushed.insert(index, (push_file_name.clone(), size, b3.clone()));
+        entries.insert(index, push_entry(file, push_file_name, size, b3)?);
+    }
+    // record the hard links of the image files in the manifest
+    for (index, origin) in links {
+        if let Some(origin) = entries.get(&origin).map(|entry| entry.path.clone()) {
+            if let Some(entry) = entries.get_mut(&index) {
+                entry.link = Some(origin);
+            }
+        }
+    }
+    let manifest_entries = entries.into_values().collect::<Vec<_>>();
+    if let Some(manifest_file) = manifest_files.first() {
+        let manifest = Manifest::new(manifest_entries).to_vec()?;
+        write_remote(client, manifest_file, &manifest, &options).await?;
+        if let (Some(key), Some(signature_file)) = (sign_key, manifest_files.get(1)) {
+            let signature = manifest::sign(&key, &manifest);
+            write_remote(client, signature_file, signature.as_bytes(), &options).await?;
+        }
+        log::info!("push image manifest:{manifest_file}");
     }
+    resume_state.finish();
+    file_pb.finish_with_message("image push finish");
+    total_pb.finish();
+    let hashed = hashed.lock().unwrap();
+    summary.add_hash_time(hashed.time);
+    summary.add_block_retries(hashed.block_retries);
+    summary.skipped = skipped.len();
+    Ok(summary.finish(count - skipped.len(), total_size))
+}
 
+/// write the data to the remote file
+#[inline]
+async fn write_remote(
+    client: &FileStoreClient,
+    file: &str,
+    data: &[u8],
+    options: &TransferOptions,
+) -> anyhow::Result<()> {
+    use tokio::io::AsyncWriteExt;
+    let mut writer = client.create(file, options.clone()).await?;
+    writer.write_all(data).await?;
+    writer.shutdown().await?;
+    Ok(())
+}
+
+/// read the remote file data
+#[inline]
+async fn read_remote(client: &FileStoreClient, file: &str) -> anyhow::Result<Vec<u8>> {
+    use tokio::io::AsyncReadExt;
+    let mut reader = client.open(file).await?;
+    let mut data = vec![];
+    reader.read_to_end(&mut data).await?;
+    Ok(data)
+}
+
+/// verify the remote image files size and blake3 by the manifest,
+/// verify the manifest signature if set the public key
+#[inline]
+async fn verify_image(
+    client: &FileStoreClient,
+    path: PathBuf,
+    public_key: Option<PathBuf>,
+    require_signature: bool,
+    json: bool,
+) -> anyhow::Result<()> {
+    let root = path.to_string_lossy().replace('\\', "/");
+    let root = root.trim_end_matches('/');
+    let data = read_remote(client, &format!("{root}/{MANIFEST_FILE}"))
+        .await
+        .with_context(|| format!("not found image:{root} manifest"))?;
+    let manifest = Manifest::from_slice(&data)?;
+
+    let signature = read_remote(client, &format!("{root}/{SIGNATURE_FILE}"))
+        .await
+        .ok();
+    let signed = match (signature, public_key) {
+        (Some(signature), Some(public_key)) => {
+            let key = manifest::load_verifying_key(&public_key)?;
+            manifest::verify(&key, &data, &String::from_utf8_lossy(&signature))?;
+            true
+        }
+        (Some(_), None) => {
+            ensure!(
+                !require_signature,
+                "image:{root} signature not verified, --public-key not set"
+            );
+            log::warn!("image:{root} signature not verified, --public-key not set");
+            false
+        }
+        (None, _) => {
+            ensure!(!require_signature, "image:{root} manifest not signed");
+            false
+        }
+    };
+
+    let remotes = manifest
+        .files
+        .iter()
+        .map(|file| PathBuf::from(format!("{root}/{}", file.path)))
+        .collect::<Vec<_>>();
+    let infos = client.files_info(&remotes, true, false).await?;
+    let mut failed = vec![];
+    for (file, info) in manifest.files.iter().zip(infos) {
+        match info {
+            Ok(info) if info.size == file.size && info.b3.as_deref() == Some(file.b3.as_str()) => {}
+            Ok(_) => failed.push((file.path.as_str(), "corrupted")),
+            Err(_) => failed.push((file.path.as_str(), "missing")),
+        }
+    }
+
+    if json {
+        let failed = failed
+            .iter()
+            .map(|(path, error)| serde_json::json!({ "path": path, "error": error }))
+            .collect::<Vec<_>>();
+        print_json(&serde_json::json!({
+            "image": root,
+            "files": manifest.files.len(),
+            "signed": signed,
+            "failed": failed,
+        }))?;
+    } else {
+        use console::style;
+        for (path, error) in failed.iter() {
+            println!("{} {path}", style(error).red());
+        }
+        println!(
+            "image:{root} files:{} failed:{} signature:{}",
+            manifest.files.len(),
+            failed.len(),
+            if signed { "verified" } else { "none" }
+        );
+    }
+    ensure!(
+        failed.is_empty(),
+        "image:{root} {} files verify failed",
+        failed.len()
+    );
     Ok(())
 }
 
 /// show directory contexts
 #[inline]
-async fn show_dir(client: NetxClientArcDef, dir: PathBuf) -> anyhow::Result<()> {
+async fn show_dir(
+    client: NetxClientArcDef,
+    dir: PathBuf,
+    format: ListFormat,
+    display: DisplayArgs,
+    json: bool,
+) -> anyhow::Result<()> {
     use console::style;
-    use humansize::{format_size, WINDOWS};
     let server = impl_struct!(client=>IFileStoreService);
     let mut files = server.show_directory_contents(dir).await?;
     files.sort_by(|a, b| b.file_type.cmp(&a.file_type));
+    if json {
+        return print_json(&files.iter().map(EntryOutput::from).collect::<Vec<_>>());
+    }
+    if format != ListFormat::Text {
+        let separator = if format == ListFormat::Csv { ',' } else { '\t' };
+        print_entries_separated(&files, separator);
+        return Ok(());
+    }
     for entry in files {
         if entry.file_type == 1 {
-            let datetime = DateTime::<Local>::from(entry.create_time);
             println!(
                 "{:10}         {}      {}/",
-                style(format_size(0u32, WINDOWS)).yellow().bold(),
-                style(datetime.format("%d/%m/%Y %T")).green().bold(),
+                style(display.size(0)).yellow().bold(),
+                style(display.time(entry.modified())).green().bold(),
                 style(entry.name).blue().bold()
             );
         } else {
-            let datetime = DateTime::<Local>::from(entry.create_time);
             println!(
                 "{:10}         {}      {}",
-                style(format_size(entry.size, WINDOWS)).yellow().bold(),
-                style(datetime.format("%d/%m/%Y %T")).green().bold(),
+                style(display.size(entry.size)).yellow().bold(),
+                style(display.time(entry.modified())).green().bold(),
                 style(entry.name).cyan().bold()
             );
         }
@@ -453,157 +1718,620 @@ async fn show_dir(client: NetxClientArcDef, dir: PathBuf) -> anyhow::Result<()>
 
 /// show file info
 #[inline]
-async fn show_file_info(client: NetxClientArcDef, file: PathBuf) -> anyhow::Result<()> {
+async fn show_file_info(
+    client: NetxClientArcDef,
+    file: PathBuf,
+    hashes: Vec<HashAlgorithm>,
+    sha512: bool,
+    display: DisplayArgs,
+    json: bool,
+) -> anyhow::Result<()> {
     use console::style;
-    use humansize::{format_size, WINDOWS};
     let server = impl_struct!(client=>IFileStoreService);
-    let info = server.get_file_info(&file, true, true).await?;
+    let mut info = server.get_file_info(&file, true, true).await?;
+    if sha512 && info.sha512.is_none() {
+        info.sha512 = server
+            .get_file_checksums(&file, vec![HashAlgorithm::Sha512])
+            .await?
+            .pop();
+    }
+    let checksums = if hashes.is_empty() {
+        vec![]
+    } else {
+        let checksums = server.get_file_checksums(&file, hashes.clone()).await?;
+        hashes.into_iter().zip(checksums).collect()
+    };
+    if json {
+        return print_json(&FileInfoOutput::from(&info).with_checksums(&checksums));
+    }
     println!(
         "file name: {}\nsize: {} Byte ({})\nblake3: {}\nsha256: {}\ncreate time: {}\ncan modify: {}",
         style(info.name).cyan().bold(),
         style(info.size).yellow().bold(),
-        style(format_size(info.size, WINDOWS)).yellow(),
+        style(display.size(info.size)).yellow(),
         style(info.b3.as_ref().map_or("none",|x|x.as_str())).blue().bold(),
         style(info.sha256.as_ref().map_or("none",|x|x.as_str())).red().bold(),
-        style(DateTime::<Local>::from(info.create_time).format("%d/%m/%Y %T"))
-            .green()
-            .bold(),
+        style(display.time(info.create_time)).green().bold(),
         style(info.can_modify)
             .white()
             .bold()
     );
+    println!("content type: {}", style(info.content_type()).magenta());
+    if let Some(sha512) = info.sha512 {
+        println!("sha512: {}", style(sha512).red().bold());
+    }
+    for (algorithm, hex) in checksums {
+        println!("{algorithm}: {}", style(hex).magenta().bold());
+    }
     Ok(())
 }
 
-/// sync pull file
+/// print the local file checksums, the same format of sha256sum
 #[inline]
-async fn pull_file(
-    client: &NetxClientArcDef,
-    wfs: Arc<Actor<FileWriteService>>,
-    file: PathBuf,
-    save: Option<PathBuf>,
-    r#async: bool,
-    block: usize,
-    overwrite: bool,
+async fn local_checksums(
+    files: Vec<PathBuf>,
+    hashes: Vec<HashAlgorithm>,
+    output: Option<PathBuf>,
+    json: bool,
 ) -> anyhow::Result<()> {
-    let server = impl_struct!(client=>IFileStoreService);
-    let info = {
-        match server.get_file_info(&file, true, false).await {
-            Ok(info) => info,
-            Err(err) => {
-                log::error!("{}", err);
-                return Ok(());
-            }
+    let mut checksums = vec![];
+    for file in files.iter() {
+        for algorithm in hashes.iter().copied() {
+            let hex = checksum::file_checksum(file, algorithm).await?;
+            checksums.push((file.to_string_lossy().to_string(), algorithm, hex));
         }
-    };
-    ensure!(
-        info.b3.is_some(),
-        "currently unable to pull file:{}",
-        file.display()
-    );
+    }
+    print_checksums(&checksums, output, json)
+}
 
-    let save_path = {
-        if let Some(save) = save {
-            if save.is_dir() {
-                save.join(&info.name)
-            } else {
-                save
-            }
-        } else {
-            PathBuf::from(&info.name)
+/// print the checksum lines of the `sha256sum` format, or write the checksum file
+#[inline]
+fn print_checksums(
+    checksums: &[(String, HashAlgorithm, String)],
+    output: Option<PathBuf>,
+    json: bool,
+) -> anyhow::Result<()> {
+    if let Some(output) = output {
+        let mut lines = String::new();
+        for (path, _, hex) in checksums.iter() {
+            writeln!(lines, "{hex}  {path}")?;
+        }
+        std::fs::write(&output, lines)
+            .with_context(|| format!("write checksum file:{} error", output.display()))?;
+        log::info!(
+            "write {} checksums to {}",
+            checksums.len(),
+            output.display()
+        );
+    } else if json {
+        let checksums = checksums
+            .iter()
+            .map(|(path, algorithm, hex)| {
+                serde_json::json!({
+                    "path": path,
+                    "algorithm": algorithm,
+                    "hex": hex,
+                })
+            })
+            .collect::<Vec<_>>();
+        print_json(&checksums)?;
+    } else {
+        for (path, _, hex) in checksums.iter() {
+            println!("{hex}  {path}");
         }
+    }
+    Ok(())
+}
+
+/// the checksums of every file in the remote directory tree, the blake3 and sha256
+/// by the batched info, the other algorithms computed by the server per file
+#[inline]
+async fn remote_checksums(
+    client: &FileStoreClient,
+    dir: PathBuf,
+    hashes: Vec<HashAlgorithm>,
+    output: Option<PathBuf>,
+    json: bool,
+    cancel: CancellationToken,
+) -> anyhow::Result<()> {
+    ensure!(
+        output.is_none() || hashes.len() == 1,
+        "the checksum file has one algorithm, set one --hash"
+    );
+    let mut files = client
+        .list_recursive(&dir, None)
+        .await?
+        .into_iter()
+        .filter(|(_, entry)| entry.file_type != 1)
+        .map(|(path, _)| path)
+        .collect::<Vec<_>>();
+    files.sort();
+    let blake3 = hashes.contains(&HashAlgorithm::Blake3);
+    let sha256 = hashes.contains(&HashAlgorithm::Sha256);
+    let infos = if blake3 || sha256 {
+        client.files_info(&files, blake3, sha256).await?
+    } else {
+        vec![]
     };
+    let mut checksums = vec![];
+    for (index, file) in files.iter().enumerate() {
+        ensure!(!cancel.is_cancelled(), "checksum cancelled");
+        let path = file
+            .strip_prefix(&dir)
+            .unwrap_or(file)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let info = match infos.get(index) {
+            Some(Ok(info)) => Some(info),
+            Some(Err(err)) => anyhow::bail!("get file:{} info error:{err:#}", file.display()),
+            None => None,
+        };
+        for algorithm in hashes.iter().copied() {
+            let hex = match (algorithm, info) {
+                (HashAlgorithm::Blake3, Some(info)) => info.b3.clone(),
+                (HashAlgorithm::Sha256, Some(info)) => info.sha256.clone(),
+                _ => None,
+            };
+            let hex = match hex {
+                Some(hex) => hex,
+                None => client
+                    .checksums(file, &[algorithm])
+                    .await?
+                    .pop()
+                    .map(|(_, hex)| hex)
+                    .unwrap_or_default(),
+            };
+            checksums.push((path.clone(), algorithm, hex));
+        }
+    }
+    print_checksums(&checksums, output, json)
+}
 
-    if save_path.exists() {
-        if !overwrite {
-            bail!("file:{} already exists", save_path.display())
-        } else {
-            std::fs::remove_file(&save_path)?;
+/// count remote files and size of the path
+#[inline]
+async fn remote_usage<T: IFileStoreService>(
+    server: &T,
+    path: PathBuf,
+) -> anyhow::Result<(u64, u64)> {
+    if let Ok(info) = server.get_file_info(&path, false, false).await {
+        return Ok((1, info.size));
+    }
+    let (mut files, mut size) = (0, 0);
+    for (_, entry) in server.list_recursive(path, 0).await? {
+        if entry.file_type != 1 {
+            files += 1;
+            size += entry.size;
         }
     }
+    Ok((files, size))
+}
 
-    log::info!("start pull file:{}", save_path.display());
-    let key = server.create_pull(&file).await?;
+/// verify the audit log hash chain
+#[inline]
+fn audit_verify(config: &Config, path: Option<PathBuf>, json: bool) -> anyhow::Result<()> {
+    let path = match path {
+        Some(path) => path,
+        None => audit::audit_path(config)?,
+    };
+    let records = audit::verify(&path)?;
+    if json {
+        print_json(&serde_json::json!({
+            "path": path,
+            "records": records,
+        }))?;
+    } else {
+        use console::style;
+        println!(
+            "audit log:{} records:{} {}",
+            path.display(),
+            records,
+            style("verified").green()
+        );
+    }
+    Ok(())
+}
 
-    let mut fd = tokio::fs::OpenOptions::new()
-        .create(true)
-        .write(true)
-        .open(&save_path)
-        .await?;
+/// remove remote file or directory, prompt with the count and size if not set --yes
+#[inline]
+async fn remove(client: &FileStoreClient, path: PathBuf, yes: bool) -> anyhow::Result<()> {
+    use humansize::{format_size, WINDOWS};
+    let netx = client.netx();
+    let server = impl_struct!(netx=>IFileStoreService);
+    let (files, size) = remote_usage(&server, path.clone()).await?;
+    if !yes
+        && !confirm(&format!(
+            "remove {} ({} files, {})?",
+            path.display(),
+            files,
+            format_size(size, WINDOWS)
+        ))?
+    {
+        println!("cancelled");
+        return Ok(());
+    }
+    client.remove(&path).await?;
+    log::info!("remove {} {} files {} bytes", path.display(), files, size);
+    Ok(())
+}
 
-    let size = info.size;
-    log::debug!("file size:{}", size);
-    let pb = ProgressBar::new(size);
-    pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
-        .unwrap()
-        .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
-        .progress_chars("#>-"));
+/// start the prometheus metrics server if set the listen address
+#[inline]
+fn start_metrics(addr: Option<SocketAddr>) -> anyhow::Result<()> {
+    if let Some(addr) = addr {
+        tokio::spawn(metrics::serve(addr)?);
+    }
+    Ok(())
+}
 
-    if r#async {
-        let (tx, mut rx) = tokio::sync::mpsc::channel(1024);
-        wfs.create_wfs(key, WriteHandle::new(fd, tx)).await;
+/// print the remote file changes of the dir until cancelled
+#[inline]
+async fn events(
+    client: &FileStoreClient,
+    dir: PathBuf,
+    json: bool,
+    cancel: CancellationToken,
+) -> anyhow::Result<()> {
+    use tokio::sync::broadcast::error::RecvError;
+    let mut changes = client.subscribe(&dir).await?;
+    log::info!("subscribe dir:{}", dir.display());
+    loop {
+        let changed = tokio::select! {
+            changed = changes.recv() => changed,
+            _ = cancel.cancelled() => return Ok(()),
+        };
+        match changed {
+            Ok(changed) if json => print_json(&changed)?,
+            Ok(changed) => println!("{:<6} {}", changed.kind.as_str(), changed.path),
+            Err(RecvError::Lagged(count)) => log::warn!("lost {count} change events"),
+            Err(RecvError::Closed) => return Ok(()),
+        }
+    }
+}
 
-        server.async_read(key, block).await;
+/// run the external command `fsc-<name>` on PATH, pass the resolved config by env:
+/// FS_BIN, FS_CONFIG, FS_SERVER, FS_SERVICE_NAME, FS_PROXY and FS_TOKEN,
+/// exit with the command exit code
+#[inline]
+async fn external(
+    config: &Config,
+    config_file: Option<PathBuf>,
+    args: Vec<OsString>,
+) -> anyhow::Result<()> {
+    let (name, args) = args.split_first().context("not found external command")?;
+    let mut program = OsString::from("fsc-");
+    program.push(name);
+    let mut command = tokio::process::Command::new(&program);
+    command
+        .args(args)
+        .env("FS_SERVER", &config.server.addr)
+        .env("FS_SERVICE_NAME", &config.server.service_name);
+    if let Ok(bin) = std::env::current_exe() {
+        command.env("FS_BIN", bin);
+    }
+    if let Some(config_file) = config_file {
+        command.env("FS_CONFIG", config_file);
+    }
+    if let Some(ref proxy) = config.proxy {
+        command.env("FS_PROXY", proxy);
+    }
+    if let Some(token) = config.auth.as_ref().and_then(|auth| auth.token.as_ref()) {
+        command.env("FS_TOKEN", token);
+    }
+    log::debug!("run external command:{}", program.to_string_lossy());
+    let status = match command.status().await {
+        Ok(status) => status,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => anyhow::bail!(
+            "unrecognized command:{}, not found {} on PATH",
+            name.to_string_lossy(),
+            program.to_string_lossy()
+        ),
+        Err(err) => return Err(err.into()),
+    };
+    std::process::exit(status.code().unwrap_or(1));
+}
 
-        let mut offset: u64 = 0;
-        while let Some(r_size) = rx.recv().await {
-            offset += r_size;
-            pb.set_position(offset.min(size));
-            if offset >= size {
-                break;
-            }
+/// serve the remote directory over the local gateways
+#[inline]
+async fn serve(
+    client: FileStoreClient,
+    dir: PathBuf,
+    webdav: Option<SocketAddr>,
+    http: Option<SocketAddr>,
+) -> anyhow::Result<()> {
+    let client = Arc::new(client);
+    let webdav = async {
+        match webdav {
+            Some(addr) => webdav::serve(client.clone(), dir.clone(), addr).await,
+            None => Ok(()),
         }
-        wfs.close_wfs(key).await?;
-    } else {
-        let mut offset = 0;
-        while let Ok(data) = server.read(key, offset, block).await {
-            if !data.is_empty() {
-                offset += data.len() as u64;
-                fd.write_all(&data).await?;
-                pb.set_position(offset.min(size));
-            } else {
-                break;
-            }
+    };
+    let http = async {
+        match http {
+            Some(addr) => http::serve(client.clone(), dir.clone(), addr).await,
+            None => Ok(()),
         }
-        fd.flush().await?;
-        drop(fd);
+    };
+    tokio::try_join!(webdav, http)?;
+    Ok(())
+}
+
+/// check the local file by the remote size and hash, return the hash if same
+#[inline]
+async fn local_current(
+    client: &FileStoreClient,
+    file: &Path,
+    local: &Path,
+    verify: PullVerify,
+) -> anyhow::Result<Option<String>> {
+    let info = client.info(file).await?;
+    if std::fs::metadata(local)?.len() != info.size {
+        return Ok(None);
     }
+    let (algorithm, expected) = match verify {
+        PullVerify::Sha256 => (HashAlgorithm::Sha256, info.sha256),
+        _ => (HashAlgorithm::Blake3, info.b3),
+    };
+    let Some(expected) = expected else {
+        return Ok(None);
+    };
+    let hash = checksum::file_checksum(local, algorithm).await?;
+    Ok((hash == expected).then_some(hash))
+}
 
-    pb.finish_with_message("downloaded success");
-    server.finish_read_key(key).await;
+/// the glob pattern remote files, list the tree of the pattern base dir and match
+#[inline]
+async fn remote_glob(
+    client: &FileStoreClient,
+    pattern: &str,
+    case_insensitive: bool,
+) -> anyhow::Result<Vec<(PathBuf, Entry)>> {
+    let pattern = pattern.replace('\\', "/");
+    let pattern = pattern.trim_start_matches("./");
+    let matcher =
+        glob::Pattern::new(pattern).with_context(|| format!("pattern:{pattern} error"))?;
+    let match_options = glob::MatchOptions {
+        case_sensitive: !case_insensitive,
+        ..Default::default()
+    };
+    // the dirs before the first glob component
+    let components = pattern.split('/').collect::<Vec<_>>();
+    let base_len = components
+        .iter()
+        .position(|component| component.contains(['*', '?', '[']))
+        .unwrap_or(components.len());
+    let base = components[..base_len].join("/");
+    let depth = (!pattern.contains("**")).then_some((components.len() - base_len) as u32);
+    let dir = if base.is_empty() { "./" } else { base.as_str() };
+    let mut files = vec![];
+    for (path, entry) in client.list_recursive(dir, depth).await? {
+        let text = path.to_string_lossy().replace('\\', "/");
+        if entry.file_type != 1
+            && matcher.matches_with(text.trim_start_matches("./"), match_options)
+        {
+            files.push((path, entry));
+        }
+    }
+    ensure!(
+        !files.is_empty(),
+        "not found remote file match pattern:{pattern}"
+    );
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(files)
+}
 
-    let b3 = computer_b3(
-        &mut tokio::fs::OpenOptions::new()
-            .read(true)
-            .open(&save_path)
-            .await?,
-    )
-    .await;
-
-    if &b3 != info.b3.as_ref().unwrap() {
-        std::fs::remove_file(save_path)?;
-        bail!(
-            "file read hash error remote b3:{} local b3:{}",
-            info.b3.unwrap(),
-            b3
+/// pull the file, or all the remote files matched the glob pattern to the save dir
+#[inline]
+async fn pull_pattern(
+    client: &FileStoreClient,
+    file: PathBuf,
+    save: Option<PathBuf>,
+    check: bool,
+    options: TransferOptions,
+) -> anyhow::Result<TransferSummary> {
+    let pattern = file.to_string_lossy().to_string();
+    if !pattern.contains(['*', '?', '[']) {
+        return pull_file(client, file, save, check, options).await;
+    }
+    let files = remote_glob(client, &pattern, options.case_insensitive).await?;
+    let save = save.unwrap_or_else(|| PathBuf::from("./"));
+    ensure!(
+        save != Path::new("-"),
+        "pull pattern can't write to the stdout"
+    );
+    std::fs::create_dir_all(&save)
+        .with_context(|| format!("create save dir:{} error", save.display()))?;
+
+    let mut summary = TransferSummary::new("pull", save.display().to_string(), pattern);
+    // resolve the local files before the progress, the current file skip if check,
+    // the existing file by the conflict policy
+    let mut pulls = Vec::with_capacity(files.len());
+    let mut names = HashSet::new();
+    for (file, entry) in files.iter() {
+        let save_path = match rewrite::to_local(&options.rename_prefix, file) {
+            Some(local) => {
+                let save_path = save.join(local);
+                if let Some(parent) = save_path.parent() {
+                    std::fs::create_dir_all(parent)
+                        .with_context(|| format!("create dir:{} error", parent.display()))?;
+                }
+                save_path
+            }
+            None => save.join(&entry.name),
+        };
+        ensure!(
+            names.insert(save_path.clone()),
+            "the files has the same save path:{}",
+            save_path.display()
         );
-    } else {
-        log::info!("pull file:{} success", save_path.display());
+        let mut options = options.clone();
+        if check && save_path.exists() {
+            if local_current(client, file, &save_path, options.verify)
+                .await?
+                .is_some()
+            {
+                log::info!("file:{} is current, skip pull", save_path.display());
+                continue;
+            }
+            options.overwrite = true;
+        }
+        if let Some(save_path) = conflict::resolve_local(
+            &save_path,
+            options.on_conflict,
+            &mut options.overwrite,
+            options.case_insensitive,
+        )? {
+            pulls.push((file, entry.size, save_path, options));
+        }
     }
+    let multi_progress = multi_progress();
+    let file_pb = multi_progress.add(ProgressBar::new(pulls.len() as u64));
+    file_pb.set_style(
+        ProgressStyle::with_template(
+            "[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}",
+        )
+        .unwrap()
+        .progress_chars("##-"),
+    );
+    let total_size = pulls.iter().map(|(_, size, _, _)| size).sum();
+    let total_pb = progress_bar("", total_size).add_to(&multi_progress);
+    total_pb.set_style(ProgressStyle::with_template("total {spinner:.green} [{elapsed_precise}] [{wide_bar:.green/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+        .unwrap()
+        .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
+        .progress_chars("#>-"));
 
-    Ok(())
+    let total_pb = Arc::new(total_pb);
+    let hashed = Arc::new(Mutex::new(Hashed::default()));
+    let concurrency = concurrency_events(
+        client,
+        options.jobs,
+        worker_events(
+            WorkerProgress::new(&multi_progress, worker_style()),
+            total_pb.clone(),
+            hashed.clone(),
+        ),
+    );
+    let file_pb = &file_pb;
+    let sizes = concurrency
+        .run(
+            pulls.iter().collect(),
+            move |(file, _, save_path, options)| {
+                file_pb.set_message(format!("start pull file:{}", file.display()));
+                let options = options.clone();
+                async move {
+                    let size = client.pull_file(file, save_path, options).await?;
+                    file_pb.inc(1);
+                    Ok::<_, anyhow::Error>(size)
+                }
+            },
+        )
+        .await?;
+    let size = sizes.into_iter().sum();
+    file_pb.finish_with_message("pull finish");
+    total_pb.finish();
+    let hashed = hashed.lock().unwrap();
+    summary.add_hash_time(hashed.time);
+    summary.add_block_retries(hashed.block_retries);
+    summary.skipped = files.len() - pulls.len();
+    Ok(summary.finish(pulls.len(), size))
 }
 
+/// pull file, save to the save path or the save dir, skip if check and the local file is current
 #[inline]
-async fn computer_b3(file: &mut File) -> String {
-    let mut sha = blake3::Hasher::new();
-    let mut data = vec![0; 512 * 1024];
-    while let Ok(len) = file.read(&mut data).await {
-        if len > 0 {
-            sha.update(&data[..len]);
+async fn pull_file(
+    client: &FileStoreClient,
+    file: PathBuf,
+    save: Option<PathBuf>,
+    check: bool,
+    mut options: TransferOptions,
+) -> anyhow::Result<TransferSummary> {
+    let mut summary = TransferSummary::new(
+        "pull",
+        save.as_ref()
+            .map_or_else(String::new, |save| save.display().to_string()),
+        file.display().to_string(),
+    );
+    // the rewritten prefix of the remote file, or the file name
+    let name = match rewrite::to_local(&options.rename_prefix, &file) {
+        Some(local) => PathBuf::from(local),
+        None => PathBuf::from(
+            file.file_name()
+                .with_context(|| format!("file:{} not name", file.display()))?,
+        ),
+    };
+    let save_path = {
+        if let Some(save) = save {
+            if save.is_dir() {
+                save.join(&name)
+            } else {
+                save
+            }
         } else {
-            break;
+            name.clone()
+        }
+    };
+    if name.components().count() > 1 {
+        if let Some(parent) = save_path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("create dir:{} error", parent.display()))?;
         }
     }
-    hex::encode(sha.finalize().as_bytes())
+
+    // save - write to the stdout, the progress on the stderr
+    let stdout = save_path == Path::new("-");
+    if check && !stdout && save_path.exists() {
+        if let Some(hash) = local_current(client, &file, &save_path, options.verify).await? {
+            log::info!("file:{} is current, skip pull", save_path.display());
+            summary.hash = Some(hash);
+            summary.local = save_path.display().to_string();
+            return Ok(summary.finish(0, 0));
+        }
+        // replace the outdated local file
+        options.overwrite = true;
+    }
+    // resolve the existing local file by the conflict policy
+    let save_path = if stdout {
+        save_path
+    } else {
+        match conflict::resolve_local(
+            &save_path,
+            options.on_conflict,
+            &mut options.overwrite,
+            options.case_insensitive,
+        )? {
+            Some(save_path) => save_path,
+            None => {
+                summary.local = save_path.display().to_string();
+                summary.skipped = 1;
+                return Ok(summary.finish(0, 0));
+            }
+        }
+    };
+
+    log::info!("start pull file:{}", save_path.display());
+    let pb = progress_bar(&file.to_string_lossy(), 0);
+    pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+        .unwrap()
+        .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
+        .progress_chars("#>-"));
+
+    let hashed = Arc::new(Mutex::new(Hashed::default()));
+    client.on_progress(progress_events(Arc::new(pb), hashed.clone()));
+    let size = if stdout {
+        client
+            .pull_stream(&file, tokio::io::stdout(), options)
+            .await?
+    } else {
+        client.pull_file(&file, &save_path, options).await?
+    };
+    let Hashed {
+        hash,
+        time,
+        block_retries,
+        ..
+    } = std::mem::take(&mut *hashed.lock().unwrap());
+    summary.add_hash_time(time);
+    summary.add_block_retries(block_retries);
+    summary.hash = hash;
+    summary.local = save_path.display().to_string();
+    Ok(summary.finish(1, size))
 }
@@ -0,0 +1,83 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::Local;
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// the image manifest file name in the image root
+pub const MANIFEST_FILE: &str = ".fsc-manifest.json";
+/// the manifest ed25519 signature file name, hex signature of the manifest file
+pub const SIGNATURE_FILE: &str = ".fsc-manifest.json.sig";
+
+/// image file list with the size and blake3
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    /// rfc3339 create time
+    pub created: String,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// the path relative to the image root
+    pub path: String,
+    pub size: u64,
+    pub b3: String,
+    /// the path of the hard linked file of the same inode, relative to the image root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+impl Manifest {
+    #[inline]
+    pub fn new(files: Vec<ManifestFile>) -> Self {
+        Self {
+            created: Local::now().to_rfc3339(),
+            files,
+        }
+    }
+
+    #[inline]
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+
+    #[inline]
+    pub fn from_slice(data: &[u8]) -> Result<Self> {
+        serde_json::from_slice(data).context("manifest parse error")
+    }
+}
+
+/// load the pkcs8 pem ed25519 private key, generate by: openssl genpkey -algorithm ed25519
+#[inline]
+pub fn load_signing_key(path: &Path) -> Result<SigningKey> {
+    let pem = std::fs::read_to_string(path)
+        .with_context(|| format!("read sign key:{} error", path.display()))?;
+    SigningKey::from_pkcs8_pem(&pem)
+        .map_err(|err| anyhow!("sign key:{} error:{err}", path.display()))
+}
+
+/// load the spki pem ed25519 public key, export by: openssl pkey -in key.pem -pubout
+#[inline]
+pub fn load_verifying_key(path: &Path) -> Result<VerifyingKey> {
+    let pem = std::fs::read_to_string(path)
+        .with_context(|| format!("read public key:{} error", path.display()))?;
+    VerifyingKey::from_public_key_pem(&pem)
+        .map_err(|err| anyhow!("public key:{} error:{err}", path.display()))
+}
+
+/// sign the manifest data, return the hex signature
+#[inline]
+pub fn sign(key: &SigningKey, manifest: &[u8]) -> String {
+    hex::encode(key.sign(manifest).to_bytes())
+}
+
+/// verify the hex signature of the manifest data
+#[inline]
+pub fn verify(key: &VerifyingKey, manifest: &[u8], signature: &str) -> Result<()> {
+    let signature = hex::decode(signature.trim()).context("signature is not hex")?;
+    let signature = Signature::from_slice(&signature).context("signature error")?;
+    key.verify(manifest, &signature)
+        .context("manifest signature verify failed")
+}
@@ -0,0 +1,159 @@
+use anyhow::Result;
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, StatusCode};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// daemon and watch mode metrics
+struct Metrics {
+    registry: Registry,
+    /// transfer files by action and result: success, skipped or failed
+    files: IntCounterVec,
+    bytes: IntCounterVec,
+    duration: HistogramVec,
+    rpc: HistogramVec,
+    /// scheduled job runs by job and result
+    jobs: IntCounterVec,
+}
+
+impl Metrics {
+    #[inline]
+    fn new() -> Self {
+        let files = IntCounterVec::new(
+            Opts::new("fsc_transfer_files_total", "transfer files"),
+            &["action", "result"],
+        )
+        .unwrap();
+        let bytes = IntCounterVec::new(
+            Opts::new("fsc_transfer_bytes_total", "transfer bytes"),
+            &["action"],
+        )
+        .unwrap();
+        let duration = HistogramVec::new(
+            HistogramOpts::new("fsc_transfer_duration_seconds", "file transfer duration"),
+            &["action"],
+        )
+        .unwrap();
+        let rpc = HistogramVec::new(
+            HistogramOpts::new("fsc_rpc_duration_seconds", "rpc latency").buckets(vec![
+                0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+            ]),
+            &["method"],
+        )
+        .unwrap();
+        let jobs = IntCounterVec::new(
+            Opts::new("fsc_job_runs_total", "scheduled job runs"),
+            &["job", "result"],
+        )
+        .unwrap();
+
+        let registry = Registry::new();
+        registry.register(Box::new(files.clone())).unwrap();
+        registry.register(Box::new(bytes.clone())).unwrap();
+        registry.register(Box::new(duration.clone())).unwrap();
+        registry.register(Box::new(rpc.clone())).unwrap();
+        registry.register(Box::new(jobs.clone())).unwrap();
+        Self {
+            registry,
+            files,
+            bytes,
+            duration,
+            rpc,
+            jobs,
+        }
+    }
+}
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+#[inline]
+fn metrics() -> &'static Metrics {
+    METRICS.get_or_init(Metrics::new)
+}
+
+/// record the transferred file
+#[inline]
+pub fn transfer(action: &str, size: u64, elapsed: Duration) {
+    let metrics = metrics();
+    metrics.files.with_label_values(&[action, "success"]).inc();
+    metrics.bytes.with_label_values(&[action]).inc_by(size);
+    metrics
+        .duration
+        .with_label_values(&[action])
+        .observe(elapsed.as_secs_f64());
+}
+
+/// record the unchanged file skipped
+#[inline]
+pub fn skipped(action: &str) {
+    metrics()
+        .files
+        .with_label_values(&[action, "skipped"])
+        .inc();
+}
+
+/// record the failed file
+#[inline]
+pub fn failed(action: &str) {
+    metrics().files.with_label_values(&[action, "failed"]).inc();
+}
+
+/// record the rpc latency
+#[inline]
+pub fn rpc(method: &str, elapsed: Duration) {
+    metrics()
+        .rpc
+        .with_label_values(&[method])
+        .observe(elapsed.as_secs_f64());
+}
+
+/// record the scheduled job run
+#[inline]
+pub fn job(name: &str, success: bool) {
+    let result = if success { "success" } else { "failed" };
+    metrics().jobs.with_label_values(&[name, result]).inc();
+}
+
+/// encode the metrics as prometheus text
+#[inline]
+fn response(req: &Request<Body>) -> Response<Body> {
+    if req.uri().path() != "/metrics" {
+        let mut response = Response::new(Body::from("not found"));
+        *response.status_mut() = StatusCode::NOT_FOUND;
+        return response;
+    }
+    let encoder = TextEncoder::new();
+    let mut buffer = vec![];
+    if let Err(err) = encoder.encode(&metrics().registry.gather(), &mut buffer) {
+        log::error!("encode metrics error:{err}");
+    }
+    let mut response = Response::new(Body::from(buffer));
+    if let Ok(content_type) = encoder.format_type().parse() {
+        response.headers_mut().insert(CONTENT_TYPE, content_type);
+    }
+    response
+}
+
+/// bind the addr, return the server serve GET /metrics
+#[inline]
+pub fn serve(addr: SocketAddr) -> Result<impl Future<Output = ()>> {
+    let make_service = make_service_fn(|_| async {
+        Ok::<_, Infallible>(service_fn(|req: Request<Body>| async move {
+            Ok::<_, Infallible>(response(&req))
+        }))
+    });
+    let server = hyper::Server::try_bind(&addr)?.serve(make_service);
+    log::info!("metrics listen on http://{addr}/metrics");
+    Ok(async move {
+        if let Err(err) = server.await {
+            log::error!("metrics server error:{err}");
+        }
+    })
+}
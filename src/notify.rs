@@ -0,0 +1,74 @@
+use anyhow::{ensure, Result};
+use std::time::Duration;
+
+use fsc::config::NotifyConfig;
+use fsc::output::TransferSummary;
+
+/// webhook request timeout
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// notify the transfer finished, the notify error not fail the transfer
+#[inline]
+pub async fn notify(config: &NotifyConfig, summary: &TransferSummary) {
+    if let Some(ref url) = config.url {
+        if let Err(err) = webhook(url, summary).await {
+            log::warn!("notify webhook {url} error:{err:#}");
+        }
+    }
+    if config.desktop && summary.elapsed_ms >= config.desktop_min_secs as u128 * 1000 {
+        if let Err(err) = desktop(summary).await {
+            log::warn!("desktop notify error:{err:#}");
+        }
+    }
+}
+
+/// show desktop notification
+#[inline]
+async fn desktop(summary: &TransferSummary) -> Result<()> {
+    use humansize::{format_size, WINDOWS};
+    let title = if summary.success {
+        format!("fsc {} finished", summary.action)
+    } else {
+        format!("fsc {} failed", summary.action)
+    };
+    let body = match summary.error {
+        Some(ref error) => error.clone(),
+        None => format!(
+            "{} -> {}\n{} files, {} in {:.1}s",
+            summary.local,
+            summary.remote,
+            summary.files,
+            format_size(summary.size, WINDOWS),
+            summary.elapsed_ms as f64 / 1000.0
+        ),
+    };
+    tokio::task::spawn_blocking(move || {
+        notify_rust::Notification::new()
+            .summary(&title)
+            .body(&body)
+            .appname("fsc")
+            .show()
+            .map(|_| ())
+    })
+    .await??;
+    Ok(())
+}
+
+/// post the transfer summary json to the webhook url
+#[inline]
+async fn webhook(url: &str, summary: &TransferSummary) -> Result<()> {
+    let response = reqwest::Client::builder()
+        .timeout(WEBHOOK_TIMEOUT)
+        .build()?
+        .post(url)
+        .json(summary)
+        .send()
+        .await?;
+    ensure!(
+        response.status().is_success(),
+        "response status:{}",
+        response.status()
+    );
+    log::debug!("notify webhook {url} success");
+    Ok(())
+}
@@ -0,0 +1,582 @@
+use anyhow::Context;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+#[cfg(feature = "cli")]
+use {
+    crate::clap_struct::{ColorMode, DisplayArgs, ProgressMode},
+    chrono::Utc,
+    indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle},
+    std::collections::HashMap,
+    std::ops::Deref,
+    std::sync::atomic::{AtomicBool, Ordering},
+    std::sync::Arc,
+    std::sync::{Mutex, OnceLock},
+};
+
+use crate::checksum::HashAlgorithm;
+use crate::interface_server::{Entry, FileInfo};
+use crate::redact::redact;
+
+/// not show progress bar
+#[cfg(feature = "cli")]
+static QUIET: AtomicBool = AtomicBool::new(false);
+/// json progress event output, set if progress mode is json
+#[cfg(feature = "cli")]
+static PROGRESS_JSON: OnceLock<Mutex<Box<dyn Write + Send>>> = OnceLock::new();
+/// json progress event min interval
+#[cfg(feature = "cli")]
+const PROGRESS_JSON_INTERVAL: Duration = Duration::from_millis(200);
+
+/// set color output of stdout and stderr
+/// auto: disable if env NO_COLOR is set, or the output is not a terminal
+#[cfg(feature = "cli")]
+#[inline]
+pub fn set_color(mode: ColorMode) {
+    let (stdout, stderr) = match mode {
+        ColorMode::Always => (true, true),
+        ColorMode::Never => (false, false),
+        ColorMode::Auto => {
+            let no_color = std::env::var_os("NO_COLOR").map_or(false, |value| !value.is_empty());
+            (
+                !no_color && console::Term::stdout().is_term(),
+                !no_color && console::Term::stderr().is_term(),
+            )
+        }
+    };
+    console::set_colors_enabled(stdout);
+    console::set_colors_enabled_stderr(stderr);
+}
+
+/// set progress output
+/// bar: progress bar, hidden in quiet mode or stderr is not a terminal
+/// json: newline-delimited progress events write to stderr or the progress file(FIFO)
+#[cfg(feature = "cli")]
+#[inline]
+pub fn set_progress(mode: ProgressMode, quiet: bool, file: Option<&Path>) -> anyhow::Result<()> {
+    match mode {
+        ProgressMode::Bar => {
+            QUIET.store(
+                quiet || !console::Term::stderr().is_term(),
+                Ordering::Relaxed,
+            );
+        }
+        ProgressMode::Json => {
+            QUIET.store(true, Ordering::Relaxed);
+            if quiet {
+                return Ok(());
+            }
+            let output: Box<dyn Write + Send> = match file {
+                Some(file) => Box::new(
+                    std::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(file)
+                        .with_context(|| format!("open progress file:{} error", file.display()))?,
+                ),
+                None => Box::new(std::io::stderr()),
+            };
+            let _ = PROGRESS_JSON.set(Mutex::new(output));
+        }
+    }
+    Ok(())
+}
+
+/// progress bar draw target, hidden in quiet mode
+#[cfg(feature = "cli")]
+#[inline]
+fn draw_target() -> ProgressDrawTarget {
+    if QUIET.load(Ordering::Relaxed) {
+        ProgressDrawTarget::hidden()
+    } else {
+        ProgressDrawTarget::stderr()
+    }
+}
+
+/// create file transfer progress, hidden in quiet mode
+#[cfg(feature = "cli")]
+#[inline]
+pub fn progress_bar(file: &str, len: u64) -> Progress {
+    Progress {
+        bar: ProgressBar::with_draw_target(Some(len), draw_target()),
+        json: PROGRESS_JSON.get().map(|_| {
+            Mutex::new(JsonProgress {
+                file: file.to_string(),
+                start: Instant::now(),
+                last: None,
+            })
+        }),
+    }
+}
+
+/// create multi progress, hidden in quiet mode
+#[cfg(feature = "cli")]
+#[inline]
+pub fn multi_progress() -> MultiProgress {
+    MultiProgress::with_draw_target(draw_target())
+}
+
+/// json progress event
+#[cfg(feature = "cli")]
+#[derive(Serialize)]
+struct ProgressEvent<'a> {
+    file: &'a str,
+    done: u64,
+    total: u64,
+    /// bytes per second
+    rate: u64,
+    /// seconds
+    eta: f64,
+}
+
+/// json progress state
+#[cfg(feature = "cli")]
+struct JsonProgress {
+    file: String,
+    start: Instant,
+    last: Option<Instant>,
+}
+
+/// file transfer progress, show the progress bar or write json progress event
+#[cfg(feature = "cli")]
+pub struct Progress {
+    bar: ProgressBar,
+    json: Option<Mutex<JsonProgress>>,
+}
+
+#[cfg(feature = "cli")]
+impl Deref for Progress {
+    type Target = ProgressBar;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.bar
+    }
+}
+
+#[cfg(feature = "cli")]
+impl Progress {
+    /// add to multi progress
+    #[inline]
+    pub fn add_to(self, multi: &MultiProgress) -> Self {
+        Self {
+            bar: multi.add(self.bar),
+            json: self.json,
+        }
+    }
+
+    /// set the transfer file name and restart
+    #[inline]
+    pub fn start_file(&self, file: &str, len: u64) {
+        self.bar.set_length(len);
+        self.bar.reset();
+        if let Some(ref json) = self.json {
+            let mut json = json.lock().unwrap();
+            json.file = file.to_string();
+            json.start = Instant::now();
+            json.last = None;
+        }
+    }
+
+    #[inline]
+    pub fn set_position(&self, position: u64) {
+        self.bar.set_position(position);
+        self.write_event(false);
+    }
+
+    #[inline]
+    pub fn finish(&self) {
+        self.bar.finish();
+        self.write_event(true);
+    }
+
+    #[inline]
+    pub fn finish_with_message(&self, msg: &'static str) {
+        self.bar.finish_with_message(msg);
+        self.write_event(true);
+    }
+
+    /// write json progress event, at most every PROGRESS_JSON_INTERVAL unless force
+    #[inline]
+    fn write_event(&self, force: bool) {
+        let (Some(json), Some(output)) = (self.json.as_ref(), PROGRESS_JSON.get()) else {
+            return;
+        };
+        let mut json = json.lock().unwrap();
+        let now = Instant::now();
+        if !force
+            && json
+                .last
+                .map_or(false, |last| now - last < PROGRESS_JSON_INTERVAL)
+        {
+            return;
+        }
+        json.last = Some(now);
+
+        let done = self.bar.position();
+        let total = self.bar.length().unwrap_or(0);
+        let elapsed = json.start.elapsed().as_secs_f64();
+        let rate = if elapsed > 0.0 {
+            done as f64 / elapsed
+        } else {
+            0.0
+        };
+        let eta = if rate > 0.0 {
+            total.saturating_sub(done) as f64 / rate
+        } else {
+            0.0
+        };
+        let event = ProgressEvent {
+            file: &json.file,
+            done,
+            total,
+            rate: rate as u64,
+            eta,
+        };
+        if let Ok(line) = serde_json::to_string(&event) {
+            let mut output = output.lock().unwrap();
+            if let Err(err) = writeln!(output, "{line}").and_then(|_| output.flush()) {
+                log::debug!("write progress event error:{err}");
+            }
+        }
+    }
+}
+
+/// the progress bars of the concurrent transfers, one bar per active worker
+/// above the last bar of the multi progress, the bar is removed when the file finish
+#[cfg(feature = "cli")]
+pub struct WorkerProgress {
+    multi: MultiProgress,
+    style: ProgressStyle,
+    /// the file -> the bar of the worker
+    active: Mutex<HashMap<String, Arc<Progress>>>,
+}
+
+#[cfg(feature = "cli")]
+impl WorkerProgress {
+    #[inline]
+    pub fn new(multi: &MultiProgress, style: ProgressStyle) -> Self {
+        Self {
+            multi: multi.clone(),
+            style,
+            active: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// show the bar of the started file
+    #[inline]
+    pub fn start(&self, file: &str, len: u64) {
+        let progress = progress_bar(file, len);
+        progress.set_style(self.style.clone());
+        progress.set_message(file.to_string());
+        let progress = Progress {
+            bar: self.multi.insert_from_back(1, progress.bar),
+            json: progress.json,
+        };
+        self.active
+            .lock()
+            .unwrap()
+            .insert(file.to_string(), Arc::new(progress));
+    }
+
+    #[inline]
+    pub fn set_position(&self, file: &str, position: u64) {
+        let progress = self.active.lock().unwrap().get(file).cloned();
+        if let Some(progress) = progress {
+            progress.set_position(position);
+        }
+    }
+
+    /// the file finished, remove the bar
+    #[inline]
+    pub fn finish(&self, file: &str) {
+        let progress = self.active.lock().unwrap().remove(file);
+        if let Some(progress) = progress {
+            progress.finish();
+            self.multi.remove(&progress.bar);
+        }
+    }
+}
+
+/// prompt and read y/n from stdin, default no
+/// if stdin is not a terminal, can't confirm, use --yes
+#[inline]
+pub fn confirm(prompt: &str) -> anyhow::Result<bool> {
+    anyhow::ensure!(
+        std::io::stdin().is_terminal(),
+        "{prompt} can't confirm, stdin is not a terminal, use --yes"
+    );
+    print!("{prompt} [y/N] ");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes" | "YES"))
+}
+
+/// print value as a json line to stdout
+#[inline]
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string(value)?);
+    Ok(())
+}
+
+/// print directory entries as csv or tsv with header
+#[inline]
+pub fn print_entries_separated(entries: &[Entry], separator: char) {
+    let field = |value: &str| {
+        if value.contains([separator, '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    };
+    println!("name{separator}type{separator}size{separator}created{separator}modified");
+    for entry in entries.iter().map(EntryOutput::from) {
+        println!(
+            "{}{separator}{}{separator}{}{separator}{}{separator}{}",
+            field(entry.name),
+            entry.r#type,
+            entry.size,
+            entry.create_time,
+            entry.modify_time
+        );
+    }
+}
+
+#[cfg(feature = "cli")]
+impl DisplayArgs {
+    /// format size, humanized or bytes
+    #[inline]
+    pub fn size(&self, size: u64) -> String {
+        if self.bytes {
+            size.to_string()
+        } else {
+            humansize::format_size(size, humansize::WINDOWS)
+        }
+    }
+
+    /// format time, local or utc, "%d/%m/%Y %T" or rfc3339
+    #[inline]
+    pub fn time(&self, time: SystemTime) -> String {
+        match (self.utc, self.iso_time) {
+            (true, true) => DateTime::<Utc>::from(time).to_rfc3339(),
+            (true, false) => DateTime::<Utc>::from(time)
+                .format("%d/%m/%Y %T")
+                .to_string(),
+            (false, true) => DateTime::<Local>::from(time).to_rfc3339(),
+            (false, false) => DateTime::<Local>::from(time)
+                .format("%d/%m/%Y %T")
+                .to_string(),
+        }
+    }
+}
+
+/// format time as rfc3339
+#[inline]
+pub fn format_time(time: SystemTime) -> String {
+    DateTime::<Local>::from(time).to_rfc3339()
+}
+
+/// directory entry json output
+#[derive(Serialize)]
+pub struct EntryOutput<'a> {
+    pub name: &'a str,
+    /// file or dir
+    pub r#type: &'static str,
+    pub size: u64,
+    pub create_time: String,
+    pub modify_time: String,
+}
+
+impl<'a> From<&'a Entry> for EntryOutput<'a> {
+    #[inline]
+    fn from(entry: &'a Entry) -> Self {
+        Self {
+            name: &entry.name,
+            r#type: if entry.file_type == 1 { "dir" } else { "file" },
+            size: entry.size,
+            create_time: format_time(entry.create_time),
+            modify_time: format_time(entry.modified()),
+        }
+    }
+}
+
+/// file info json output
+#[derive(Serialize)]
+pub struct FileInfoOutput<'a> {
+    pub name: &'a str,
+    pub size: u64,
+    pub create_time: String,
+    pub b3: Option<&'a str>,
+    pub sha256: Option<&'a str>,
+    pub can_modify: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha512: Option<&'a str>,
+    pub content_type: String,
+    /// the checksums of the selected algorithms
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub checksums: BTreeMap<&'static str, &'a str>,
+}
+
+impl<'a> FileInfoOutput<'a> {
+    #[inline]
+    pub fn with_checksums(mut self, checksums: &'a [(HashAlgorithm, String)]) -> Self {
+        self.checksums = checksums
+            .iter()
+            .map(|(algorithm, hex)| (algorithm.as_str(), hex.as_str()))
+            .collect();
+        self
+    }
+}
+
+impl<'a> From<&'a FileInfo> for FileInfoOutput<'a> {
+    #[inline]
+    fn from(info: &'a FileInfo) -> Self {
+        Self {
+            name: &info.name,
+            size: info.size,
+            create_time: format_time(info.create_time),
+            b3: info.b3.as_deref(),
+            sha256: info.sha256.as_deref(),
+            can_modify: info.can_modify,
+            sha512: info.sha512.as_deref(),
+            content_type: info.content_type(),
+            checksums: BTreeMap::new(),
+        }
+    }
+}
+
+/// push or pull summary
+#[derive(Serialize)]
+pub struct TransferSummary {
+    /// push, pull or image push
+    pub action: &'static str,
+    pub local: String,
+    pub remote: String,
+    pub files: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub size: u64,
+    pub elapsed_ms: u128,
+    /// average bytes per second
+    pub throughput: u64,
+    /// file hash computer time
+    pub hash_ms: u128,
+    /// the block write or read retry count
+    pub block_retries: u32,
+    /// the file blake3 of the single file transfer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// the final remote paths of the renamed files
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub renamed: Vec<String>,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip)]
+    start: Instant,
+}
+
+impl TransferSummary {
+    #[inline]
+    pub fn new(action: &'static str, local: String, remote: String) -> Self {
+        Self {
+            action,
+            local,
+            remote,
+            files: 0,
+            skipped: 0,
+            failed: 0,
+            size: 0,
+            elapsed_ms: 0,
+            throughput: 0,
+            hash_ms: 0,
+            block_retries: 0,
+            hash: None,
+            renamed: vec![],
+            success: false,
+            error: None,
+            start: Instant::now(),
+        }
+    }
+
+    /// set the transfer start time
+    #[inline]
+    pub fn with_start(mut self, start: Instant) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// add file hash computer time
+    #[inline]
+    pub fn add_hash_time(&mut self, time: Duration) {
+        self.hash_ms += time.as_millis();
+    }
+
+    /// add the block retry count
+    #[inline]
+    pub fn add_block_retries(&mut self, count: u32) {
+        self.block_retries += count;
+    }
+
+    /// transfer success
+    #[inline]
+    pub fn finish(mut self, files: usize, size: u64) -> Self {
+        self.files = files;
+        self.size = size;
+        self.success = true;
+        let elapsed = self.start.elapsed();
+        self.elapsed_ms = elapsed.as_millis();
+        if elapsed.as_secs_f64() > 0.0 {
+            self.throughput = (size as f64 / elapsed.as_secs_f64()) as u64;
+        }
+        self
+    }
+
+    /// transfer not start or failed
+    #[inline]
+    pub fn fail(mut self, failed: usize, error: String) -> Self {
+        self.failed = failed;
+        self.error = Some(redact(&error));
+        self.elapsed_ms = self.start.elapsed().as_millis();
+        self
+    }
+
+    /// print summary report
+    #[cfg(feature = "cli")]
+    #[inline]
+    pub fn print(&self) {
+        use console::style;
+        use humansize::{format_size, WINDOWS};
+        println!(
+            "{} files:{} skipped:{} failed:{} size:{} elapsed:{:.1}s throughput:{}/s hash:{:.1}s",
+            style(self.action).cyan().bold(),
+            style(self.files).green(),
+            style(self.skipped).yellow(),
+            style(self.failed).red(),
+            format_size(self.size, WINDOWS),
+            self.elapsed_ms as f64 / 1000.0,
+            format_size(self.throughput, WINDOWS),
+            self.hash_ms as f64 / 1000.0,
+        );
+        if self.block_retries > 0 {
+            println!("block retries: {}", style(self.block_retries).yellow());
+        }
+        for remote in self.renamed.iter() {
+            println!("renamed: {}", style(remote).yellow());
+        }
+        if let Some(ref error) = self.error {
+            println!("error: {}", style(error).red());
+        }
+    }
+
+    /// write summary report json to file
+    #[inline]
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("write summary file:{} error", path.display()))
+    }
+}
@@ -0,0 +1,143 @@
+use anyhow::Result;
+use console::style;
+use netxclient::prelude::*;
+use serde::Serialize;
+use std::path::PathBuf;
+
+use fsc::interface_server::*;
+use fsc::output::print_json;
+use fsc::FileStoreClient;
+
+/// the bytes of the hex dump line
+const HEX_LINE: usize = 16;
+
+/// the preview output
+#[derive(Serialize)]
+struct PreviewOutput<'a> {
+    path: &'a str,
+    size: u64,
+    /// the preview bytes
+    bytes: usize,
+    /// utf-8, utf-16le, utf-16be or binary
+    encoding: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hex: Option<String>,
+}
+
+/// detect the text encoding by the bom and the utf-8 validation, none if binary
+#[inline]
+fn decode_text(data: &[u8]) -> Option<(&'static str, String)> {
+    let utf16 = |data: &[u8], decode: fn([u8; 2]) -> u16| {
+        let units = data
+            .chunks_exact(2)
+            .map(|unit| decode([unit[0], unit[1]]))
+            .collect::<Vec<_>>();
+        String::from_utf16_lossy(&units)
+    };
+    if let Some(data) = data.strip_prefix(&[0xff, 0xfe]) {
+        return Some(("utf-16le", utf16(data, u16::from_le_bytes)));
+    }
+    if let Some(data) = data.strip_prefix(&[0xfe, 0xff]) {
+        return Some(("utf-16be", utf16(data, u16::from_be_bytes)));
+    }
+    let data = data.strip_prefix(&[0xef, 0xbb, 0xbf]).unwrap_or(data);
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        // the block end cut the last char
+        Err(err) if err.error_len().is_none() => {
+            std::str::from_utf8(&data[..err.valid_up_to()]).ok()?
+        }
+        Err(_) => return None,
+    };
+    let control = text
+        .chars()
+        .filter(|c| c.is_control() && !c.is_whitespace())
+        .count();
+    // the nul or many control chars is binary
+    if text.contains('\0') || control * 10 > text.chars().count() {
+        return None;
+    }
+    Some(("utf-8", text.to_string()))
+}
+
+/// the hex dump of the data, offset, hex bytes and ascii
+#[inline]
+fn hex_dump(data: &[u8]) -> String {
+    let mut out = String::new();
+    for (index, line) in data.chunks(HEX_LINE).enumerate() {
+        let hex = line
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let ascii = line
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+        out.push_str(&format!(
+            "{:08x}  {hex:<width$}  |{ascii}|\n",
+            index * HEX_LINE,
+            width = HEX_LINE * 3 - 1
+        ));
+    }
+    out
+}
+
+/// read the first block of the remote file, print the text or the hex dump
+#[inline]
+pub async fn preview(
+    client: &FileStoreClient,
+    file: PathBuf,
+    bytes: usize,
+    json: bool,
+) -> Result<()> {
+    let netx = client.netx();
+    let server = impl_struct!(netx=>IFileStoreService);
+    let info = server.get_file_info(&file, false, false).await?;
+    let key = server.create_pull(&file).await?;
+    let data = server.read(key, 0, bytes).await;
+    server.finish_read_key(key).await;
+    let data = data?;
+    log::debug!("preview file:{} read:{}", file.display(), data.len());
+
+    let (encoding, text, hex) = match decode_text(&data) {
+        Some((encoding, text)) => (encoding, Some(text), None),
+        None => ("binary", None, Some(hex_dump(&data))),
+    };
+    if json {
+        let path = file.to_string_lossy();
+        return print_json(&PreviewOutput {
+            path: &path,
+            size: info.size,
+            bytes: data.len(),
+            encoding,
+            text,
+            hex,
+        });
+    }
+    println!(
+        "{} {}/{} bytes {}",
+        style(file.display()).cyan().bold(),
+        style(data.len()).yellow(),
+        style(info.size).yellow(),
+        style(encoding).magenta()
+    );
+    if let Some(text) = text {
+        println!("{text}");
+    }
+    if let Some(hex) = hex {
+        print!("{hex}");
+    }
+    if (data.len() as u64) < info.size {
+        println!("{}", style("...").dim());
+    }
+    Ok(())
+}
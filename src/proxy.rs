@@ -0,0 +1,165 @@
+use anyhow::{bail, ensure, Context, Result};
+use std::net::IpAddr;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_socks::tcp::Socks5Stream;
+
+/// proxy kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyKind {
+    Socks5,
+    Http,
+}
+
+/// proxy server
+/// socks5://[user:password@]host:port
+/// http://host:port
+#[derive(Debug, Clone)]
+pub struct Proxy {
+    pub kind: ProxyKind,
+    pub addr: String,
+    pub auth: Option<(String, String)>,
+    /// the NO_PROXY hosts of the env proxy connect directly, none the config proxy not bypass
+    pub no_proxy: Option<Vec<String>>,
+}
+
+impl Proxy {
+    /// parse proxy url
+    #[inline]
+    pub fn parse(url: &str) -> Result<Self> {
+        let (scheme, rest) = url.split_once("://").with_context(|| {
+            format!("proxy:{url} not found scheme, example: socks5://host:port")
+        })?;
+        let kind = match scheme.to_lowercase().as_str() {
+            "socks5" | "socks5h" => ProxyKind::Socks5,
+            "http" | "https" => ProxyKind::Http,
+            _ => bail!("proxy:{url} scheme:{scheme} not support, support socks5 and http"),
+        };
+        let rest = rest.trim_end_matches('/');
+        let (auth, addr) = match rest.rsplit_once('@') {
+            Some((auth, addr)) => {
+                let (user, password) = auth.split_once(':').unwrap_or((auth, ""));
+                (Some((user.to_string(), password.to_string())), addr)
+            }
+            None => (None, rest),
+        };
+        ensure!(!addr.is_empty(), "proxy:{url} not found addr");
+        let addr = if addr.rsplit_once(':').is_some() {
+            addr.to_string()
+        } else {
+            match kind {
+                ProxyKind::Socks5 => format!("{addr}:1080"),
+                ProxyKind::Http => format!("{addr}:80"),
+            }
+        };
+        Ok(Self {
+            kind,
+            addr,
+            auth,
+            no_proxy: None,
+        })
+    }
+
+    /// find proxy, config first, then env HTTPS_PROXY excluding the env NO_PROXY hosts
+    #[inline]
+    pub fn find(config_proxy: Option<&str>) -> Result<Option<Self>> {
+        if let Some(url) = config_proxy {
+            return Ok(Some(Self::parse(url)?));
+        }
+        match std::env::var("HTTPS_PROXY").or_else(|_| std::env::var("https_proxy")) {
+            Ok(url) if !url.is_empty() => {
+                let mut proxy = Self::parse(&url)?;
+                let no_proxy = std::env::var("NO_PROXY")
+                    .or_else(|_| std::env::var("no_proxy"))
+                    .unwrap_or_default();
+                proxy.no_proxy = Some(
+                    no_proxy
+                        .split(',')
+                        .map(|host| host.trim().to_lowercase())
+                        .filter(|host| !host.is_empty())
+                        .collect(),
+                );
+                Ok(Some(proxy))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// the server addr connect directly: the loopback of the env proxy and the NO_PROXY hosts,
+    /// `example.com` and `.example.com` match the host and the subdomains, `*` match all
+    #[inline]
+    pub fn bypass(&self, addr: &str) -> bool {
+        let Some(ref no_proxy) = self.no_proxy else {
+            return false;
+        };
+        let addr = addr.to_lowercase();
+        let host = match addr.rsplit_once(':') {
+            Some((host, port)) if port.parse::<u16>().is_ok() => host,
+            _ => addr.as_str(),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host == "localhost" || host.parse::<IpAddr>().map_or(false, |ip| ip.is_loopback()) {
+            return true;
+        }
+        no_proxy.iter().any(|entry| {
+            let suffix = entry.trim_start_matches('.');
+            entry == "*"
+                || *entry == addr
+                || host == suffix
+                || host
+                    .strip_suffix(suffix)
+                    .map_or(false, |rest| rest.ends_with('.'))
+        })
+    }
+
+    /// connect target addr by proxy
+    #[inline]
+    pub async fn connect(&self, target: &str) -> Result<TcpStream> {
+        match self.kind {
+            ProxyKind::Socks5 => {
+                let stream = match self.auth {
+                    Some((ref user, ref password)) => {
+                        Socks5Stream::connect_with_password(
+                            self.addr.as_str(),
+                            target,
+                            user,
+                            password,
+                        )
+                        .await
+                    }
+                    None => Socks5Stream::connect(self.addr.as_str(), target).await,
+                };
+                let stream = stream.with_context(|| {
+                    format!("socks5 proxy:{} connect:{} error", self.addr, target)
+                })?;
+                Ok(stream.into_inner())
+            }
+            ProxyKind::Http => {
+                let mut stream = TcpStream::connect(&self.addr)
+                    .await
+                    .with_context(|| format!("connect http proxy:{} error", self.addr))?;
+                let request = format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n\r\n");
+                stream.write_all(request.as_bytes()).await?;
+
+                let mut reader = BufReader::new(&mut stream);
+                let mut status = String::new();
+                reader.read_line(&mut status).await?;
+                ensure!(
+                    status.split_whitespace().nth(1) == Some("200"),
+                    "http proxy:{} connect:{} error:{}",
+                    self.addr,
+                    target,
+                    status.trim()
+                );
+                // skip response headers
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+                        break;
+                    }
+                }
+                Ok(stream)
+            }
+        }
+    }
+}
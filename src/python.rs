@@ -0,0 +1,147 @@
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::client::{FileStoreClient, TransferOptions};
+use crate::config::{get_config_path, parse_config, ConfigFormat, DefaultsConfig};
+use crate::output::{EntryOutput, FileInfoOutput};
+use crate::redact::redact;
+
+#[inline]
+fn py_err(err: anyhow::Error) -> PyErr {
+    PyRuntimeError::new_err(redact(&format!("{err:#}")))
+}
+
+/// file store client, the methods return awaitable
+#[pyclass(name = "Client")]
+struct PyClient {
+    client: Arc<FileStoreClient>,
+    defaults: DefaultsConfig,
+}
+
+#[pymethods]
+impl PyClient {
+    /// connect the server by the config file, if not set find the default config
+    #[staticmethod]
+    #[pyo3(signature = (config = None))]
+    fn connect(py: Python, config: Option<PathBuf>) -> PyResult<&PyAny> {
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let path = match config {
+                Some(path) => path,
+                None => get_config_path().map_err(py_err)?,
+            };
+            let config = tokio::fs::read_to_string(&path).await?;
+            let config =
+                parse_config(&config, ConfigFormat::detect(&path, &config)).map_err(py_err)?;
+            let defaults = config.defaults.clone();
+            let client = FileStoreClient::connect(config).await.map_err(py_err)?;
+            Ok(PyClient {
+                client: Arc::new(client),
+                defaults,
+            })
+        })
+    }
+
+    /// push the local file to the remote path, return the size
+    #[pyo3(signature = (local, remote, overwrite = false, async_mode = false, block = None))]
+    fn push<'a>(
+        &self,
+        py: Python<'a>,
+        local: PathBuf,
+        remote: String,
+        overwrite: bool,
+        async_mode: bool,
+        block: Option<usize>,
+    ) -> PyResult<&'a PyAny> {
+        let client = self.client.clone();
+        let options = TransferOptions::new(&self.defaults, async_mode, block, overwrite);
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            client
+                .push_file(&local, &remote, options)
+                .await
+                .map_err(py_err)
+        })
+    }
+
+    /// pull the remote file to the local path, return the size
+    #[pyo3(signature = (remote, local, overwrite = false, async_mode = false, block = None))]
+    fn pull<'a>(
+        &self,
+        py: Python<'a>,
+        remote: PathBuf,
+        local: PathBuf,
+        overwrite: bool,
+        async_mode: bool,
+        block: Option<usize>,
+    ) -> PyResult<&'a PyAny> {
+        let client = self.client.clone();
+        let options = TransferOptions::new(&self.defaults, async_mode, block, overwrite);
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            client
+                .pull_file(&remote, &local, options)
+                .await
+                .map_err(py_err)
+        })
+    }
+
+    /// list the remote directory, return the entry dict list
+    fn list<'a>(&self, py: Python<'a>, dir: PathBuf) -> PyResult<&'a PyAny> {
+        let client = self.client.clone();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let entries = client.list(&dir).await.map_err(py_err)?;
+            Python::with_gil(|py| {
+                entries
+                    .iter()
+                    .map(EntryOutput::from)
+                    .map(|entry| {
+                        let dict = PyDict::new(py);
+                        dict.set_item("name", entry.name)?;
+                        dict.set_item("type", entry.r#type)?;
+                        dict.set_item("size", entry.size)?;
+                        dict.set_item("create_time", entry.create_time)?;
+                        dict.set_item("modify_time", entry.modify_time)?;
+                        Ok(dict.to_object(py))
+                    })
+                    .collect::<PyResult<Vec<PyObject>>>()
+            })
+        })
+    }
+
+    /// the remote file info dict, with blake3 and sha256
+    fn info<'a>(&self, py: Python<'a>, file: PathBuf) -> PyResult<&'a PyAny> {
+        let client = self.client.clone();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let info = client.info(&file).await.map_err(py_err)?;
+            let info = FileInfoOutput::from(&info);
+            Python::with_gil(|py| {
+                let dict = PyDict::new(py);
+                dict.set_item("name", info.name)?;
+                dict.set_item("size", info.size)?;
+                dict.set_item("create_time", info.create_time)?;
+                dict.set_item("b3", info.b3)?;
+                dict.set_item("sha256", info.sha256)?;
+                dict.set_item("can_modify", info.can_modify)?;
+                dict.set_item("sha512", info.sha512)?;
+                dict.set_item("content_type", info.content_type)?;
+                Ok(dict.to_object(py))
+            })
+        })
+    }
+
+    /// remove the remote file or directory
+    fn remove<'a>(&self, py: Python<'a>, path: PathBuf) -> PyResult<&'a PyAny> {
+        let client = self.client.clone();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            client.remove(&path).await.map_err(py_err)
+        })
+    }
+}
+
+/// python module, `import fsc`
+#[pymodule]
+fn fsc(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyClient>()?;
+    Ok(())
+}
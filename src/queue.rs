@@ -0,0 +1,397 @@
+use anyhow::{bail, ensure, Context, Result};
+use chrono::Local;
+use console::style;
+use futures::stream::{FuturesUnordered, StreamExt};
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+use fsc::client::TransferOptions;
+use fsc::config::{get_config_path, get_current_exec_path, DefaultsConfig};
+use fsc::output::TransferSummary;
+use fsc::FileStoreClient;
+
+/// the interval of the daemon check the queue
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// the job states
+const PENDING: &str = "pending";
+const RUNNING: &str = "running";
+const PAUSED: &str = "paused";
+const CANCELLED: &str = "cancelled";
+const DONE: &str = "done";
+const FAILED: &str = "failed";
+
+/// the queued transfer job
+#[derive(Serialize)]
+pub struct Job {
+    pub id: i64,
+    pub time: String,
+    /// push or pull
+    pub action: String,
+    pub local: String,
+    pub remote: String,
+    /// the larger priority run first
+    pub priority: i64,
+    pub overwrite: bool,
+    pub state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Job {
+    #[inline]
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            time: row.get("time")?,
+            action: row.get("action")?,
+            local: row.get("local")?,
+            remote: row.get("remote")?,
+            priority: row.get("priority")?,
+            overwrite: row.get("overwrite")?,
+            state: row.get("state")?,
+            error: row.get("error")?,
+        })
+    }
+
+    /// print the job line
+    #[inline]
+    pub fn print_line(&self) {
+        let state = match self.state.as_str() {
+            DONE => style(self.state.as_str()).green(),
+            FAILED | CANCELLED => style(self.state.as_str()).red(),
+            RUNNING => style(self.state.as_str()).cyan(),
+            _ => style(self.state.as_str()).yellow(),
+        };
+        let (from, to) = if self.action == "pull" {
+            (&self.remote, &self.local)
+        } else {
+            (&self.local, &self.remote)
+        };
+        println!(
+            "{:>5} {} {:<4} {:<9} {:>4} {from} -> {to}",
+            style(self.id).cyan(),
+            self.time,
+            self.action,
+            state,
+            self.priority
+        );
+        if let Some(ref error) = self.error {
+            println!("      error: {}", style(error).red());
+        }
+    }
+}
+
+/// the queue db file, next to the config file
+#[inline]
+fn db_path() -> Result<PathBuf> {
+    let dir = match get_config_path() {
+        Ok(config) => config.parent().map(Path::to_path_buf).unwrap_or_default(),
+        Err(_) => get_current_exec_path()?,
+    };
+    Ok(dir.join("queue.db"))
+}
+
+/// open the queue db, create the table if not exists
+#[inline]
+fn open() -> Result<Connection> {
+    let path = db_path()?;
+    let conn = Connection::open(&path)
+        .with_context(|| format!("open queue db:{} error", path.display()))?;
+    // the daemon and the jobs command write the db at the same time
+    conn.busy_timeout(Duration::from_secs(5))?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS jobs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            time TEXT NOT NULL,
+            action TEXT NOT NULL,
+            local TEXT NOT NULL,
+            remote TEXT NOT NULL,
+            priority INTEGER NOT NULL,
+            overwrite INTEGER NOT NULL,
+            state TEXT NOT NULL,
+            error TEXT
+        );
+        CREATE INDEX IF NOT EXISTS jobs_state ON jobs (state, priority);",
+    )?;
+    Ok(conn)
+}
+
+/// queue the push or pull job, return the job id
+#[inline]
+pub fn add(
+    action: &str,
+    local: &Path,
+    remote: &str,
+    priority: i64,
+    overwrite: bool,
+) -> Result<i64> {
+    if action == "push" {
+        ensure!(local.is_file(), "path:{} not file", local.display());
+    }
+    // the daemon may run in the other dir
+    let local = if local.is_absolute() {
+        local.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(local)
+    };
+    let conn = open()?;
+    conn.execute(
+        "INSERT INTO jobs (time, action, local, remote, priority, overwrite, state)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            action,
+            local.to_string_lossy(),
+            remote.replace('\\', "/"),
+            priority,
+            overwrite,
+            PENDING
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// has the pending jobs
+#[inline]
+pub fn has_pending() -> Result<bool> {
+    let pending: i64 = open()?.query_row(
+        "SELECT COUNT(*) FROM jobs WHERE state = ?1",
+        params![PENDING],
+        |row| row.get(0),
+    )?;
+    Ok(pending > 0)
+}
+
+/// the queued jobs by the run order, the finished jobs if all
+#[inline]
+pub fn list(all: bool) -> Result<Vec<Job>> {
+    let conn = open()?;
+    let mut statement = conn.prepare(
+        "SELECT * FROM jobs
+        WHERE ?1 OR state IN ('pending', 'running', 'paused')
+        ORDER BY state = 'running' DESC, priority DESC, id",
+    )?;
+    let jobs = statement
+        .query_map(params![all], Job::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(jobs)
+}
+
+/// change the job state, the running job is stopped by the daemon
+#[inline]
+fn set_state(id: i64, state: &str, from: &[&str]) -> Result<()> {
+    let conn = open()?;
+    let current = state(&conn, id)?.with_context(|| format!("not found job:{id}"))?;
+    if !from.contains(&current.as_str()) {
+        bail!("job:{id} is {current}, can't change to {state}");
+    }
+    conn.execute(
+        "UPDATE jobs SET state = ?2 WHERE id = ?1 AND state = ?3",
+        params![id, state, current],
+    )?;
+    Ok(())
+}
+
+/// cancel the queued or running job
+#[inline]
+pub fn cancel(id: i64) -> Result<()> {
+    set_state(id, CANCELLED, &[PENDING, RUNNING, PAUSED])
+}
+
+/// pause the queued or running job, the running job restart when resumed
+#[inline]
+pub fn pause(id: i64) -> Result<()> {
+    set_state(id, PAUSED, &[PENDING, RUNNING])
+}
+
+/// resume the paused job
+#[inline]
+pub fn resume(id: i64) -> Result<()> {
+    set_state(id, PENDING, &[PAUSED])
+}
+
+/// take the pending jobs of the highest priority, mark running
+#[inline]
+fn take(limit: usize) -> Result<Vec<Job>> {
+    let mut conn = open()?;
+    let transaction = conn.transaction()?;
+    let jobs = transaction
+        .prepare(
+            "SELECT * FROM jobs WHERE state = 'pending'
+            ORDER BY priority DESC, id LIMIT ?1",
+        )?
+        .query_map(params![limit as i64], Job::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for job in jobs.iter() {
+        transaction.execute(
+            "UPDATE jobs SET state = ?2, error = NULL WHERE id = ?1",
+            params![job.id, RUNNING],
+        )?;
+    }
+    transaction.commit()?;
+    Ok(jobs)
+}
+
+/// the job state, none if the job removed
+#[inline]
+fn state(conn: &Connection, id: i64) -> Result<Option<String>> {
+    match conn.query_row("SELECT state FROM jobs WHERE id = ?1", params![id], |row| {
+        row.get(0)
+    }) {
+        Ok(state) => Ok(Some(state)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// set the finished job state, keep the state if cancelled or paused when running
+#[inline]
+fn finish(id: i64, state: &str, error: Option<String>) -> Result<()> {
+    open()?.execute(
+        "UPDATE jobs SET state = ?2, error = ?3 WHERE id = ?1 AND state = 'running'",
+        params![id, state, error],
+    )?;
+    Ok(())
+}
+
+/// run the job transfer, return the summary
+#[inline]
+async fn run_job(client: &FileStoreClient, job: &Job, options: TransferOptions) -> TransferSummary {
+    let action = if job.action == "pull" {
+        "queue pull"
+    } else {
+        "queue push"
+    };
+    let start = Instant::now();
+    let summary =
+        TransferSummary::new(action, job.local.clone(), job.remote.clone()).with_start(start);
+    let result = async {
+        if job.action == "pull" {
+            if let Some(parent) = Path::new(&job.local).parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            client.pull_file(&job.remote, &job.local, options).await
+        } else {
+            client.push_file(&job.local, &job.remote, options).await
+        }
+    }
+    .await;
+    match result {
+        Ok(size) => {
+            crate::metrics::transfer(action, size, start.elapsed());
+            summary.finish(1, size)
+        }
+        Err(err) => {
+            crate::metrics::failed(action);
+            summary.fail(1, format!("{err:#}"))
+        }
+    }
+}
+
+/// run the pending jobs one by one until none, example the offline queued push when the connection returns,
+/// the running jobs of the daemon are not restarted, return the summaries
+#[inline]
+pub async fn drain(
+    client: &FileStoreClient,
+    defaults: &DefaultsConfig,
+    cancel: CancellationToken,
+) -> Result<Vec<TransferSummary>> {
+    let mut summaries = vec![];
+    while !cancel.is_cancelled() {
+        let Some(job) = take(1)?.pop() else {
+            break;
+        };
+        log::info!("start job:{} {} {}", job.id, job.action, job.local);
+        let options = TransferOptions::new(defaults, false, None, job.overwrite)
+            .with_cancel(cancel.child_token());
+        let summary = run_job(client, &job, options).await;
+        crate::history::record(&summary);
+        if cancel.is_cancelled() {
+            // the interrupted job run again at the next time
+            finish(job.id, PENDING, None)?;
+        } else if summary.success {
+            log::info!("job:{} finish size:{}", job.id, summary.size);
+            finish(job.id, DONE, None)?;
+        } else {
+            log::error!(
+                "job:{} failed:{}",
+                job.id,
+                summary.error.as_deref().unwrap_or_default()
+            );
+            finish(job.id, FAILED, summary.error.clone())?;
+        }
+        summaries.push(summary);
+    }
+    Ok(summaries)
+}
+
+/// run the queued jobs by the priority until cancelled, at most concurrency jobs at the same time,
+/// the running jobs of the last run are restarted
+#[inline]
+pub async fn run(
+    client: &FileStoreClient,
+    defaults: &DefaultsConfig,
+    concurrency: usize,
+    cancel: CancellationToken,
+) -> Result<()> {
+    let concurrency = concurrency.max(1);
+    let recovered = open()?.execute(
+        "UPDATE jobs SET state = ?1 WHERE state = ?2",
+        params![PENDING, RUNNING],
+    )?;
+    log::info!("start job queue concurrency:{concurrency} recovered:{recovered}");
+
+    let mut running = HashMap::new();
+    let mut tasks = FuturesUnordered::new();
+    loop {
+        if running.len() < concurrency {
+            for job in take(concurrency - running.len())? {
+                log::info!("start job:{} {} {}", job.id, job.action, job.local);
+                let token = cancel.child_token();
+                let options = TransferOptions::new(defaults, false, None, job.overwrite)
+                    .with_cancel(token.clone());
+                running.insert(job.id, token);
+                tasks.push(async move {
+                    let summary = run_job(client, &job, options).await;
+                    (job.id, summary)
+                });
+            }
+        }
+        tokio::select! {
+            Some((id, summary)) = tasks.next(), if !tasks.is_empty() => {
+                running.remove(&id);
+                crate::history::record(&summary);
+                if summary.success {
+                    log::info!("job:{id} finish size:{}", summary.size);
+                    finish(id, DONE, None)?;
+                } else {
+                    log::error!("job:{id} failed:{}", summary.error.as_deref().unwrap_or_default());
+                    finish(id, FAILED, summary.error)?;
+                }
+            }
+            _ = tokio::time::sleep(POLL_INTERVAL) => {
+                // stop the cancelled or paused running jobs
+                let conn = open()?;
+                for (id, token) in running.iter() {
+                    if state(&conn, *id)?.as_deref() != Some(RUNNING) {
+                        log::info!("stop job:{id}");
+                        token.cancel();
+                    }
+                }
+            }
+            _ = cancel.cancelled() => break,
+        }
+    }
+
+    // the interrupted jobs run again at the next start
+    while let Some((id, _)) = tasks.next().await {
+        finish(id, PENDING, None)?;
+    }
+    log::info!("stop job queue");
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use fsc::config::{get_config_path, get_current_exec_path};
+use fsc::manifest::ManifestFile;
+
+/// the pushed file done marker, json line per file
+#[derive(Serialize, Deserialize)]
+pub struct DoneMarker {
+    /// the remote file path
+    pub remote: String,
+    /// the local file size and modified secs when pushed
+    pub local_size: u64,
+    pub modified: u64,
+    #[serde(flatten)]
+    pub file: ManifestFile,
+}
+
+/// the image push completion state, the done markers of the pushed files
+pub struct ImageResume {
+    path: PathBuf,
+    markers: HashMap<String, DoneMarker>,
+}
+
+/// the local file size and modified secs
+#[inline]
+fn local_stat(file: &Path) -> Result<(u64, u64)> {
+    let metadata = std::fs::metadata(file)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    Ok((metadata.len(), modified))
+}
+
+/// the state file of the image, next to the config file, named by the local and remote path
+#[inline]
+fn state_path(local: &Path, remote: &str) -> Result<PathBuf> {
+    let dir = match get_config_path() {
+        Ok(config) => config.parent().map(Path::to_path_buf).unwrap_or_default(),
+        Err(_) => get_current_exec_path()?,
+    };
+    let local = local.canonicalize().unwrap_or_else(|_| local.to_path_buf());
+    let key = blake3::hash(format!("{}\n{remote}", local.display()).as_bytes());
+    Ok(dir
+        .join("image-resume")
+        .join(format!("{}.jsonl", &key.to_hex()[..16])))
+}
+
+impl ImageResume {
+    /// open the state of the image push, load the done markers if resume, else start new
+    #[inline]
+    pub fn open(local: &Path, remote: &str, resume: bool) -> Result<Self> {
+        let path = state_path(local, remote)?;
+        let mut markers = HashMap::new();
+        if !resume {
+            if path.exists() {
+                std::fs::remove_file(&path)?;
+            }
+        } else if path.exists() {
+            for line in BufReader::new(File::open(&path)?).lines() {
+                let line = line?;
+                // the last line may be cut by the interrupt
+                match serde_json::from_str::<DoneMarker>(&line) {
+                    Ok(marker) => {
+                        markers.insert(marker.remote.clone(), marker);
+                    }
+                    Err(err) => log::warn!("skip image resume marker error:{err}"),
+                }
+            }
+        }
+        log::debug!(
+            "image resume state:{} markers:{}",
+            path.display(),
+            markers.len()
+        );
+        Ok(Self { path, markers })
+    }
+
+    /// take the done marker of the remote file, none if the local file changed after pushed
+    #[inline]
+    pub fn take(&mut self, local: &Path, remote: &str) -> Option<DoneMarker> {
+        let marker = self.markers.remove(remote)?;
+        match local_stat(local) {
+            Ok((size, modified)) if size == marker.local_size && modified == marker.modified => {
+                Some(marker)
+            }
+            _ => None,
+        }
+    }
+
+    /// append the done marker of the pushed file
+    #[inline]
+    pub fn done(&self, local: &Path, remote: &str, file: &ManifestFile) -> Result<()> {
+        let (local_size, modified) = local_stat(local)?;
+        let mut line = serde_json::to_string(&DoneMarker {
+            remote: remote.to_string(),
+            local_size,
+            modified,
+            file: ManifestFile {
+                path: file.path.clone(),
+                size: file.size,
+                b3: file.b3.clone(),
+                link: None,
+            },
+        })?;
+        line.push('\n');
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("open image resume state:{} error", self.path.display()))?
+            .write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// the image push finished, remove the state
+    #[inline]
+    pub fn finish(self) {
+        if self.path.exists() {
+            if let Err(err) = std::fs::remove_file(&self.path) {
+                log::warn!(
+                    "remove image resume state:{} error:{err}",
+                    self.path.display()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use anyhow::Result;
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use crate::config::ConnectConfig;
+
+/// the max delay between the retries
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// netx report the connection and timeout errors by the message
+const TRANSIENT_MESSAGES: &[&str] = &[
+    "time out",
+    "timed out",
+    "timeout",
+    "not connect",
+    "disconnect",
+    "connection reset",
+    "connection refused",
+    "connection closed",
+    "broken pipe",
+];
+
+/// the rpc retry policy, exponential backoff with jitter
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// the retry count, 0 not retry
+    pub retries: u32,
+    /// the delay before the first retry
+    pub delay: Duration,
+    /// the delay multiplier of the next retry
+    pub backoff: f64,
+}
+
+impl Default for RetryPolicy {
+    #[inline]
+    fn default() -> Self {
+        Self::from(&ConnectConfig::default())
+    }
+}
+
+impl From<&ConnectConfig> for RetryPolicy {
+    #[inline]
+    fn from(connect: &ConnectConfig) -> Self {
+        Self {
+            retries: connect.failover_retries,
+            delay: Duration::from_millis(connect.retry_delay_ms),
+            backoff: connect.retry_backoff.max(1.0),
+        }
+    }
+}
+
+/// the connection, timeout and io errors can be retried, the server reported errors not
+#[inline]
+pub fn is_transient(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        if cause.is::<std::io::Error>() || cause.is::<tokio::time::error::Elapsed>() {
+            return true;
+        }
+        let msg = cause.to_string().to_lowercase();
+        TRANSIENT_MESSAGES
+            .iter()
+            .any(|transient| msg.contains(transient))
+    })
+}
+
+/// random 0..1, not need the rand crate for the jitter
+#[inline]
+fn random() -> f64 {
+    let value = RandomState::new().build_hasher().finish();
+    (value % 10000) as f64 / 10000.0
+}
+
+impl RetryPolicy {
+    /// the delay before the retry, retry start from 1, the jitter is +-25%
+    #[inline]
+    pub fn delay(&self, retry: u32) -> Duration {
+        let secs = (self.delay.as_secs_f64() * self.backoff.powi(retry.saturating_sub(1) as i32))
+            .min(MAX_DELAY.as_secs_f64());
+        Duration::from_secs_f64(secs * (0.75 + random() * 0.5))
+    }
+
+    /// run the rpc, retry the connection, timeout and io errors,
+    /// the server reported errors return immediately
+    #[inline]
+    pub async fn run<T, F, Fut>(&self, action: &str, mut rpc: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut retry = 0;
+        loop {
+            match rpc().await {
+                Ok(value) => return Ok(value),
+                Err(err) if retry < self.retries && is_transient(&err) => {
+                    retry += 1;
+                    let delay = self.delay(retry);
+                    log::warn!(
+                        "{action} error:{err:#}, retry {retry}/{} after {}ms",
+                        self.retries,
+                        delay.as_millis()
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use anyhow::{ensure, Context, Result};
+use std::path::Path;
+use std::str::FromStr;
+
+/// rewrite the path prefix between the local and the remote tree,
+/// example `build/output/=images/v3/`, the push map local to remote, the pull the reverse
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenamePrefix {
+    pub local: String,
+    pub remote: String,
+}
+
+impl FromStr for RenamePrefix {
+    type Err = anyhow::Error;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self> {
+        let (local, remote) = s.split_once('=').with_context(|| {
+            format!("rename prefix:{s} format error, example build/output/=images/v3/")
+        })?;
+        let (local, remote) = (normalize(local), normalize(remote));
+        ensure!(!local.is_empty(), "rename prefix:{s} local prefix is empty");
+        Ok(Self { local, remote })
+    }
+}
+
+impl std::fmt::Display for RenamePrefix {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.local, self.remote)
+    }
+}
+
+/// the path with the `/` separator, without the leading `./`
+#[inline]
+fn normalize(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    path.trim_start_matches("./").to_string()
+}
+
+/// the rest of the path after the prefix, the prefix match only at the `/` boundary,
+/// `build/out` match `build/out/a.bin` but not `build/output/a.bin`
+#[inline]
+fn strip_dir_prefix<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    let rest = path.strip_prefix(prefix.trim_end_matches('/'))?;
+    if rest.is_empty() {
+        return Some(rest);
+    }
+    rest.strip_prefix('/')
+}
+
+/// the rewritten path by the first matched prefix, none if not matched
+#[inline]
+fn rewrite<'a>(path: &Path, rules: impl Iterator<Item = (&'a str, &'a str)>) -> Option<String> {
+    let path = normalize(&path.to_string_lossy());
+    for (from, to) in rules {
+        if let Some(rest) = strip_dir_prefix(&path, from) {
+            let to = to.trim_end_matches('/');
+            return Some(match (to.is_empty(), rest.is_empty()) {
+                (true, _) => rest.to_string(),
+                (false, true) => to.to_string(),
+                (false, false) => format!("{to}/{rest}"),
+            });
+        }
+    }
+    None
+}
+
+/// the remote path of the local file by the first matched local prefix
+#[inline]
+pub fn to_remote(rules: &[RenamePrefix], local: &Path) -> Option<String> {
+    rewrite(
+        local,
+        rules
+            .iter()
+            .map(|rule| (rule.local.as_str(), rule.remote.as_str())),
+    )
+}
+
+/// the local path of the remote file by the first matched remote prefix
+#[inline]
+pub fn to_local(rules: &[RenamePrefix], remote: &Path) -> Option<String> {
+    rewrite(
+        remote,
+        rules
+            .iter()
+            .filter(|rule| !rule.remote.is_empty())
+            .map(|rule| (rule.remote.as_str(), rule.local.as_str())),
+    )
+}
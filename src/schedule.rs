@@ -0,0 +1,290 @@
+use anyhow::{bail, ensure, Context, Result};
+use chrono::Local;
+use cron::Schedule;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Instant;
+use tokio::fs::File;
+use tokio_util::sync::CancellationToken;
+
+use fsc::checksum::{file_checksum, HashAlgorithm};
+use fsc::client::{computer_b3, TransferOptions};
+use fsc::config::{DefaultsConfig, JobConfig, NotifyConfig, SyncDirection};
+use fsc::interface_server::FileInfo;
+use fsc::output::TransferSummary;
+use fsc::FileStoreClient;
+
+use crate::watch::visit_dirs;
+
+/// sync file result
+enum Synced {
+    Transferred(u64),
+    Skipped,
+}
+
+/// the local file is same as the remote file, compare the blake3,
+/// fast verify compare the size and the xxh3
+#[inline]
+async fn same_file(
+    client: &FileStoreClient,
+    file: &Path,
+    remote: &Path,
+    info: Option<&FileInfo>,
+    fast_verify: bool,
+) -> Result<bool> {
+    let Some(info) = info else {
+        return Ok(false);
+    };
+    if !fast_verify {
+        let hash = computer_b3(&mut File::open(file).await?).await;
+        return Ok(info.b3.as_ref() == Some(&hash));
+    }
+    if std::fs::metadata(file)?.len() != info.size {
+        return Ok(false);
+    }
+    let hash = file_checksum(file, HashAlgorithm::Xxh3).await?;
+    let start = Instant::now();
+    let remote_hash = client.checksums(remote, &[HashAlgorithm::Xxh3]).await?;
+    crate::metrics::rpc("get_file_checksums", start.elapsed());
+    Ok(remote_hash.first().map(|(_, hex)| hex) == Some(&hash))
+}
+
+/// push the local file if the remote hash is different
+#[inline]
+async fn push_changed(
+    client: &FileStoreClient,
+    file: &Path,
+    remote: &str,
+    info: Option<&FileInfo>,
+    job: &JobConfig,
+    options: &TransferOptions,
+) -> Result<Synced> {
+    if same_file(client, file, Path::new(remote), info, job.fast_verify).await? {
+        return Ok(Synced::Skipped);
+    }
+    let size = client.push_file(file, remote, options.clone()).await?;
+    Ok(Synced::Transferred(size))
+}
+
+/// pull the remote file if the local hash is different
+#[inline]
+async fn pull_changed(
+    client: &FileStoreClient,
+    remote: &Path,
+    file: &Path,
+    info: Option<&FileInfo>,
+    job: &JobConfig,
+    options: &TransferOptions,
+) -> Result<Synced> {
+    if file.is_file() {
+        if same_file(client, file, remote, info, job.fast_verify).await? {
+            return Ok(Synced::Skipped);
+        }
+    } else if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let size = client.pull_file(remote, file, options.clone()).await?;
+    Ok(Synced::Transferred(size))
+}
+
+/// sync the job dirs, skip the unchanged files, the failed file not abort the job
+#[inline]
+async fn sync(
+    client: &FileStoreClient,
+    job: &JobConfig,
+    options: &TransferOptions,
+) -> Result<TransferSummary> {
+    let action = match job.direction {
+        SyncDirection::Push => "sync push",
+        SyncDirection::Pull => "sync pull",
+    };
+    let mut summary = TransferSummary::new(
+        action,
+        job.local.display().to_string(),
+        job.remote.display().to_string(),
+    );
+
+    // the local file and the remote path pairs, if case insensitive
+    // the existing file of the other case is the pair, not transfer the second file
+    let mut pairs = vec![];
+    match job.direction {
+        SyncDirection::Push => {
+            ensure!(job.local.is_dir(), "path:{} not dir", job.local.display());
+            let mut files = vec![];
+            visit_dirs(&job.local, &mut files)?;
+            let existing = if options.case_insensitive {
+                client
+                    .list_recursive(&job.remote, None)
+                    .await
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|(_, entry)| entry.file_type != 1)
+                    .map(|(remote, _)| {
+                        let remote = remote.to_string_lossy().replace('\\', "/");
+                        (remote.to_lowercase(), remote)
+                    })
+                    .collect()
+            } else {
+                HashMap::new()
+            };
+            for file in files {
+                let relative = file.strip_prefix(&job.local)?;
+                let remote = job
+                    .remote
+                    .join(relative)
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                let remote = existing
+                    .get(&remote.to_lowercase())
+                    .cloned()
+                    .unwrap_or(remote);
+                pairs.push((file, PathBuf::from(remote)));
+            }
+        }
+        SyncDirection::Pull => {
+            let mut existing = HashMap::new();
+            if options.case_insensitive && job.local.is_dir() {
+                let mut files = vec![];
+                visit_dirs(&job.local, &mut files)?;
+                for file in files {
+                    existing.insert(file.to_string_lossy().to_lowercase(), file);
+                }
+            }
+            for (remote, entry) in client.list_recursive(&job.remote, None).await? {
+                if entry.file_type != 1 {
+                    let file = job.local.join(remote.strip_prefix(&job.remote)?);
+                    let file = existing
+                        .get(&file.to_string_lossy().to_lowercase())
+                        .cloned()
+                        .unwrap_or(file);
+                    pairs.push((file, remote));
+                }
+            }
+        }
+    }
+
+    // the remote files info by batch, the missing file is none,
+    // fast verify not need the remote blake3
+    let remotes = pairs
+        .iter()
+        .map(|(_, remote)| remote.clone())
+        .collect::<Vec<_>>();
+    let start = Instant::now();
+    let infos = client.files_info(&remotes, !job.fast_verify, false).await?;
+    crate::metrics::rpc("get_files_info", start.elapsed());
+
+    let (mut files, mut size, mut failed) = (0, 0, 0);
+    for ((file, remote), info) in pairs.into_iter().zip(infos) {
+        ensure!(!options.cancel.is_cancelled(), "{action} cancelled");
+        let info = info.ok();
+        let start = Instant::now();
+        let result = match job.direction {
+            SyncDirection::Push => {
+                let remote = remote.to_string_lossy();
+                push_changed(client, &file, &remote, info.as_ref(), job, options).await
+            }
+            SyncDirection::Pull => {
+                pull_changed(client, &remote, &file, info.as_ref(), job, options).await
+            }
+        };
+        match result {
+            Ok(Synced::Transferred(len)) => {
+                log::info!(
+                    "{action} {} <-> {} size:{len}",
+                    file.display(),
+                    remote.display()
+                );
+                crate::metrics::transfer(action, len, start.elapsed());
+                files += 1;
+                size += len;
+            }
+            Ok(Synced::Skipped) => {
+                crate::metrics::skipped(action);
+                summary.skipped += 1;
+            }
+            Err(err) => {
+                crate::metrics::failed(action);
+                log::error!("{action} {} error:{err:#}", file.display());
+                failed += 1;
+            }
+        }
+    }
+
+    let mut summary = summary.finish(files, size);
+    if failed > 0 {
+        summary = summary.fail(failed, format!("{failed} files sync failed"));
+        summary.success = false;
+    }
+    Ok(summary)
+}
+
+/// run the config jobs by the cron schedule until cancelled,
+/// the jobs run one by one, notify when the job failed
+#[inline]
+pub async fn schedule(
+    client: &FileStoreClient,
+    jobs: BTreeMap<String, JobConfig>,
+    defaults: &DefaultsConfig,
+    notify: &NotifyConfig,
+    fast_verify: bool,
+    cancel: CancellationToken,
+) -> Result<()> {
+    ensure!(!jobs.is_empty(), "not found [jobs] in config");
+    let jobs = jobs
+        .into_iter()
+        .map(|(name, mut job)| {
+            job.fast_verify |= fast_verify;
+            let schedule = Schedule::from_str(&job.cron)
+                .with_context(|| format!("job:{name} invalid cron:{}", job.cron))?;
+            let options = TransferOptions::new(defaults, job.r#async, job.block, true)
+                .with_cancel(cancel.clone());
+            Ok((name, job, schedule, options))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    loop {
+        let now = Local::now();
+        let Some((time, (name, job, _, options))) = jobs
+            .iter()
+            .filter_map(|job| job.2.after(&now).next().map(|time| (time, job)))
+            .min_by_key(|(time, _)| *time)
+        else {
+            bail!("no upcoming job");
+        };
+        log::info!("next job:{name} at {time}");
+        let wait = (time - now).to_std().unwrap_or_default();
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = cancel.cancelled() => return Ok(()),
+        }
+
+        log::info!("start job:{name}");
+        let start = Instant::now();
+        let summary = sync(client, job, options).await.unwrap_or_else(|err| {
+            TransferSummary::new(
+                "sync",
+                job.local.display().to_string(),
+                job.remote.display().to_string(),
+            )
+            .with_start(start)
+            .fail(1, format!("{err:#}"))
+        });
+        crate::history::record(&summary);
+        crate::metrics::job(name, summary.success);
+        if summary.success {
+            log::info!(
+                "job:{name} finish files:{} skipped:{} size:{}",
+                summary.files,
+                summary.skipped,
+                summary.size
+            );
+        } else {
+            log::error!(
+                "job:{name} failed:{}",
+                summary.error.as_deref().unwrap_or_default()
+            );
+            crate::notify::notify(notify, &summary).await;
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use anyhow::{ensure, Result};
+use console::style;
+use netxclient::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
+
+use fsc::interface_server::*;
+use fsc::manifest::{Manifest, MANIFEST_FILE, SIGNATURE_FILE};
+use fsc::output::print_json;
+use fsc::FileStoreClient;
+
+/// the corrupted file
+#[derive(Serialize)]
+struct Corrupted {
+    path: String,
+    error: String,
+}
+
+/// the scrub report
+#[derive(Serialize)]
+struct ScrubReport<'a> {
+    dir: &'a str,
+    files: usize,
+    corrupted: Vec<Corrupted>,
+}
+
+/// read the image manifest, the expected blake3 of the image files
+#[inline]
+async fn manifest_hashes(
+    client: &FileStoreClient,
+    manifest: &Path,
+    hashes: &mut HashMap<PathBuf, String>,
+) -> Result<()> {
+    use tokio::io::AsyncReadExt;
+    let mut reader = client.open(manifest).await?;
+    let mut data = vec![];
+    reader.read_to_end(&mut data).await?;
+    let root = manifest.parent().unwrap_or(Path::new(""));
+    for file in Manifest::from_slice(&data)?.files {
+        let path = root.join(&file.path).to_string_lossy().replace('\\', "/");
+        hashes.insert(PathBuf::from(path), file.b3);
+    }
+    Ok(())
+}
+
+/// ask the server rehash the files of the dir, compare with the stored hash and the manifest
+#[inline]
+pub async fn scrub(
+    client: &FileStoreClient,
+    dir: PathBuf,
+    json: bool,
+    quiet: bool,
+    cancel: CancellationToken,
+) -> Result<()> {
+    let mut files = vec![];
+    let mut manifests = HashMap::new();
+    for (path, entry) in client.list_recursive(&dir, None).await? {
+        if entry.file_type == 1 {
+            continue;
+        }
+        match path.file_name().and_then(|name| name.to_str()) {
+            Some(MANIFEST_FILE) => {
+                if let Err(err) = manifest_hashes(client, &path, &mut manifests).await {
+                    log::warn!("read manifest:{} error:{err:#}", path.display());
+                }
+            }
+            Some(SIGNATURE_FILE) => {}
+            _ => files.push(path),
+        }
+    }
+
+    let stored_infos = client.files_info(&files, true, false).await?;
+    let netx = client.netx();
+    let server = impl_struct!(netx=>IFileStoreService);
+    let mut corrupted = vec![];
+    for (path, stored) in files.iter().zip(stored_infos) {
+        ensure!(!cancel.is_cancelled(), "scrub cancelled");
+        let actual = server.rehash(path).await;
+        let error = match (stored, actual) {
+            (_, Err(err)) => Some(format!("rehash error:{err}")),
+            (Err(err), _) => Some(format!("get file info error:{err}")),
+            (Ok(info), Ok(hash)) => {
+                if info.b3.as_deref() != Some(hash.as_str()) {
+                    Some(format!(
+                        "stored b3:{} actual b3:{hash}",
+                        info.b3.unwrap_or_default()
+                    ))
+                } else {
+                    match manifests.get(path) {
+                        Some(expected) if *expected != hash => {
+                            Some(format!("manifest b3:{expected} actual b3:{hash}"))
+                        }
+                        _ => None,
+                    }
+                }
+            }
+        };
+        log::debug!("scrub file:{} error:{error:?}", path.display());
+        if let Some(error) = error {
+            if !json && !quiet {
+                println!("{} {} {error}", style("corrupted").red(), path.display());
+            }
+            corrupted.push(Corrupted {
+                path: path.to_string_lossy().to_string(),
+                error,
+            });
+        }
+    }
+
+    let dir = dir.to_string_lossy();
+    let report = ScrubReport {
+        dir: &dir,
+        files: files.len(),
+        corrupted,
+    };
+    if json {
+        print_json(&report)?;
+    } else if !quiet {
+        println!(
+            "scrub {} files:{} corrupted:{}",
+            report.dir,
+            style(report.files).green(),
+            style(report.corrupted.len()).red()
+        );
+    }
+    ensure!(
+        report.corrupted.is_empty(),
+        "{} files corrupted",
+        report.corrupted.len()
+    );
+    Ok(())
+}
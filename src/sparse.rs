@@ -0,0 +1,48 @@
+use std::path::Path;
+
+/// the data ranges (start, end) of the sparse file by SEEK_DATA and SEEK_HOLE,
+/// none if the file has no hole or the file system not support
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[inline]
+pub fn data_ranges(path: &Path, size: u64) -> std::io::Result<Option<Vec<(u64, u64)>>> {
+    use std::os::unix::io::AsRawFd;
+    let file = std::fs::File::open(path)?;
+    let fd = file.as_raw_fd();
+    let mut ranges = vec![];
+    let mut position = 0;
+    while position < size {
+        // SAFETY: the fd is open until the file drop
+        let start = unsafe { libc::lseek(fd, position as libc::off_t, libc::SEEK_DATA) };
+        if start < 0 {
+            let err = std::io::Error::last_os_error();
+            match err.raw_os_error() {
+                // not data after the position, the file end with the hole
+                Some(libc::ENXIO) => break,
+                Some(libc::EINVAL) => return Ok(None),
+                _ => return Err(err),
+            }
+        }
+        // SAFETY: the fd is open until the file drop
+        let end = unsafe { libc::lseek(fd, start, libc::SEEK_HOLE) };
+        if end < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        ranges.push((start as u64, (end as u64).min(size)));
+        position = end as u64;
+    }
+    let data = ranges.iter().map(|(start, end)| end - start).sum::<u64>();
+    Ok((data < size).then_some(ranges))
+}
+
+/// the holes not detected on the platform
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+#[inline]
+pub fn data_ranges(_path: &Path, _size: u64) -> std::io::Result<Option<Vec<(u64, u64)>>> {
+    Ok(None)
+}
+
+/// the block is all zero, the pull seek over it to keep the hole
+#[inline]
+pub fn is_zero(data: &[u8]) -> bool {
+    data.iter().all(|&byte| byte == 0)
+}
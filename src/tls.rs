@@ -0,0 +1,389 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use netxclient::prelude::RustlsAcceptAnyCertVerifier;
+use rustls_pemfile::{certs, read_one, Item};
+use sha2::{Digest, Sha256};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio_rustls::rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use tokio_rustls::rustls::sign::any_supported_type;
+use tokio_rustls::rustls::version::TLS13;
+use tokio_rustls::rustls::{
+    Certificate, ClientConfig, PrivateKey, RootCertStore, ServerName, SignatureScheme,
+    SupportedCipherSuite, SupportedProtocolVersion, ALL_CIPHER_SUITES, DEFAULT_CIPHER_SUITES,
+    DEFAULT_VERSIONS,
+};
+use tokio_rustls::TlsConnector;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use crate::config::{get_current_exec_path, TlsConfig};
+
+/// if the file not exists in work directory, find it in current exec path
+#[inline]
+pub fn resolve_path(path: &Path) -> Result<PathBuf> {
+    if path.exists() {
+        return Ok(path.to_path_buf());
+    }
+    let mut current_exec_path = get_current_exec_path()?;
+    current_exec_path.push(path);
+    ensure!(
+        current_exec_path.exists(),
+        "not found file:{:?}",
+        current_exec_path
+    );
+    Ok(current_exec_path)
+}
+
+/// load pem certificates, the client cert file can include the intermediate certificates
+#[inline]
+pub fn load_certs(path: &Path) -> Result<Vec<Certificate>> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("open cert file:{} error", path.display()))?;
+    let certs = certs(&mut BufReader::new(file))
+        .with_context(|| format!("cert file:{} is not pem", path.display()))?;
+    ensure!(
+        !certs.is_empty(),
+        "cert file:{} not found certificate",
+        path.display()
+    );
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+/// load pem private key, support RSA, PKCS#8 and EC
+#[inline]
+pub fn load_private_key(path: &Path) -> Result<PrivateKey> {
+    let data =
+        std::fs::read(path).with_context(|| format!("open key file:{} error", path.display()))?;
+    let mut reader = BufReader::new(data.as_slice());
+    while let Some(item) =
+        read_one(&mut reader).with_context(|| format!("key file:{} is not pem", path.display()))?
+    {
+        match item {
+            Item::RSAKey(key) | Item::PKCS8Key(key) | Item::ECKey(key) => {
+                return Ok(PrivateKey(key))
+            }
+            _ => continue,
+        }
+    }
+
+    let text = String::from_utf8_lossy(&data);
+    if let Some(pem) = find_pem_section(&text, "ENCRYPTED PRIVATE KEY") {
+        return decrypt_private_key(path, pem)
+            .with_context(|| format!("key file:{} decrypt error", path.display()));
+    }
+
+    // name the pem sections found, so the user know which format is not supported
+    let sections = text
+        .lines()
+        .filter_map(|line| {
+            line.trim()
+                .strip_prefix("-----BEGIN ")
+                .and_then(|line| line.strip_suffix("-----"))
+                .map(|label| label.to_string())
+        })
+        .collect::<Vec<_>>();
+    if sections.is_empty() {
+        bail!("key file:{} not found private key", path.display())
+    } else {
+        bail!(
+            "key file:{} unsupported private key format:{}, supported: RSA, PKCS#8, EC, ENCRYPTED PKCS#8",
+            path.display(),
+            sections.join(",")
+        )
+    }
+}
+
+/// find pem section by label, return the section text include begin and end line
+#[inline]
+fn find_pem_section<'a>(text: &'a str, label: &str) -> Option<&'a str> {
+    let begin = text.find(&format!("-----BEGIN {label}-----"))?;
+    let end_line = format!("-----END {label}-----");
+    let end = text[begin..].find(&end_line)? + begin + end_line.len();
+    Some(&text[begin..end])
+}
+
+/// decrypt PKCS#8 encrypted private key,
+/// the passphrase read from env FS_KEY_PASSPHRASE, then os keyring, if not found prompt input
+#[inline]
+fn decrypt_private_key(path: &Path, pem: &str) -> Result<PrivateKey> {
+    let passphrase = match std::env::var("FS_KEY_PASSPHRASE") {
+        Ok(passphrase) => passphrase,
+        Err(_) => match crate::auth::keyring_get(&crate::auth::passphrase_entry(path)) {
+            Some(passphrase) => passphrase,
+            None => rpassword::prompt_password("private key passphrase: ")?,
+        },
+    };
+    crate::redact::add_secret(&passphrase);
+    let (_, document) = pkcs8::der::Document::from_pem(pem)
+        .map_err(|err| anyhow!("encrypted private key pem error:{err}"))?;
+    let key = pkcs8::EncryptedPrivateKeyInfo::try_from(document.as_bytes())
+        .map_err(|err| anyhow!("encrypted private key error:{err}"))?
+        .decrypt(passphrase)
+        .map_err(|_| anyhow!("decrypt private key fail, passphrase is wrong"))?;
+    Ok(PrivateKey(key.as_bytes().to_vec()))
+}
+
+/// check the private key can sign for the certificate public key
+#[inline]
+pub fn check_key_match(cert: &Certificate, key: &PrivateKey) -> Result<()> {
+    const SCHEMES: [SignatureScheme; 4] = [
+        SignatureScheme::ED25519,
+        SignatureScheme::ECDSA_NISTP256_SHA256,
+        SignatureScheme::ECDSA_NISTP384_SHA384,
+        SignatureScheme::RSA_PKCS1_SHA256,
+    ];
+    const MESSAGE: &[u8] = b"file store client key check";
+
+    let signer = any_supported_type(key)
+        .map_err(|_| anyhow!("private key type not supported"))?
+        .choose_scheme(&SCHEMES)
+        .context("private key signature scheme not supported")?;
+    let signature = signer.sign(MESSAGE)?;
+    let algorithm = match signer.scheme() {
+        SignatureScheme::ED25519 => &webpki::ED25519,
+        SignatureScheme::ECDSA_NISTP256_SHA256 => &webpki::ECDSA_P256_SHA256,
+        SignatureScheme::ECDSA_NISTP384_SHA384 => &webpki::ECDSA_P384_SHA384,
+        _ => &webpki::RSA_PKCS1_2048_8192_SHA256,
+    };
+    let cert = webpki::EndEntityCert::try_from(cert.0.as_slice())
+        .map_err(|err| anyhow!("certificate parse error:{err:?}"))?;
+    cert.verify_signature(algorithm, MESSAGE, &signature)
+        .map_err(|_| anyhow!("private key does not match certificate"))
+}
+
+/// check the client certificate chain and private key,
+/// the error tell which part is wrong, key, leaf or chain
+#[inline]
+pub fn check_client_cert(chain: &[Certificate], key: &PrivateKey) -> Result<()> {
+    any_supported_type(key).map_err(|_| {
+        anyhow!("private key error: the key is corrupt or not RSA/ECDSA/Ed25519 key")
+    })?;
+
+    let certs = chain
+        .iter()
+        .enumerate()
+        .map(|(index, cert)| {
+            X509Certificate::from_der(&cert.0)
+                .map(|(_, cert)| cert)
+                .map_err(|err| anyhow!("certificate #{index} parse error:{err}"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let leaf = certs.first().context("not found leaf certificate")?;
+    ensure!(
+        !leaf.is_ca(),
+        "leaf certificate error: the first certificate:{} is a ca, the leaf certificate must be first",
+        leaf.subject()
+    );
+    ensure!(
+        leaf.validity().is_valid(),
+        "leaf certificate error: {} not valid, valid time {} - {}",
+        leaf.subject(),
+        leaf.validity().not_before,
+        leaf.validity().not_after
+    );
+    check_key_match(&chain[0], key)
+        .with_context(|| format!("leaf certificate:{}", leaf.subject()))?;
+
+    for (index, pair) in certs.windows(2).enumerate() {
+        ensure!(
+            pair[0].issuer().as_raw() == pair[1].subject().as_raw(),
+            "certificate chain error: certificate #{} issuer:{} but certificate #{} subject:{}",
+            index,
+            pair[0].issuer(),
+            index + 1,
+            pair[1].subject()
+        );
+    }
+    Ok(())
+}
+
+/// load ca certificates to root store
+#[inline]
+pub fn load_root_store(path: &Path) -> Result<RootCertStore> {
+    let ca_certs = load_certs(path)?
+        .into_iter()
+        .map(|cert| cert.0)
+        .collect::<Vec<_>>();
+    let mut server_auth_roots = RootCertStore::empty();
+    let (valid, invalid) = server_auth_roots.add_parsable_certificates(&ca_certs);
+    ensure!(
+        valid > 0,
+        "ca file:{} not found valid certificate, invalid:{}",
+        path.display(),
+        invalid
+    );
+    Ok(server_auth_roots)
+}
+
+/// create server certificate verifier
+/// pinned fingerprint > ca > accept any
+#[inline]
+fn build_verifier(tls: &TlsConfig) -> Result<Arc<dyn ServerCertVerifier>> {
+    let roots = match tls.ca {
+        Some(ref ca) => Some(load_root_store(&resolve_path(ca)?)?),
+        None => None,
+    };
+    let verifier: Arc<dyn ServerCertVerifier> = match (tls.pinned_sha256.as_ref(), roots) {
+        (Some(fingerprint), roots) => Arc::new(PinnedCertVerifier::new(fingerprint, roots)?),
+        (None, Some(roots)) => Arc::new(WebPkiVerifier::new(roots, None)),
+        (None, None) => Arc::new(RustlsAcceptAnyCertVerifier),
+    };
+    Ok(verifier)
+}
+
+/// tls 1.3 only, used by min_version = "1.3"
+static TLS13_ONLY: &[&SupportedProtocolVersion] = &[&TLS13];
+
+/// tls protocol versions by min_version, default 1.2
+#[inline]
+fn protocol_versions(tls: &TlsConfig) -> Result<&'static [&'static SupportedProtocolVersion]> {
+    match tls.min_version.as_deref() {
+        None | Some("1.2") => Ok(DEFAULT_VERSIONS),
+        Some("1.3") => Ok(TLS13_ONLY),
+        Some(version) => bail!("tls min_version:{version} not support, support 1.2 and 1.3"),
+    }
+}
+
+/// tls cipher suites by name, default rustls safe cipher suites
+#[inline]
+fn cipher_suites(tls: &TlsConfig) -> Result<Vec<SupportedCipherSuite>> {
+    let Some(ref names) = tls.cipher_suites else {
+        return Ok(DEFAULT_CIPHER_SUITES.to_vec());
+    };
+    names
+        .iter()
+        .map(|name| {
+            ALL_CIPHER_SUITES
+                .iter()
+                .find(|suite| format!("{:?}", suite.suite()).eq_ignore_ascii_case(name))
+                .copied()
+                .with_context(|| {
+                    format!(
+                        "tls cipher suite:{} not support, support:{}",
+                        name,
+                        ALL_CIPHER_SUITES
+                            .iter()
+                            .map(|suite| format!("{:?}", suite.suite()))
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    )
+                })
+        })
+        .collect()
+}
+
+/// create tls client config
+/// if not set cert and key, not use client certificate auth
+#[inline]
+pub fn build_client_config(tls: &TlsConfig) -> Result<ClientConfig> {
+    let builder = ClientConfig::builder()
+        .with_cipher_suites(&cipher_suites(tls)?)
+        .with_safe_default_kx_groups()
+        .with_protocol_versions(protocol_versions(tls)?)
+        .context("tls min_version and cipher_suites have no common protocol")?
+        .with_custom_certificate_verifier(build_verifier(tls)?);
+
+    match (tls.cert.as_ref(), tls.key.as_ref()) {
+        (Some(cert), Some(key)) => {
+            let cert_chain = load_certs(&resolve_path(cert)?)?;
+            let key = load_private_key(&resolve_path(key)?)?;
+            check_client_cert(&cert_chain, &key)?;
+            builder
+                .with_client_auth_cert(cert_chain, key)
+                .context("bad certificate/key")
+        }
+        (None, None) => Ok(builder.with_no_client_auth()),
+        (Some(_), None) => bail!("tls cert is set, but key not set"),
+        (None, Some(_)) => bail!("tls key is set, but cert not set"),
+    }
+}
+
+/// certificate sha256 fingerprint hex
+#[inline]
+pub fn fingerprint(cert: &Certificate) -> String {
+    hex::encode(Sha256::digest(&cert.0))
+}
+
+/// accept the server certificate only if the sha256 fingerprint matches,
+/// if ca is set, the certificate must also be verified by ca
+pub struct PinnedCertVerifier {
+    fingerprint: String,
+    webpki: Option<WebPkiVerifier>,
+}
+
+impl PinnedCertVerifier {
+    /// fingerprint is hex, allow ':' separator, example: AB:CD:...
+    #[inline]
+    pub fn new(fingerprint: &str, roots: Option<RootCertStore>) -> Result<Self> {
+        let fingerprint = fingerprint.replace(':', "").to_lowercase();
+        ensure!(
+            fingerprint.len() == 64 && hex::decode(&fingerprint).is_ok(),
+            "pinned_sha256:{} is not sha256 hex",
+            fingerprint
+        );
+        Ok(Self {
+            fingerprint,
+            webpki: roots.map(|roots| WebPkiVerifier::new(roots, None)),
+        })
+    }
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    #[inline]
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        if let Some(ref webpki) = self.webpki {
+            webpki.verify_server_cert(
+                end_entity,
+                intermediates,
+                server_name,
+                scts,
+                ocsp_response,
+                now,
+            )?;
+        }
+        let fingerprint = fingerprint(end_entity);
+        if fingerprint == self.fingerprint {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            log::error!(
+                "server certificate sha256:{} not match pinned:{}",
+                fingerprint,
+                self.fingerprint
+            );
+            Err(tokio_rustls::rustls::Error::General(
+                "server certificate fingerprint mismatch".to_string(),
+            ))
+        }
+    }
+}
+
+/// create tls connector
+#[inline]
+pub fn build_connector(tls: &TlsConfig) -> Result<TlsConnector> {
+    Ok(TlsConnector::from(Arc::new(build_client_config(tls)?)))
+}
+
+/// get tls server name,
+/// use tls.server_name if set, otherwise the server addr host
+#[inline]
+pub fn server_name(tls: &TlsConfig, addr: &str) -> Result<ServerName> {
+    if let Some(ref server_name) = tls.server_name {
+        return ServerName::try_from(server_name.as_str())
+            .with_context(|| format!("tls server_name:{server_name} error"));
+    }
+    let host = match addr.rsplit_once(':') {
+        Some((host, port)) if port.parse::<u16>().is_ok() => host,
+        _ => addr,
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    ServerName::try_from(host).with_context(|| format!("server name:{host} error"))
+}
@@ -0,0 +1,238 @@
+use anyhow::{ensure, Context, Result};
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::client::TransferEvent;
+
+/// the max workers of the auto tune
+pub const MAX_JOBS: usize = 16;
+/// the start workers of the auto tune
+const START_JOBS: usize = 2;
+/// the throughput and latency measure window of the auto tune
+const WINDOW: Duration = Duration::from_secs(3);
+/// re-evaluate the workers if no transfer finished
+const TICK: Duration = Duration::from_millis(500);
+
+/// the concurrent file transfers, fixed or auto tuned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Jobs {
+    Fixed(usize),
+    /// adjust the workers by the observed throughput and the block latency
+    Auto,
+}
+
+impl Default for Jobs {
+    #[inline]
+    fn default() -> Self {
+        Jobs::Fixed(1)
+    }
+}
+
+impl FromStr for Jobs {
+    type Err = anyhow::Error;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(Jobs::Auto);
+        }
+        let jobs = s
+            .parse::<usize>()
+            .with_context(|| format!("jobs:{s} error, a number or auto"))?;
+        ensure!(jobs > 0, "jobs must be larger than 0");
+        Ok(Jobs::Fixed(jobs))
+    }
+}
+
+/// the config jobs, a number or "auto"
+impl<'de> Deserialize<'de> for Jobs {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Value {
+            Number(usize),
+            Text(String),
+        }
+        match Value::deserialize(deserializer)? {
+            Value::Number(jobs) => jobs.to_string().parse(),
+            Value::Text(jobs) => jobs.parse(),
+        }
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+impl std::fmt::Display for Jobs {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Jobs::Fixed(jobs) => write!(f, "{jobs}"),
+            Jobs::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+/// the measure of the current window
+struct TuneState {
+    workers: usize,
+    start: Instant,
+    /// the transferred bytes of the window
+    bytes: u64,
+    /// the sum and count of the block latency of the window
+    latency: Duration,
+    samples: u32,
+    /// the last position and event time of the transferring files,
+    /// the block latency is the interval of the progress events
+    files: HashMap<String, (u64, Instant)>,
+    /// the throughput and the average latency of the last window
+    last: Option<(f64, Duration)>,
+    /// the last change is grow or shrink
+    grow: bool,
+}
+
+/// the concurrency of the file transfers, the auto tune climb the workers
+/// while the throughput increase, and back off if the throughput drop or the latency rise
+pub struct Concurrency {
+    jobs: Jobs,
+    state: Mutex<TuneState>,
+}
+
+impl Concurrency {
+    #[inline]
+    pub fn new(jobs: Jobs) -> Self {
+        Self {
+            jobs,
+            state: Mutex::new(TuneState {
+                workers: START_JOBS,
+                start: Instant::now(),
+                bytes: 0,
+                latency: Duration::ZERO,
+                samples: 0,
+                files: HashMap::new(),
+                last: None,
+                grow: true,
+            }),
+        }
+    }
+
+    /// the current workers, the auto tune re-evaluate at the end of the window
+    #[inline]
+    pub fn workers(&self) -> usize {
+        match self.jobs {
+            Jobs::Fixed(jobs) => jobs.max(1),
+            Jobs::Auto => self.tune(),
+        }
+    }
+
+    /// measure the transfer event of the auto tune
+    #[inline]
+    pub fn event(&self, event: &TransferEvent) {
+        if self.jobs != Jobs::Auto {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        match *event {
+            TransferEvent::Start { file, .. } => {
+                state.files.insert(file.to_string(), (0, Instant::now()));
+            }
+            TransferEvent::Progress { file, position, .. } => {
+                let now = Instant::now();
+                if let Some((last, time)) = state.files.insert(file.to_string(), (position, now)) {
+                    if position > last {
+                        state.bytes += position - last;
+                        state.latency += now - time;
+                        state.samples += 1;
+                    }
+                }
+            }
+            TransferEvent::Finish { file, .. } => {
+                state.files.remove(file);
+            }
+            _ => {}
+        }
+    }
+
+    /// the hill climbing of the workers by the last window
+    #[inline]
+    fn tune(&self) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let elapsed = state.start.elapsed();
+        if elapsed < WINDOW {
+            return state.workers;
+        }
+        if state.bytes > 0 && state.samples > 0 {
+            let throughput = state.bytes as f64 / elapsed.as_secs_f64();
+            let latency = state.latency / state.samples;
+            // grow while the throughput increase, reverse if worse, hold if flat
+            let grow = match state.last {
+                None => Some(true),
+                Some((last_throughput, _)) if throughput > last_throughput * 1.05 => {
+                    Some(state.grow)
+                }
+                Some((last_throughput, last_latency))
+                    if throughput < last_throughput * 0.95
+                        || latency > last_latency.mul_f64(1.5) =>
+                {
+                    Some(!state.grow)
+                }
+                Some(_) => None,
+            };
+            let workers = match grow {
+                Some(true) => (state.workers + 1).min(MAX_JOBS),
+                Some(false) => state.workers.saturating_sub(1).max(1),
+                None => state.workers,
+            };
+            if let Some(grow) = grow {
+                state.grow = grow;
+            }
+            log::debug!(
+                "auto jobs:{} -> {workers} throughput:{:.0}/s latency:{}ms",
+                state.workers,
+                throughput,
+                latency.as_millis()
+            );
+            state.workers = workers;
+            state.last = Some((throughput, latency));
+        }
+        state.start = Instant::now();
+        state.bytes = 0;
+        state.latency = Duration::ZERO;
+        state.samples = 0;
+        state.workers
+    }
+
+    /// run the items concurrently by the workers, abort at the first error,
+    /// return the results in the finish order
+    #[inline]
+    pub async fn run<T, R, F, Fut>(&self, items: Vec<T>, mut run: F) -> Result<Vec<R>>
+    where
+        F: FnMut(T) -> Fut,
+        Fut: Future<Output = Result<R>>,
+    {
+        let mut items = items.into_iter();
+        let mut tasks = FuturesUnordered::new();
+        let mut results = vec![];
+        let mut tick = tokio::time::interval(TICK);
+        loop {
+            while tasks.len() < self.workers() {
+                match items.next() {
+                    Some(item) => tasks.push(run(item)),
+                    None => break,
+                }
+            }
+            if tasks.is_empty() {
+                break;
+            }
+            tokio::select! {
+                Some(result) = tasks.next() => results.push(result?),
+                _ = tick.tick() => {}
+            }
+        }
+        Ok(results)
+    }
+}
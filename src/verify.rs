@@ -0,0 +1,124 @@
+use anyhow::{ensure, Context, Result};
+use console::style;
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use std::path::Path;
+
+use fsc::checksum::{self, HashAlgorithm};
+use fsc::output::print_json;
+
+/// the file not match the checksum file
+#[derive(Serialize)]
+struct Failed {
+    path: String,
+    error: String,
+}
+
+/// the verify report
+#[derive(Serialize)]
+struct VerifyReport<'a> {
+    manifest: &'a str,
+    algorithm: HashAlgorithm,
+    files: usize,
+    mismatched: Vec<Failed>,
+    missing: Vec<String>,
+}
+
+/// hash the local files of the root concurrently, compare with the checksum file
+#[inline]
+pub async fn verify(
+    manifest: &Path,
+    root: &Path,
+    hash: Option<HashAlgorithm>,
+    jobs: Option<usize>,
+    json: bool,
+    quiet: bool,
+) -> Result<()> {
+    let text = std::fs::read_to_string(manifest)
+        .with_context(|| format!("read checksum file:{} error", manifest.display()))?;
+    let checksums = checksum::parse_checksum_file(&text)?;
+    ensure!(
+        !checksums.is_empty(),
+        "checksum file:{} is empty",
+        manifest.display()
+    );
+    let algorithm = match hash {
+        Some(algorithm) => algorithm,
+        None => checksum::guess_algorithm(manifest, &checksums[0].0)?,
+    };
+    let jobs = jobs
+        .or_else(|| std::thread::available_parallelism().ok().map(Into::into))
+        .unwrap_or(1)
+        .max(1);
+    log::debug!(
+        "verify {} files of {} by {algorithm} jobs:{jobs}",
+        checksums.len(),
+        manifest.display()
+    );
+
+    let results = stream::iter(checksums.iter())
+        .map(|(expected, path)| async move {
+            let file = root.join(path);
+            if !file.is_file() {
+                return (path, None);
+            }
+            let result = checksum::file_checksum(&file, algorithm).await;
+            (path, Some(result.map(|actual| (expected, actual))))
+        })
+        .buffer_unordered(jobs)
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut mismatched = vec![];
+    let mut missing = vec![];
+    for (path, result) in results {
+        let path = path.to_string_lossy().to_string();
+        let error = match result {
+            None => {
+                if !json && !quiet {
+                    println!("{} {path}", style("missing").red());
+                }
+                missing.push(path);
+                continue;
+            }
+            Some(Err(err)) => format!("{err:#}"),
+            Some(Ok((expected, actual))) if *expected != actual => {
+                format!("expected {algorithm}:{expected} actual {algorithm}:{actual}")
+            }
+            Some(Ok(_)) => continue,
+        };
+        if !json && !quiet {
+            println!("{} {path} {error}", style("mismatched").red());
+        }
+        mismatched.push(Failed { path, error });
+    }
+    mismatched.sort_by(|a, b| a.path.cmp(&b.path));
+    missing.sort();
+
+    let manifest = manifest.to_string_lossy();
+    let report = VerifyReport {
+        manifest: &manifest,
+        algorithm,
+        files: checksums.len(),
+        mismatched,
+        missing,
+    };
+    if json {
+        print_json(&report)?;
+    } else if !quiet {
+        println!(
+            "verify {} files:{} mismatched:{} missing:{}",
+            report.manifest,
+            style(report.files).green(),
+            style(report.mismatched.len()).red(),
+            style(report.missing.len()).red()
+        );
+    }
+    ensure!(
+        report.mismatched.is_empty() && report.missing.is_empty(),
+        "{} files mismatched, {} files missing",
+        report.mismatched.len(),
+        report.missing.len()
+    );
+    Ok(())
+}
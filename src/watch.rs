@@ -0,0 +1,194 @@
+use anyhow::{ensure, Result};
+use netxclient::prelude::*;
+use notify_debouncer_mini::notify::RecursiveMode;
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::fs::File;
+
+use fsc::checksum::{file_checksum, HashAlgorithm};
+use fsc::client::{computer_b3, TransferOptions};
+use fsc::interface_server::*;
+use fsc::output::TransferSummary;
+use fsc::FileStoreClient;
+
+/// local dir watcher, push the new and changed files
+struct Watcher<'a> {
+    client: &'a FileStoreClient,
+    local: PathBuf,
+    remote_dir: Option<PathBuf>,
+    options: TransferOptions,
+    /// compare the xxh3 instead of the blake3
+    fast_verify: bool,
+    quiet: bool,
+    /// the pushed file hash, skip the unchanged file
+    hashes: HashMap<PathBuf, String>,
+}
+
+impl Watcher<'_> {
+    /// remote file name of the local file
+    #[inline]
+    fn remote_path(&self, file: &Path) -> Option<String> {
+        let relative = file.strip_prefix(&self.local).ok()?;
+        let remote = match self.remote_dir {
+            Some(ref dir) => dir.join(relative),
+            None => relative.to_path_buf(),
+        };
+        Some(remote.to_string_lossy().replace('\\', "/"))
+    }
+
+    /// push the file if changed, compare the hash with the last pushed and the remote
+    #[inline]
+    async fn push(&mut self, file: &Path) -> Result<()> {
+        let Some(remote) = self.remote_path(file) else {
+            return Ok(());
+        };
+        let hash = if self.fast_verify {
+            file_checksum(file, HashAlgorithm::Xxh3).await?
+        } else {
+            computer_b3(&mut File::open(file).await?).await
+        };
+        if self.hashes.get(file) == Some(&hash) {
+            log::debug!("skip unchanged file:{}", file.display());
+            return Ok(());
+        }
+        let remote_hash = remote_hash(self.client, Path::new(&remote), self.fast_verify).await;
+        if remote_hash.as_ref() != Some(&hash) {
+            let start = Instant::now();
+            let size = self
+                .client
+                .push_file(file, &remote, self.options.clone())
+                .await?;
+            crate::metrics::transfer("watch push", size, start.elapsed());
+            let mut summary =
+                TransferSummary::new("watch push", file.display().to_string(), remote.clone())
+                    .with_start(start);
+            if !self.fast_verify {
+                summary.hash = Some(hash.clone());
+            }
+            crate::history::record(&summary.finish(1, size));
+            log::info!("push file:{} -> {remote} size:{size}", file.display());
+            if !self.quiet {
+                println!("push {} -> {remote}", file.display());
+            }
+        } else {
+            crate::metrics::skipped("watch push");
+            log::debug!("skip unchanged remote file:{remote}");
+        }
+        self.hashes.insert(file.to_path_buf(), hash);
+        Ok(())
+    }
+
+    /// push the file, log the error and continue
+    #[inline]
+    async fn push_or_log(&mut self, file: &Path) {
+        let start = Instant::now();
+        if let Err(err) = self.push(file).await {
+            crate::metrics::failed("watch push");
+            let remote = self.remote_path(file).unwrap_or_default();
+            let summary = TransferSummary::new("watch push", file.display().to_string(), remote)
+                .with_start(start)
+                .fail(1, format!("{err:#}"));
+            crate::history::record(&summary);
+            log::error!("watch push file:{} error:{err:#}", file.display());
+        }
+    }
+}
+
+/// the remote file blake3, or the xxh3 if fast verify, none if not found
+#[inline]
+async fn remote_hash(client: &FileStoreClient, remote: &Path, fast_verify: bool) -> Option<String> {
+    let start = Instant::now();
+    if fast_verify {
+        let checksums = client.checksums(remote, &[HashAlgorithm::Xxh3]).await;
+        crate::metrics::rpc("get_file_checksums", start.elapsed());
+        return checksums
+            .ok()
+            .and_then(|checksums| checksums.into_iter().next())
+            .map(|(_, hex)| hex);
+    }
+    let client = client.netx();
+    let server = impl_struct!(client=>IFileStoreService);
+    let info = server.get_file_info(remote, true, false).await;
+    crate::metrics::rpc("get_file_info", start.elapsed());
+    info.ok().and_then(|info| info.b3)
+}
+
+/// collect the files of the dir
+#[inline]
+pub fn visit_dirs(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            visit_dirs(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// push the changed files of the local dir, then watch and push the new and changed files,
+/// the events are debounced, until cancelled
+#[inline]
+pub async fn watch(
+    client: &FileStoreClient,
+    local: PathBuf,
+    remote_dir: Option<PathBuf>,
+    options: TransferOptions,
+    debounce: Duration,
+    fast_verify: bool,
+    quiet: bool,
+) -> Result<()> {
+    ensure!(local.is_dir(), "path:{} not dir", local.display());
+    let local = local.canonicalize()?;
+    let cancel = options.cancel.clone();
+    let mut watcher = Watcher {
+        client,
+        local,
+        remote_dir,
+        options,
+        fast_verify,
+        quiet,
+        hashes: HashMap::new(),
+    };
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut debouncer = new_debouncer(debounce, move |result: DebounceEventResult| {
+        let _ = tx.send(result);
+    })?;
+    debouncer
+        .watcher()
+        .watch(&watcher.local, RecursiveMode::Recursive)?;
+    log::info!("start watch dir:{}", watcher.local.display());
+
+    let mut files = vec![];
+    visit_dirs(&watcher.local, &mut files)?;
+    for file in files {
+        if cancel.is_cancelled() {
+            break;
+        }
+        watcher.push_or_log(&file).await;
+    }
+
+    loop {
+        let result = tokio::select! {
+            result = rx.recv() => result,
+            _ = cancel.cancelled() => None,
+        };
+        match result {
+            Some(Ok(events)) => {
+                for event in events {
+                    if event.path.is_file() {
+                        watcher.push_or_log(&event.path).await;
+                    }
+                }
+            }
+            Some(Err(err)) => log::error!("watch dir error:{err}"),
+            None => break,
+        }
+    }
+    log::info!("stop watch dir:{}", watcher.local.display());
+    Ok(())
+}
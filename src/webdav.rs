@@ -0,0 +1,271 @@
+use bytes::{Buf, Bytes};
+use dav_server::davpath::DavPath;
+use dav_server::fakels::FakeLs;
+use dav_server::fs::{
+    DavDirEntry, DavFile, DavFileSystem, DavMetaData, FsError, FsFuture, FsResult, FsStream,
+    OpenOptions, ReadDirMeta,
+};
+use dav_server::DavHandler;
+use futures::FutureExt;
+use hyper::service::{make_service_fn, service_fn};
+use std::convert::Infallible;
+use std::io::SeekFrom;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::Mutex;
+
+use fsc::interface_server::Entry;
+use fsc::io::RemoteReader;
+use fsc::FileStoreClient;
+
+/// read only webdav file system of the remote dir
+#[derive(Clone)]
+struct RemoteFs {
+    client: Arc<FileStoreClient>,
+    root: PathBuf,
+}
+
+impl RemoteFs {
+    /// remote path of the relative path in root
+    #[inline]
+    fn remote_path(&self, path: &Path) -> PathBuf {
+        PathBuf::from(self.root.join(path).to_string_lossy().replace('\\', "/"))
+    }
+
+    /// find the entry in the parent dir, the root is dir
+    #[inline]
+    async fn entry_metadata(&self, path: &DavPath) -> FsResult<RemoteMeta> {
+        let path = path.as_rel_ospath();
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            return Ok(RemoteMeta {
+                dir: true,
+                size: 0,
+                time: SystemTime::now(),
+            });
+        };
+        self.client
+            .list(self.remote_path(parent))
+            .await
+            .map_err(fs_error)?
+            .iter()
+            .find(|entry| name == entry.name.as_str())
+            .map(RemoteMeta::from)
+            .ok_or(FsError::NotFound)
+    }
+}
+
+impl DavFileSystem for RemoteFs {
+    fn open<'a>(
+        &'a self,
+        path: &'a DavPath,
+        options: OpenOptions,
+    ) -> FsFuture<'a, Box<dyn DavFile>> {
+        async move {
+            if options.write || options.append || options.truncate || options.create {
+                return Err(FsError::Forbidden);
+            }
+            let meta = self.entry_metadata(path).await?;
+            if meta.dir {
+                return Err(FsError::Forbidden);
+            }
+            let reader = self
+                .client
+                .open(self.remote_path(path.as_rel_ospath()))
+                .await
+                .map_err(fs_error)?;
+            Ok(Box::new(RemoteFile {
+                reader: Mutex::new(reader),
+                meta,
+            }) as Box<dyn DavFile>)
+        }
+        .boxed()
+    }
+
+    fn read_dir<'a>(
+        &'a self,
+        path: &'a DavPath,
+        _meta: ReadDirMeta,
+    ) -> FsFuture<'a, FsStream<Box<dyn DavDirEntry>>> {
+        async move {
+            let entries = self
+                .client
+                .list(self.remote_path(path.as_rel_ospath()))
+                .await
+                .map_err(fs_error)?;
+            let entries = futures::stream::iter(
+                entries
+                    .into_iter()
+                    .map(|entry| Box::new(RemoteDirEntry(entry)) as Box<dyn DavDirEntry>),
+            );
+            Ok(Box::pin(entries) as FsStream<Box<dyn DavDirEntry>>)
+        }
+        .boxed()
+    }
+
+    fn metadata<'a>(&'a self, path: &'a DavPath) -> FsFuture<'a, Box<dyn DavMetaData>> {
+        async move { Ok(Box::new(self.entry_metadata(path).await?) as Box<dyn DavMetaData>) }
+            .boxed()
+    }
+}
+
+/// remote entry metadata
+#[derive(Debug, Clone)]
+struct RemoteMeta {
+    dir: bool,
+    size: u64,
+    time: SystemTime,
+}
+
+impl From<&Entry> for RemoteMeta {
+    #[inline]
+    fn from(entry: &Entry) -> Self {
+        Self {
+            dir: entry.file_type == 1,
+            size: entry.size,
+            time: entry.modified(),
+        }
+    }
+}
+
+impl DavMetaData for RemoteMeta {
+    #[inline]
+    fn len(&self) -> u64 {
+        self.size
+    }
+
+    #[inline]
+    fn modified(&self) -> FsResult<SystemTime> {
+        Ok(self.time)
+    }
+
+    #[inline]
+    fn created(&self) -> FsResult<SystemTime> {
+        Ok(self.time)
+    }
+
+    #[inline]
+    fn is_dir(&self) -> bool {
+        self.dir
+    }
+}
+
+/// remote dir entry
+struct RemoteDirEntry(Entry);
+
+impl DavDirEntry for RemoteDirEntry {
+    #[inline]
+    fn name(&self) -> Vec<u8> {
+        self.0.name.as_bytes().to_vec()
+    }
+
+    #[inline]
+    fn metadata(&self) -> FsFuture<'_, Box<dyn DavMetaData>> {
+        let meta = RemoteMeta::from(&self.0);
+        async move { Ok(Box::new(meta) as Box<dyn DavMetaData>) }.boxed()
+    }
+}
+
+/// remote file opened for read
+struct RemoteFile {
+    reader: Mutex<RemoteReader>,
+    meta: RemoteMeta,
+}
+
+impl std::fmt::Debug for RemoteFile {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemoteFile")
+            .field("meta", &self.meta)
+            .finish()
+    }
+}
+
+impl DavFile for RemoteFile {
+    #[inline]
+    fn metadata(&mut self) -> FsFuture<'_, Box<dyn DavMetaData>> {
+        let meta = self.meta.clone();
+        async move { Ok(Box::new(meta) as Box<dyn DavMetaData>) }.boxed()
+    }
+
+    #[inline]
+    fn write_buf(&mut self, _buf: Box<dyn Buf + Send>) -> FsFuture<'_, ()> {
+        async { Err(FsError::Forbidden) }.boxed()
+    }
+
+    #[inline]
+    fn write_bytes(&mut self, _buf: Bytes) -> FsFuture<'_, ()> {
+        async { Err(FsError::Forbidden) }.boxed()
+    }
+
+    #[inline]
+    fn read_bytes(&mut self, count: usize) -> FsFuture<'_, Bytes> {
+        async move {
+            let reader = self.reader.get_mut();
+            let mut buff = vec![0; count];
+            let mut len = 0;
+            while len < count {
+                match reader.read(&mut buff[len..]).await {
+                    Ok(0) => break,
+                    Ok(size) => len += size,
+                    Err(err) => {
+                        log::error!("webdav read error:{err}");
+                        return Err(FsError::GeneralFailure);
+                    }
+                }
+            }
+            buff.truncate(len);
+            Ok(Bytes::from(buff))
+        }
+        .boxed()
+    }
+
+    #[inline]
+    fn seek(&mut self, pos: SeekFrom) -> FsFuture<'_, u64> {
+        async move {
+            self.reader.get_mut().seek(pos).await.map_err(|err| {
+                log::error!("webdav seek error:{err}");
+                FsError::GeneralFailure
+            })
+        }
+        .boxed()
+    }
+
+    #[inline]
+    fn flush(&mut self) -> FsFuture<'_, ()> {
+        async { Ok(()) }.boxed()
+    }
+}
+
+#[inline]
+fn fs_error(err: anyhow::Error) -> FsError {
+    log::error!("webdav error:{err}");
+    FsError::GeneralFailure
+}
+
+/// serve the remote dir over webdav, read only
+#[inline]
+pub async fn serve(
+    client: Arc<FileStoreClient>,
+    root: PathBuf,
+    addr: SocketAddr,
+) -> anyhow::Result<()> {
+    let handler = DavHandler::builder()
+        .filesystem(Box::new(RemoteFs { client, root }))
+        .locksystem(FakeLs::new())
+        .build_handler();
+    let make_service = make_service_fn(move |_| {
+        let handler = handler.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let handler = handler.clone();
+                async move { Ok::<_, Infallible>(handler.handle(req).await) }
+            }))
+        }
+    });
+    log::info!("webdav gateway listen on http://{addr}");
+    hyper::Server::try_bind(&addr)?.serve(make_service).await?;
+    Ok(())
+}